@@ -1,4 +1,4 @@
-use crate::video::VideoError;
+use crate::{input::Action, video::VideoError};
 use std::sync::Arc;
 use thiserror::Error;
 use winit::{
@@ -6,46 +6,121 @@ use winit::{
     error::{EventLoopError, OsError},
     event,
     event::WindowEvent,
-    keyboard::{KeyCode, PhysicalKey},
+    window::Fullscreen,
 };
 
 /// Game application main entry point.
 pub async fn launch() -> Result<(), LaunchError> {
     env_logger::init();
 
+    let args = crate::cli::Args::parse(std::env::args().skip(1))?;
+    if args.list_adapters {
+        for info in crate::video::list_adapters() {
+            println!(
+                "{:?}: {} ({:?}, {} {})",
+                info.backend, info.name, info.device_type, info.driver, info.driver_info
+            );
+        }
+        return Ok(());
+    }
+
     let event_loop = winit::event_loop::EventLoop::new()?;
     event_loop.set_control_flow(winit::event_loop::ControlFlow::Poll);
 
+    let config_path = crate::config::Config::path();
+    let mut config = crate::config::Config::load(&config_path)?;
+
     let window = Arc::new(
         winit::window::WindowBuilder::new()
-            .with_inner_size(PhysicalSize::new(1920, 1280))
-            .with_position(PhysicalPosition::new(25, 25))
+            .with_inner_size(PhysicalSize::new(config.window.width, config.window.height))
+            .with_position(PhysicalPosition::new(config.window.x, config.window.y))
+            .with_fullscreen(fullscreen(config.window.mode))
             .with_title("roguelike 🔮🧝🏻🪄")
             .build(&event_loop)?,
     );
 
-    let mut video = crate::video::Video::new(Arc::clone(&window)).await?;
+    let graphics = args.graphics(&config.graphics);
+    let mut video = crate::video::Video::new(Arc::clone(&window), &graphics).await?;
     let mut surface_ready = false;
 
-    let mut input = crate::input::Input::new();
-    let mut scene = crate::scene::Scene::new(&video)?;
-    scene.observer.handle_resize(window.inner_size().into());
+    let mut input = new_input(&config);
+    let mut gamepads = crate::gamepad::Gamepads::new(&config.gamepad);
+    let mut idle_input = crate::input::Input::new();
+    let level_id = args
+        .level_id
+        .or_else(|| std::env::var(LEVEL_ENV_VAR).ok())
+        .unwrap_or_else(|| crate::level::DEFAULT_LEVEL_ID.to_string());
+    let mut scene = new_scene(&level_id, &config, &window)?;
+    let mut console = crate::console::Console::new();
+    let mut menu = crate::menu::Menu::new();
+
+    // fixed timestep: the simulation catches up with the real time in fixed size ticks,
+    // and the rendering interpolates between the last two ticks
+    let mut last_frame = std::time::Instant::now();
+    let mut accumulator = 0.0;
 
     event_loop.run(move |event, control_flow| match event {
         event::Event::WindowEvent { ref event, window_id } if window_id == window.id() => {
             match event {
-                WindowEvent::CloseRequested
-                | WindowEvent::KeyboardInput {
-                    event:
-                        event::KeyEvent {
-                            state: event::ElementState::Pressed,
-                            physical_key: PhysicalKey::Code(KeyCode::Escape),
-                            ..
-                        },
+                // the console takes over the keyboard while it's open
+                WindowEvent::KeyboardInput { event, .. } if console.open => {
+                    let toggle = crate::input::pressed_key(event)
+                        .is_some_and(|it| input.bindings.is_bound(Action::ToggleConsole, it));
+                    if toggle {
+                        console.toggle();
+                    } else {
+                        console.handle_key_event(event, &mut scene);
+                    }
+                }
+
+                WindowEvent::KeyboardInput { event, .. } if !menu.is_playing() => {
+                    if let Some(action) = menu.handle_key_event(event, &mut config) {
+                        if apply_menu_action(
+                            action,
+                            &mut scene,
+                            &level_id,
+                            &config,
+                            &config_path,
+                            &window,
+                        ) {
+                            control_flow.exit();
+                        }
+                    }
+                    // start with all the keys released, using the current bindings
+                    input = new_input(&config);
+                }
+
+                WindowEvent::KeyboardInput { event, .. } => {
+                    input.handle_key_event(event);
+                    handle_toggles(&mut input, &mut console, &mut menu, &mut scene, &config);
+                }
+
+                WindowEvent::CursorMoved { position, .. } => {
+                    scene.cursor.position =
+                        Some(cgmath::Point2::new(position.x as f32, position.y as f32));
+                }
+
+                WindowEvent::CursorLeft { .. } => scene.cursor.position = None,
+
+                WindowEvent::MouseWheel { delta, .. } if menu.is_playing() && !console.open => {
+                    let steps = match delta {
+                        event::MouseScrollDelta::LineDelta(_, y) => *y,
+                        event::MouseScrollDelta::PixelDelta(it) => {
+                            it.y as f32 / PIXELS_PER_WHEEL_STEP
+                        }
+                    };
+                    scene.observer.zoom(steps);
+                }
+
+                WindowEvent::MouseInput {
+                    state: event::ElementState::Pressed,
+                    button: event::MouseButton::Left,
                     ..
-                } => control_flow.exit(),
+                } if menu.is_playing() && !console.open => {
+                    scene.walk_to_cursor();
+                }
 
-                WindowEvent::KeyboardInput { event, .. } => input.handle_key_event(event),
+                WindowEvent::CloseRequested => control_flow.exit(),
 
                 WindowEvent::Resized(physical_size) => {
                     video.handle_resize(*physical_size);
@@ -54,15 +129,63 @@ pub async fn launch() -> Result<(), LaunchError> {
                 }
 
                 WindowEvent::RedrawRequested => {
-                    // artificially slow down / cap frame rate
-                    std::thread::sleep(std::time::Duration::new(0, FRAME_DELAY_NSECS));
-                    scene.advance(&video, &input);
+                    // the gamepad buttons navigate the menus like the matching keys
+                    for button in gamepads.poll(&mut input) {
+                        if menu.is_playing() || console.open {
+                            continue;
+                        }
+                        let action = button
+                            .menu_key()
+                            .and_then(|key_code| menu.handle_key(key_code, &mut config));
+                        if let Some(action) = action {
+                            if apply_menu_action(
+                                action,
+                                &mut scene,
+                                &level_id,
+                                &config,
+                                &config_path,
+                                &window,
+                            ) {
+                                control_flow.exit();
+                            }
+                        }
+                        // the presses went to the menu, not to the game
+                        input.end_tick();
+                    }
+                    handle_toggles(&mut input, &mut console, &mut menu, &mut scene, &config);
+
+                    let now = std::time::Instant::now();
+                    accumulator += (now - last_frame).as_secs_f32().min(MAX_FRAME_SECS);
+                    last_frame = now;
+                    while accumulator >= crate::scene::TICK_SECS {
+                        match menu.state {
+                            crate::menu::GameState::Playing => scene.advance(&mut input),
+                            state if state.is_simulated() => scene.advance(&mut idle_input),
+                            _ => {}
+                        }
+                        accumulator -= crate::scene::TICK_SECS;
+                    }
+                    menu.update(scene.player.is_alive());
 
                     window.request_redraw();
                     if !surface_ready {
                         return;
                     }
 
+                    scene.hud.enabled = menu.is_playing();
+                    scene.hud.dimmed = !menu.is_playing();
+                    scene.cursor.enabled = menu.is_playing() && !console.open;
+                    scene.build_hud();
+                    menu.draw(&mut scene.text, &config, scene.observer.screen_size());
+                    console.draw(&mut scene.text);
+
+                    let alpha = accumulator / crate::scene::TICK_SECS;
+                    if let Err(e) = scene.prepare_render(&video, alpha) {
+                        log::error!("cannot prepare scene for rendering: {}", e);
+                        control_flow.exit();
+                        return;
+                    }
+
                     match video.render(&scene) {
                         Ok(_) => {}
                         Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
@@ -89,7 +212,84 @@ pub async fn launch() -> Result<(), LaunchError> {
     Ok(())
 }
 
-const FRAME_DELAY_NSECS: u32 = 1_000_000_000 / 60; // 1_000 msecs / 60
+/// Touchpads scroll by pixels, this many of them make a single zoom step of the mouse wheel.
+const PIXELS_PER_WHEEL_STEP: f32 = 40.0;
+/// Longest frame time to be simulated, so that a long stall doesn't spiral into more ticks.
+const MAX_FRAME_SECS: f32 = 0.25;
+const LEVEL_ENV_VAR: &str = "ROGUELIKE_LEVEL";
+
+/// Creates a new input with all the keys and buttons released, using the configured bindings.
+fn new_input(config: &crate::config::Config) -> crate::input::Input {
+    crate::input::Input::with_bindings(config.keys.clone(), config.gamepad.buttons.clone())
+}
+
+/// Handle the toggle actions right away, they don't wait for a simulation tick.
+fn handle_toggles(
+    input: &mut crate::input::Input,
+    console: &mut crate::console::Console,
+    menu: &mut crate::menu::Menu,
+    scene: &mut crate::scene::Scene,
+    config: &crate::config::Config,
+) {
+    if input.take_pressed(Action::ToggleConsole) {
+        console.toggle();
+        *input = new_input(config);
+    }
+    if input.take_pressed(Action::Pause) {
+        menu.pause();
+    }
+    if input.take_pressed(Action::ToggleDebug) {
+        scene.debug.toggle();
+    }
+}
+
+/// Do what the menu can't do on its own, returns whether the game should quit.
+fn apply_menu_action(
+    action: crate::menu::MenuAction,
+    scene: &mut crate::scene::Scene,
+    level_id: &str,
+    config: &crate::config::Config,
+    config_path: &str,
+    window: &winit::window::Window,
+) -> bool {
+    match action {
+        crate::menu::MenuAction::NewGame => match new_scene(level_id, config, window) {
+            Ok(mut new_scene) => {
+                new_scene.cursor.position = scene.cursor.position;
+                *scene = new_scene;
+            }
+            Err(e) => log::error!("cannot start a new game: {}", e),
+        },
+        crate::menu::MenuAction::Quit => return true,
+        crate::menu::MenuAction::ConfigChanged => {
+            window.set_fullscreen(fullscreen(config.window.mode));
+            if let Err(e) = config.save(config_path) {
+                log::error!("cannot save config {:?}: {}", config_path, e);
+            }
+        }
+    }
+    false
+}
+
+/// Creates a new scene set up by the config, sized to the window.
+fn new_scene(
+    level_id: &str,
+    config: &crate::config::Config,
+    window: &winit::window::Window,
+) -> Result<crate::scene::Scene, crate::scene::SceneError> {
+    let mut scene = crate::scene::Scene::with_config(level_id, config.gameplay.clone())?;
+    scene.observer.handle_resize(window.inner_size().into());
+    scene.observer.set_pixels_per_tile(config.graphics.pixels_per_tile);
+    Ok(scene)
+}
+
+/// Little helper function.
+fn fullscreen(mode: crate::config::WindowMode) -> Option<Fullscreen> {
+    match mode {
+        crate::config::WindowMode::Windowed => None,
+        crate::config::WindowMode::Fullscreen => Some(Fullscreen::Borderless(None)),
+    }
+}
 
 #[derive(Error, Debug)]
 pub enum LaunchError {
@@ -104,4 +304,10 @@ pub enum LaunchError {
 
     #[error("scene error: {0}")]
     Scene(#[from] crate::scene::SceneError),
+
+    #[error("config error: {0}")]
+    Config(#[from] crate::config::ConfigError),
+
+    #[error("args error: {0}")]
+    Args(#[from] crate::cli::ArgsError),
 }
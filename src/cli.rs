@@ -0,0 +1,107 @@
+use thiserror::Error;
+
+// --------------------------------------------------
+// --- ARGS ---
+// --------------------------------------------------
+
+/// Command line arguments: `roguelike [LEVEL] [--backend NAME] [--adapter NAME] [--list-adapters]`.
+/// The graphics options override the config file, but they are never saved into it.
+#[derive(Debug, Default, PartialEq)]
+pub struct Args {
+    pub level_id: Option<String>,
+    pub backend: Option<crate::config::Backend>,
+    pub adapter: Option<String>,
+    pub list_adapters: bool,
+}
+
+impl Args {
+    /// Parse the arguments, without the program name.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut result = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--backend" => {
+                    let value = args.next().ok_or(ArgsError::MissingValue("--backend"))?;
+                    result.backend = Some(value.parse()?);
+                }
+                "--adapter" => {
+                    result.adapter = Some(args.next().ok_or(ArgsError::MissingValue("--adapter"))?);
+                }
+                "--list-adapters" => result.list_adapters = true,
+                _ if arg.starts_with("--") => return Err(ArgsError::UnknownOption(arg)),
+                _ if result.level_id.is_none() => result.level_id = Some(arg),
+                _ => return Err(ArgsError::UnexpectedArgument(arg)),
+            }
+        }
+        Ok(result)
+    }
+
+    /// The graphics config with the command line overrides applied.
+    pub fn graphics(
+        &self,
+        config: &crate::config::GraphicsConfig,
+    ) -> crate::config::GraphicsConfig {
+        let mut graphics = config.clone();
+        if let Some(backend) = self.backend {
+            graphics.backend = backend;
+        }
+        if let Some(adapter) = &self.adapter {
+            graphics.adapter = Some(adapter.clone());
+        }
+        graphics
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ArgsError {
+    #[error("missing value of {0}")]
+    MissingValue(&'static str),
+
+    #[error("unknown option {0}")]
+    UnknownOption(String),
+
+    #[error("unexpected argument {0}")]
+    UnexpectedArgument(String),
+
+    #[error("{0}")]
+    Backend(#[from] crate::config::ConfigError),
+}
+
+// --------------------------------------------------
+// --- TEST ---
+// --------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::{Args, ArgsError};
+    use crate::config::{Backend, GraphicsConfig};
+
+    fn parse(args: &[&str]) -> Result<Args, ArgsError> {
+        Args::parse(args.iter().map(|it| it.to_string()))
+    }
+
+    #[test]
+    fn test_args_parse() {
+        assert_eq!(parse(&[]).unwrap(), Args::default());
+        let args = parse(&["level1", "--backend", "gl", "--adapter", "llvmpipe"]).unwrap();
+        assert_eq!(args.level_id.as_deref(), Some("level1"));
+        assert_eq!(args.backend, Some(Backend::Gl));
+        assert!(!args.list_adapters);
+
+        let graphics = args.graphics(&GraphicsConfig::default());
+        assert_eq!(graphics.backend, Backend::Gl);
+        assert_eq!(graphics.adapter.as_deref(), Some("llvmpipe"));
+        assert!(graphics.fallback);
+
+        assert!(parse(&["--list-adapters"]).unwrap().list_adapters);
+    }
+
+    #[test]
+    fn test_args_errors() {
+        assert!(matches!(parse(&["--backend"]), Err(ArgsError::MissingValue("--backend"))));
+        assert!(matches!(parse(&["--backend", "glide"]), Err(ArgsError::Backend(_))));
+        assert!(matches!(parse(&["--fast"]), Err(ArgsError::UnknownOption(_))));
+        assert!(matches!(parse(&["level0", "level1"]), Err(ArgsError::UnexpectedArgument(_))));
+    }
+}
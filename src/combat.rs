@@ -0,0 +1,203 @@
+use crate::{
+    enemy::Enemy,
+    geometry::{Aabb, Direction},
+    player::Player,
+    sprite::ANIM_LOOP_SECS,
+};
+use cgmath::{InnerSpace, Point2, Vector2};
+
+// --------------------------------------------------
+// --- MELEE ---
+// --------------------------------------------------
+
+/// Melee attack of a character: swing timing, cooldown and the targets hit during the swing.
+/// All the timers are in seconds, knockback speed is per second.
+pub struct Melee {
+    damage: f32,
+    knockback: f32,
+    cooldown: f32,
+
+    swing_timer: f32,
+    cooldown_timer: f32,
+    hit_targets: Vec<usize>,
+}
+
+impl Melee {
+    /// Creates a new melee attack, the cooldown is never shorter than a single swing.
+    pub fn new(damage: f32, knockback: f32, cooldown: f32) -> Self {
+        Self {
+            damage,
+            knockback,
+            cooldown: cooldown.max(ANIM_LOOP_SECS),
+
+            swing_timer: 0.0,
+            cooldown_timer: 0.0,
+            hit_targets: vec![],
+        }
+    }
+
+    /// Start a new swing, returns `false` if the attack is still cooling down.
+    pub fn start(&mut self) -> bool {
+        if self.cooldown_timer > 0.0 {
+            return false;
+        }
+        self.swing_timer = ANIM_LOOP_SECS;
+        self.cooldown_timer = self.cooldown;
+        self.hit_targets.clear();
+        true
+    }
+
+    /// Interrupt the current swing, e.g. when the attacker gets hurt.
+    pub fn cancel(&mut self) {
+        self.swing_timer = 0.0;
+    }
+
+    /// Advance the swing and cooldown timers by `dt` seconds.
+    pub fn advance(&mut self, dt: f32) {
+        self.swing_timer = (self.swing_timer - dt).max(0.0);
+        self.cooldown_timer = (self.cooldown_timer - dt).max(0.0);
+    }
+
+    pub fn is_swinging(&self) -> bool {
+        self.swing_timer > 0.0
+    }
+
+    /// Area in front of the attacker which is hit by the swing.
+    pub fn hitbox(position: Point2<f32>, facing: Direction) -> Aabb {
+        Aabb::from_center(position + facing.to_vector() * HIT_REACH, HIT_HALF_SIZE)
+    }
+
+    /// Remember the target as hit, returns `false` if it was already hit during this swing.
+    fn register_hit(&mut self, target: usize) -> bool {
+        if self.hit_targets.contains(&target) {
+            return false;
+        }
+        self.hit_targets.push(target);
+        true
+    }
+
+    /// Hit landed by the attacker at `from` on the target at `to`.
+    fn hit(&self, from: Point2<f32>, to: Point2<f32>) -> Hit {
+        let delta = to - from;
+        let knockback = if delta.magnitude2() > f32::EPSILON {
+            delta.normalize() * self.knockback
+        } else {
+            Vector2::new(0.0, 0.0)
+        };
+        Hit { damage: self.damage, knockback }
+    }
+}
+
+/// A single hit landed by a melee attack.
+#[derive(Clone, Copy, Debug)]
+pub struct Hit {
+    pub damage: f32,
+    pub knockback: Vector2<f32>,
+}
+
+/// Body of a character which can be hit, same size as used for the level collision.
+pub fn body(position: Point2<f32>) -> Aabb {
+    Aabb::from_center(position, BODY_HALF_SIZE)
+}
+
+/// Check all the active swings against the bodies of their targets and apply the hits.
+/// A target is hit at most once per swing, and only during the hit frames of the animation.
+pub fn resolve(player: &mut Player, enemies: &mut [Enemy]) {
+    if player.is_alive() && player.melee.is_swinging() && player.animation.is_hit_frame() {
+        let hitbox = Melee::hitbox(player.position, player.facing());
+        for (idx, enemy) in enemies.iter_mut().enumerate() {
+            if !enemy.is_alive() || !hitbox.intersects(&body(enemy.position)) {
+                continue;
+            }
+            if player.melee.register_hit(idx) {
+                let mut hit = player.melee.hit(player.position, enemy.position);
+                hit.damage += player.inventory.damage_bonus();
+                enemy.take_hit(hit);
+            }
+        }
+    }
+
+    for enemy in enemies.iter_mut() {
+        if !player.is_alive() || !enemy.melee.is_swinging() || !enemy.animation.is_hit_frame() {
+            continue;
+        }
+        let hitbox = Melee::hitbox(enemy.position, enemy.facing());
+        if hitbox.intersects(&body(player.position)) && enemy.melee.register_hit(0) {
+            player.take_hit(enemy.melee.hit(enemy.position, player.position));
+        }
+    }
+}
+
+/// Distance from the attacker to the center of its hitbox.
+const HIT_REACH: f32 = 0.75;
+const HIT_HALF_SIZE: f32 = 0.5;
+pub const BODY_HALF_SIZE: f32 = crate::level::MESH_XZ_COORD * 0.75;
+
+// --------------------------------------------------
+// --- TEST ---
+// --------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::{resolve, Melee};
+    use crate::{
+        enemy::{Enemy, EnemyKind, EnemyState},
+        input::{Action, Input},
+        player::{Player, PLAYER_MELEE_DAMAGE},
+        sprite::ANIM_LOOP_SECS,
+    };
+    use cgmath::Point2;
+
+    const DT: f32 = 1.0 / 60.0;
+
+    #[test]
+    fn test_melee_cooldown() {
+        let mut melee = Melee::new(10.0, 1.0, 1.0);
+        assert!(melee.start());
+        assert!(melee.is_swinging());
+        assert!(!melee.start());
+
+        melee.advance(ANIM_LOOP_SECS + DT);
+        assert!(!melee.is_swinging());
+        assert!(!melee.start());
+
+        melee.advance(1.0);
+        assert!(melee.start());
+    }
+
+    #[test]
+    fn test_melee_player_hits_enemy_once_per_swing() {
+        let mut player = Player::new(Point2::new(0.0, 0.0));
+        let mut enemies = vec![Enemy::new(EnemyKind::Orc3, Point2::new(0.0, 0.8), 1)];
+        let hp = enemies[0].stats.hp;
+
+        let mut input = Input::new();
+        input.set(Action::Attack, true);
+        player.apply_input(&input, DT);
+        for _ in 0..(ANIM_LOOP_SECS / DT) as usize {
+            player.advance(DT);
+            resolve(&mut player, &mut enemies);
+        }
+        assert_eq!(enemies[0].stats.hp, hp - PLAYER_MELEE_DAMAGE);
+        assert!(matches!(enemies[0].state, EnemyState::Hurt { .. }));
+    }
+
+    #[test]
+    fn test_melee_enemy_killed() {
+        let mut player = Player::new(Point2::new(0.0, 0.0));
+        let mut enemies = vec![Enemy::new(EnemyKind::Orc3, Point2::new(0.0, 0.8), 1)];
+
+        let mut input = Input::new();
+        for i in 0..600 {
+            // a swing per press, the attack key is tapped every half a second
+            input.set(Action::Attack, i % 30 == 0);
+            player.apply_input(&input, DT);
+            input.end_tick();
+            player.advance(DT);
+            enemies[0].advance(Some(player.position), DT);
+            resolve(&mut player, &mut enemies);
+        }
+        assert!(!enemies[0].is_alive());
+        assert_eq!(enemies[0].state, EnemyState::Dead);
+    }
+}
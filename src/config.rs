@@ -0,0 +1,412 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+// --------------------------------------------------
+// --- CONFIG ---
+// --------------------------------------------------
+
+/// User settings persisted in a TOML file. Missing keys fall back to the defaults,
+/// so an older or hand-written file still loads.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub window: WindowConfig,
+    pub graphics: GraphicsConfig,
+    pub audio: AudioConfig,
+    pub keys: crate::input::Bindings,
+    pub gamepad: GamepadConfig,
+    pub gameplay: GameplayConfig,
+}
+
+impl Config {
+    /// Load the config from the given file, a missing file gives the default config.
+    pub fn load(path: &str) -> Result<Self, ConfigError> {
+        let source = match std::fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(ConfigError::Read(e)),
+        };
+        Self::parse(&source)
+    }
+
+    /// Parse and validate the config file contents.
+    pub fn parse(source: &str) -> Result<Self, ConfigError> {
+        let config: Self = toml::from_str(source)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Check the values which parse fine but make no sense.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let positive = [
+            ("window.width", self.window.width as f32),
+            ("window.height", self.window.height as f32),
+            ("graphics.pixels_per_tile", self.graphics.pixels_per_tile as f32),
+            ("gameplay.player_velocity_delta", self.gameplay.player_velocity_delta),
+            ("gameplay.player_velocity_max", self.gameplay.player_velocity_max),
+            ("gameplay.player_max_hp", self.gameplay.player_max_hp),
+            ("gameplay.player_melee_damage", self.gameplay.player_melee_damage),
+            ("gameplay.enemy_max_hp", self.gameplay.enemy_max_hp),
+            ("gameplay.player_light_radius", self.gameplay.player_light_radius),
+        ];
+        if let Some((key, _)) =
+            positive.iter().find(|(_, value)| !value.is_finite() || *value <= 0.0)
+        {
+            return Err(ConfigError::Invalid { key, reason: "has to be positive" });
+        }
+
+        let non_negative = [
+            ("gameplay.player_hp_regen", self.gameplay.player_hp_regen),
+            ("gameplay.player_max_mp", self.gameplay.player_max_mp),
+            ("gameplay.player_mp_regen", self.gameplay.player_mp_regen),
+            ("gameplay.player_respawn_delay", self.gameplay.player_respawn_delay),
+            ("gameplay.enemy_melee_damage", self.gameplay.enemy_melee_damage),
+            ("gameplay.player_light_color[0]", self.gameplay.player_light_color[0]),
+            ("gameplay.player_light_color[1]", self.gameplay.player_light_color[1]),
+            ("gameplay.player_light_color[2]", self.gameplay.player_light_color[2]),
+        ];
+        if let Some((key, _)) =
+            non_negative.iter().find(|(_, value)| !value.is_finite() || *value < 0.0)
+        {
+            return Err(ConfigError::Invalid { key, reason: "cannot be negative" });
+        }
+
+        if !(0.0..1.0).contains(&self.gamepad.deadzone) {
+            return Err(ConfigError::Invalid {
+                key: "gamepad.deadzone",
+                reason: "has to be in 0..1",
+            });
+        }
+        if !(0.0..=1.0).contains(&self.audio.volume) {
+            return Err(ConfigError::Invalid { key: "audio.volume", reason: "has to be in 0..=1" });
+        }
+        Ok(())
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), ConfigError> {
+        std::fs::write(path, toml::to_string(self)?).map_err(ConfigError::Write)
+    }
+
+    /// Config file path: set by the environment variable or the default one.
+    pub fn path() -> String {
+        std::env::var(CONFIG_ENV_VAR).unwrap_or_else(|_| CONFIG_DEFAULT_PATH.to_string())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowConfig {
+    /// Inner size of the window in pixels.
+    pub width: u32,
+    pub height: u32,
+    /// Position of the window on the screen in pixels.
+    pub x: i32,
+    pub y: i32,
+    pub mode: WindowMode,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self { width: 1920, height: 1280, x: 25, y: 25, mode: WindowMode::Windowed }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowMode {
+    #[default]
+    Windowed,
+    Fullscreen,
+}
+
+impl WindowMode {
+    pub fn toggle(&self) -> Self {
+        match self {
+            Self::Windowed => Self::Fullscreen,
+            Self::Fullscreen => Self::Windowed,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GraphicsConfig {
+    pub backend: Backend,
+    /// Use the adapter whose name contains this (case insensitive), e.g. `"nvidia"`.
+    pub adapter: Option<String>,
+    /// Try a software adapter and then OpenGL when the backend has no usable adapter.
+    pub fallback: bool,
+    /// Wait for the vertical blank (no tearing), otherwise present frames as soon as possible.
+    pub vsync: bool,
+    /// Zoom: size of a single level block on the screen, in pixels.
+    pub pixels_per_tile: u32,
+}
+
+impl Default for GraphicsConfig {
+    fn default() -> Self {
+        Self {
+            backend: Backend::Primary,
+            adapter: None,
+            fallback: true,
+            vsync: true,
+            pixels_per_tile: crate::observer::PIXELS_PER_TILE,
+        }
+    }
+}
+
+/// Graphics API used by `wgpu`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Backend {
+    /// Whatever works best on the platform (Vulkan, Metal, DX12 or WebGPU).
+    #[default]
+    Primary,
+    Vulkan,
+    Gl,
+    Metal,
+    Dx12,
+}
+
+impl GraphicsConfig {
+    /// Backends to request an adapter from, in order; `true` asks for a software adapter.
+    pub fn adapter_attempts(&self) -> Vec<(Backend, bool)> {
+        let mut attempts = vec![(self.backend, false)];
+        if self.fallback {
+            attempts.push((self.backend, true));
+            if self.backend != Backend::Gl {
+                attempts.extend([(Backend::Gl, false), (Backend::Gl, true)]);
+            }
+        }
+        attempts
+    }
+}
+
+impl Backend {
+    pub const ALL: [Backend; 5] = [Self::Primary, Self::Vulkan, Self::Gl, Self::Metal, Self::Dx12];
+
+    /// Name used in the config file and on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Primary => "primary",
+            Self::Vulkan => "vulkan",
+            Self::Gl => "gl",
+            Self::Metal => "metal",
+            Self::Dx12 => "dx12",
+        }
+    }
+
+    pub fn to_wgpu(self) -> wgpu::Backends {
+        match self {
+            Self::Primary => wgpu::Backends::PRIMARY,
+            Self::Vulkan => wgpu::Backends::VULKAN,
+            Self::Gl => wgpu::Backends::GL,
+            Self::Metal => wgpu::Backends::METAL,
+            Self::Dx12 => wgpu::Backends::DX12,
+        }
+    }
+}
+
+impl std::str::FromStr for Backend {
+    type Err = ConfigError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|it| it.name().eq_ignore_ascii_case(name))
+            .ok_or(ConfigError::Invalid { key: "graphics.backend", reason: "unknown backend" })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioConfig {
+    /// Master volume in `0..=1`. There is no audio yet, the setting is only kept.
+    pub volume: f32,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self { volume: 0.8 }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GamepadConfig {
+    /// Use gamepads, when the game is built with the `gamepad` feature.
+    pub enabled: bool,
+    /// Part of the stick range (`0..1`) around the center which is ignored.
+    pub deadzone: f32,
+    pub buttons: crate::input::Bindings<crate::input::Button>,
+}
+
+impl Default for GamepadConfig {
+    fn default() -> Self {
+        Self { enabled: true, deadzone: 0.2, buttons: Default::default() }
+    }
+}
+
+/// Gameplay tunables. Speeds are per second, accelerations are per second squared,
+/// timers are in seconds.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameplayConfig {
+    pub player_velocity_delta: f32,
+    pub player_velocity_max: f32,
+    pub player_max_hp: f32,
+    pub player_hp_regen: f32,
+    pub player_max_mp: f32,
+    pub player_mp_regen: f32,
+    pub player_melee_damage: f32,
+    pub player_respawn_delay: f32,
+    pub enemy_max_hp: f32,
+    pub enemy_melee_damage: f32,
+    /// Radius (in blocks) of the light carried by the player.
+    pub player_light_radius: f32,
+    /// RGB color of the player light, the components are usually in `0..=1`.
+    pub player_light_color: [f32; 3],
+}
+
+impl Default for GameplayConfig {
+    fn default() -> Self {
+        use crate::{enemy, light, player};
+        Self {
+            player_velocity_delta: player::PLAYER_VELOCITY_DELTA,
+            player_velocity_max: player::PLAYER_VELOCITY_MAX,
+            player_max_hp: player::PLAYER_HP,
+            player_hp_regen: player::PLAYER_HP_REGEN,
+            player_max_mp: player::PLAYER_MP,
+            player_mp_regen: player::PLAYER_MP_REGEN,
+            player_melee_damage: player::PLAYER_MELEE_DAMAGE,
+            player_respawn_delay: player::PLAYER_RESPAWN_DELAY,
+            enemy_max_hp: enemy::ENEMY_HP,
+            enemy_melee_damage: enemy::ENEMY_MELEE_DAMAGE,
+            player_light_radius: light::PLAYER_LIGHT.radius,
+            player_light_color: light::PLAYER_LIGHT.color,
+        }
+    }
+}
+
+const CONFIG_DEFAULT_PATH: &str = "./config.toml";
+const CONFIG_ENV_VAR: &str = "ROGUELIKE_CONFIG";
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("read error: {0}")]
+    Read(std::io::Error),
+
+    #[error("write error: {0}")]
+    Write(std::io::Error),
+
+    #[error("parse error: {0}")]
+    Parse(#[from] toml::de::Error),
+
+    #[error("serialize error: {0}")]
+    Serialize(#[from] toml::ser::Error),
+
+    #[error("invalid value of {key}: {reason}")]
+    Invalid { key: &'static str, reason: &'static str },
+}
+
+// --------------------------------------------------
+// --- TEST ---
+// --------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::{Backend, Config, ConfigError, WindowMode};
+    use crate::input::{Action, Button};
+    use winit::keyboard::KeyCode;
+
+    #[test]
+    fn test_config_round_trip() {
+        let mut config = Config::default();
+        config.window.mode = WindowMode::Fullscreen;
+        config.audio.volume = 0.3;
+        config.keys.set(Action::Attack, vec![KeyCode::Space, KeyCode::KeyJ]);
+        config.graphics.adapter = Some("llvmpipe".to_string());
+        let source = toml::to_string(&config).unwrap();
+        assert_eq!(Config::parse(&source).unwrap(), config);
+    }
+
+    #[test]
+    fn test_config_partial() {
+        let config = Config::parse("[window]\nmode = \"fullscreen\"\n").unwrap();
+        assert_eq!(config.window.mode, WindowMode::Fullscreen);
+        assert_eq!(config.audio, Default::default());
+        assert_eq!(config.keys, Default::default());
+        assert!(Config::parse("[window]\nmode = \"maximized\"\n").is_err());
+    }
+
+    #[test]
+    fn test_config_keys() {
+        let source = "[keys]\nmove_up = \"KeyK\"\nattack = [\"Space\", \"Enter\"]\n";
+        let config = Config::parse(source).unwrap();
+        assert_eq!(config.keys.get(Action::MoveUp), [KeyCode::KeyK]);
+        assert_eq!(config.keys.get(Action::Attack), [KeyCode::Space, KeyCode::Enter]);
+        // the rest keeps the defaults
+        assert_eq!(config.keys.get(Action::MoveDown), [KeyCode::ArrowDown]);
+        assert!(Config::parse("[keys]\nfly = \"KeyF\"\n").is_err());
+
+        let source = "[gamepad.buttons]\nattack = \"east\"\ninteract = [\"d_pad_up\"]\n";
+        let config = Config::parse(source).unwrap();
+        assert_eq!(config.gamepad.buttons.get(Action::Attack), [Button::East]);
+        assert_eq!(config.gamepad.buttons.get(Action::Interact), [Button::DPadUp]);
+        assert_eq!(config.gamepad.buttons.get(Action::Pause), [Button::Start]);
+        assert!(Config::parse("[gamepad]\ndeadzone = 1.0\n").is_err());
+    }
+
+    #[test]
+    fn test_config_validation() {
+        let actual = Config::parse("[graphics]\npixels_per_tile = 0\n");
+        assert!(matches!(
+            actual,
+            Err(ConfigError::Invalid { key: "graphics.pixels_per_tile", .. })
+        ));
+        let actual = Config::parse("[gameplay]\nplayer_hp_regen = -1.0\n");
+        assert!(matches!(
+            actual,
+            Err(ConfigError::Invalid { key: "gameplay.player_hp_regen", .. })
+        ));
+        let actual = Config::parse("[gameplay]\nenemy_max_hp = nan\n");
+        assert!(matches!(actual, Err(ConfigError::Invalid { key: "gameplay.enemy_max_hp", .. })));
+        let actual = Config::parse("[gameplay]\nplayer_light_color = [1.0, -0.5, 1.0]\n");
+        assert!(matches!(
+            actual,
+            Err(ConfigError::Invalid { key: "gameplay.player_light_color[1]", .. })
+        ));
+        assert!(Config::parse("[gameplay]\nplayer_light_radius = 0.0\n").is_err());
+        assert!(Config::parse("[gameplay]\nplayer_light_color = [1.0, 1.0]\n").is_err());
+        assert!(Config::parse("[audio]\nvolume = 1.5\n").is_err());
+
+        let config = Config::parse("[graphics]\nbackend = \"gl\"\nvsync = false\n").unwrap();
+        assert_eq!(config.graphics.backend, Backend::Gl);
+        assert_eq!(config.window, Default::default());
+    }
+
+    #[test]
+    fn test_config_backend() {
+        for backend in Backend::ALL {
+            assert_eq!(backend.name().parse::<Backend>().unwrap(), backend);
+        }
+        assert_eq!("Vulkan".parse::<Backend>().unwrap(), Backend::Vulkan);
+        assert!("glide".parse::<Backend>().is_err());
+
+        let mut config = Config::default();
+        config.graphics.backend = Backend::Vulkan;
+        let expected = vec![
+            (Backend::Vulkan, false),
+            (Backend::Vulkan, true),
+            (Backend::Gl, false),
+            (Backend::Gl, true),
+        ];
+        assert_eq!(config.graphics.adapter_attempts(), expected);
+        config.graphics.backend = Backend::Gl;
+        assert_eq!(
+            config.graphics.adapter_attempts(),
+            vec![(Backend::Gl, false), (Backend::Gl, true)]
+        );
+        config.graphics.fallback = false;
+        assert_eq!(config.graphics.adapter_attempts(), vec![(Backend::Gl, false)]);
+    }
+}
@@ -0,0 +1,463 @@
+use crate::{
+    enemy::{Enemy, EnemyKind},
+    scene::{Scene, SceneError},
+    text::Text,
+};
+use cgmath::Point2;
+use std::str::FromStr;
+use thiserror::Error;
+use winit::{
+    event::{ElementState, KeyEvent},
+    keyboard::{Key, NamedKey},
+};
+
+// --------------------------------------------------
+// --- CONSOLE ---
+// --------------------------------------------------
+
+/// In-game developer console: a line of text input, output scrollback,
+/// history of the submitted lines and tab completion of the registered commands.
+pub struct Console {
+    pub open: bool,
+    pub input: String,
+    pub output: Vec<String>,
+
+    history: Vec<String>,
+    history_pos: Option<usize>,
+
+    pub registry: Registry,
+}
+
+impl Console {
+    /// Creates a new (closed) console with all the built-in commands registered.
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            input: String::new(),
+            output: vec![],
+            history: vec![],
+            history_pos: None,
+            registry: Registry::new(),
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.input.clear();
+        self.history_pos = None;
+    }
+
+    /// Handle a key press while the console is open. The key closing the console
+    /// ([`crate::input::Action::ToggleConsole`]) is handled by the application.
+    pub fn handle_key_event(&mut self, e: &KeyEvent, scene: &mut Scene) {
+        if e.state != ElementState::Pressed {
+            return;
+        }
+        match &e.logical_key {
+            Key::Named(NamedKey::Escape) => self.toggle(),
+            Key::Named(NamedKey::Enter) => self.submit(scene),
+            Key::Named(NamedKey::Tab) => self.complete(),
+            Key::Named(NamedKey::ArrowUp) => self.history_prev(),
+            Key::Named(NamedKey::ArrowDown) => self.history_next(),
+            Key::Named(NamedKey::Backspace) => {
+                self.input.pop();
+            }
+            _ => {
+                if let Some(text) = &e.text {
+                    self.input.extend(text.chars().filter(|c| !c.is_control()));
+                }
+            }
+        }
+    }
+
+    /// Run the typed line on the scene, the result goes to the console output.
+    pub fn submit(&mut self, scene: &mut Scene) {
+        let line = std::mem::take(&mut self.input);
+        self.history_pos = None;
+        if line.trim().is_empty() {
+            return;
+        }
+        self.print(format!("> {}", line));
+        if self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+        }
+
+        match self.registry.execute(scene, &line) {
+            Ok(output) if output.is_empty() => {}
+            Ok(output) => self.print(output),
+            Err(e) => self.print(format!("error: {}", e)),
+        }
+    }
+
+    /// Complete the typed line: a single candidate replaces the line,
+    /// multiple candidates extend it to their common prefix and get printed.
+    pub fn complete(&mut self) {
+        let candidates = self.registry.complete(&self.input);
+        match candidates.as_slice() {
+            [] => {}
+            [candidate] => self.input = format!("{} ", candidate),
+            [first, rest @ ..] => {
+                let mut prefix = first.as_str();
+                for candidate in rest {
+                    let len =
+                        prefix.chars().zip(candidate.chars()).take_while(|(a, b)| a == b).count();
+                    prefix = &prefix[..len];
+                }
+                self.input = prefix.to_string();
+                self.print(candidates.join("  "));
+            }
+        }
+    }
+
+    /// Go back in the history of the submitted lines.
+    pub fn history_prev(&mut self) {
+        let pos = match self.history_pos {
+            _ if self.history.is_empty() => return,
+            Some(pos) => pos.saturating_sub(1),
+            None => self.history.len() - 1,
+        };
+        self.history_pos = Some(pos);
+        self.input = self.history[pos].clone();
+    }
+
+    /// Go forward in the history, past the last line the input gets empty.
+    pub fn history_next(&mut self) {
+        let Some(pos) = self.history_pos else {
+            return;
+        };
+        if pos + 1 < self.history.len() {
+            self.history_pos = Some(pos + 1);
+            self.input = self.history[pos + 1].clone();
+        } else {
+            self.history_pos = None;
+            self.input.clear();
+        }
+    }
+
+    /// Queue the console for drawing: the last lines of the output followed by the input line.
+    pub fn draw(&self, text: &mut Text) {
+        if !self.open {
+            return;
+        }
+        let lines: Vec<&str> = self.output.iter().flat_map(|it| it.lines()).collect();
+        let lines = &lines[lines.len().saturating_sub(CONSOLE_VISIBLE_LINES)..];
+        let position = Point2::new(CONSOLE_MARGIN, CONSOLE_MARGIN);
+        text.queue(&lines.join("\n"), position, 1.0, CONSOLE_OUTPUT_COLOR);
+
+        let y = CONSOLE_MARGIN + lines.len() as f32 * crate::text::GLYPH_HEIGHT;
+        let input = format!("> {}_", self.input);
+        text.queue(&input, Point2::new(CONSOLE_MARGIN, y), 1.0, CONSOLE_INPUT_COLOR);
+    }
+
+    /// Add a line to the console output, the oldest lines are dropped.
+    pub fn print(&mut self, line: String) {
+        log::info!("console: {}", line);
+        self.output.push(line);
+        if self.output.len() > CONSOLE_OUTPUT_LINES {
+            self.output.remove(0);
+        }
+    }
+}
+
+const CONSOLE_OUTPUT_LINES: usize = 100;
+/// Number of the output lines drawn on the screen.
+const CONSOLE_VISIBLE_LINES: usize = 12;
+/// Distance of the text from the window corner, in pixels.
+const CONSOLE_MARGIN: f32 = 16.0;
+const CONSOLE_OUTPUT_COLOR: [f32; 4] = [0.8, 0.8, 0.8, 1.0];
+const CONSOLE_INPUT_COLOR: [f32; 4] = [1.0, 0.9, 0.4, 1.0];
+
+// --------------------------------------------------
+// --- REGISTRY ---
+// --------------------------------------------------
+
+/// Function implementing a command, it gets the arguments (without the command name)
+/// and returns a message to print.
+pub type CommandFn = fn(&mut Scene, &[&str]) -> Result<String, CommandError>;
+
+pub struct Command {
+    pub name: &'static str,
+    pub usage: &'static str,
+    /// Known values of the first argument, used by the tab completion.
+    pub args: &'static [&'static str],
+    pub run: CommandFn,
+}
+
+/// Set of the console commands. Commands can be executed without the console itself,
+/// e.g. to script a scene in tests.
+pub struct Registry {
+    commands: Vec<Command>,
+}
+
+impl Registry {
+    /// Creates a new registry with all the built-in commands.
+    pub fn new() -> Self {
+        let mut registry = Self { commands: vec![] };
+        registry.register(Command {
+            name: "teleport",
+            usage: "teleport <x> <y>",
+            args: &[],
+            run: cmd_teleport,
+        });
+        registry.register(Command {
+            name: "load_level",
+            usage: "load_level <id>",
+            args: &[],
+            run: cmd_load_level,
+        });
+        registry.register(Command { name: "god", usage: "god", args: &[], run: cmd_god });
+        registry.register(Command {
+            name: "spawn",
+            usage: "spawn <enemy> <x> <y>",
+            args: &["orc3"],
+            run: cmd_spawn,
+        });
+        registry.register(Command {
+            name: "set",
+            usage: "set <variable> <value>",
+            args: &SET_VARIABLES,
+            run: cmd_set,
+        });
+        registry.register(Command {
+            name: "toggle",
+            usage: "toggle <flag>",
+            args: &TOGGLE_FLAGS,
+            run: cmd_toggle,
+        });
+        registry
+    }
+
+    /// Add a command, replacing the one with the same name.
+    pub fn register(&mut self, command: Command) {
+        self.commands.retain(|it| it.name != command.name);
+        self.commands.push(command);
+    }
+
+    /// Parse and execute a single command line.
+    pub fn execute(&self, scene: &mut Scene, line: &str) -> Result<String, CommandError> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let Some((name, args)) = words.split_first() else {
+            return Ok(String::new());
+        };
+        if *name == "help" {
+            return Ok(self.commands.iter().map(|it| it.usage).collect::<Vec<_>>().join("\n"));
+        }
+        let command = self
+            .commands
+            .iter()
+            .find(|it| it.name == *name)
+            .ok_or_else(|| CommandError::UnknownCommand(name.to_string()))?;
+        (command.run)(scene, args).map_err(|e| match e {
+            CommandError::Usage(_) => CommandError::Usage(command.usage),
+            e => e,
+        })
+    }
+
+    /// Full lines the given partial line can be completed to.
+    pub fn complete(&self, line: &str) -> Vec<String> {
+        match line.split_once(' ') {
+            None => {
+                let names = self.commands.iter().map(|it| it.name).chain(["help"]);
+                names.filter(|it| it.starts_with(line)).map(str::to_string).collect()
+            }
+            Some((name, arg)) if !arg.contains(' ') => {
+                let Some(command) = self.commands.iter().find(|it| it.name == name) else {
+                    return vec![];
+                };
+                let args = command.args.iter().filter(|it| it.starts_with(arg));
+                args.map(|it| format!("{} {}", name, it)).collect()
+            }
+            Some(_) => vec![],
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum CommandError {
+    #[error("unknown command {0:?}, try \"help\"")]
+    UnknownCommand(String),
+
+    #[error("usage: {0}")]
+    Usage(&'static str),
+
+    #[error("invalid argument {0:?}")]
+    InvalidArgument(String),
+
+    #[error("scene error: {0}")]
+    Scene(#[from] SceneError),
+}
+
+/// Parse a single argument, missing arguments are reported with the command usage.
+fn arg<T: FromStr>(args: &[&str], idx: usize) -> Result<T, CommandError> {
+    let arg = args.get(idx).ok_or(CommandError::Usage(""))?;
+    arg.parse().map_err(|_| CommandError::InvalidArgument(arg.to_string()))
+}
+
+/// Parse a single number argument, see [`arg`]. `nan` and `inf` are refused.
+fn number(args: &[&str], idx: usize) -> Result<f32, CommandError> {
+    let value: f32 = arg(args, idx)?;
+    if !value.is_finite() {
+        return Err(CommandError::InvalidArgument(args[idx].to_string()));
+    }
+    Ok(value)
+}
+
+fn cmd_teleport(scene: &mut Scene, args: &[&str]) -> Result<String, CommandError> {
+    let position = Point2::new(number(args, 0)?, number(args, 1)?);
+    scene.player.teleport(position);
+    scene.observer.teleport(position);
+    Ok(format!("teleported to {:?}", position))
+}
+
+fn cmd_load_level(scene: &mut Scene, args: &[&str]) -> Result<String, CommandError> {
+    let id: String = arg(args, 0)?;
+    scene.load_level(&id)?;
+    Ok(format!("level {:?} ({}) loaded", scene.level.name, scene.level.id))
+}
+
+fn cmd_god(scene: &mut Scene, _: &[&str]) -> Result<String, CommandError> {
+    scene.player.god = !scene.player.god;
+    Ok(format!("god mode {}", if scene.player.god { "on" } else { "off" }))
+}
+
+fn cmd_spawn(scene: &mut Scene, args: &[&str]) -> Result<String, CommandError> {
+    let name: String = arg(args, 0)?;
+    let kind = EnemyKind::from_name(&name).ok_or(CommandError::InvalidArgument(name))?;
+    let position = Point2::new(number(args, 1)?, number(args, 2)?);
+    let mut enemy = Enemy::new(kind, position, scene.enemies.len() as u64);
+    enemy.apply_config(&scene.gameplay);
+    scene.enemies.push(enemy);
+    Ok(format!("spawned {:?} at {:?}", kind, position))
+}
+
+fn cmd_set(scene: &mut Scene, args: &[&str]) -> Result<String, CommandError> {
+    let (name, value): (String, f32) = (arg(args, 0)?, number(args, 1)?);
+    let player = &mut scene.player;
+    let variable = match name.as_str() {
+        "velocity_max" => &mut player.velocity_max,
+        "velocity_delta" => &mut player.velocity_delta,
+        "hp" => &mut player.stats.hp,
+        "mp" => &mut player.stats.mp,
+        _ => return Err(CommandError::InvalidArgument(name)),
+    };
+    *variable = value;
+    Ok(format!("{} = {}", name, value))
+}
+
+fn cmd_toggle(scene: &mut Scene, args: &[&str]) -> Result<String, CommandError> {
+    let name: String = arg(args, 0)?;
+    match name.as_str() {
+        "collision_debug" => scene.debug.toggle(),
+        _ => return Err(CommandError::InvalidArgument(name)),
+    }
+    Ok(String::new())
+}
+
+const SET_VARIABLES: [&str; 4] = ["velocity_max", "velocity_delta", "hp", "mp"];
+const TOGGLE_FLAGS: [&str; 1] = ["collision_debug"];
+
+// --------------------------------------------------
+// --- TEST ---
+// --------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::{CommandError, Console, Registry};
+    use crate::{enemy::EnemyKind, input::Input, scene::Scene};
+    use cgmath::Point2;
+
+    #[test]
+    fn test_console_commands() {
+        let mut scene = Scene::new("level0").unwrap();
+        let registry = Registry::new();
+
+        registry.execute(&mut scene, "teleport 4 3").unwrap();
+        assert_eq!(scene.player.position, Point2::new(4.0, 3.0));
+
+        let enemies = scene.enemies.len();
+        registry.execute(&mut scene, "spawn orc3 5 3").unwrap();
+        assert_eq!(scene.enemies.len(), enemies + 1);
+        assert_eq!(scene.enemies[enemies].kind, EnemyKind::Orc3);
+
+        registry.execute(&mut scene, "set velocity_max 0.05").unwrap();
+        registry.execute(&mut scene, "god").unwrap();
+        assert!(scene.player.god);
+        registry.execute(&mut scene, "toggle collision_debug").unwrap();
+        assert!(scene.debug.enabled);
+
+        assert_eq!(scene.player.velocity_max, 0.05);
+
+        // immortal next to an orc
+        (0..300).for_each(|_| scene.advance(&mut Input::new()));
+        assert_eq!(scene.player.stats.hp, scene.player.stats.max_hp);
+
+        registry.execute(&mut scene, "load_level level1").unwrap();
+        assert_eq!(scene.level.id, "level1");
+    }
+
+    #[test]
+    fn test_console_command_errors() {
+        let mut scene = Scene::new("level0").unwrap();
+        let registry = Registry::new();
+        let result = registry.execute(&mut scene, "fly 1 2");
+        assert!(matches!(result, Err(CommandError::UnknownCommand(_))));
+        let result = registry.execute(&mut scene, "teleport 1");
+        assert!(matches!(result, Err(CommandError::Usage("teleport <x> <y>"))));
+        let result = registry.execute(&mut scene, "teleport 1 up");
+        assert!(matches!(result, Err(CommandError::InvalidArgument(_))));
+        for command in ["teleport nan 0", "teleport 0 inf", "spawn orc3 1 -inf", "set hp NaN"] {
+            let result = registry.execute(&mut scene, command);
+            assert!(matches!(result, Err(CommandError::InvalidArgument(_))), "{}", command);
+        }
+        assert!(scene.player.position.x.is_finite() && scene.player.stats.hp.is_finite());
+        let result = registry.execute(&mut scene, "spawn dragon 1 1");
+        assert!(matches!(result, Err(CommandError::InvalidArgument(_))));
+        let result = registry.execute(&mut scene, "load_level nowhere");
+        assert!(matches!(result, Err(CommandError::Scene(_))));
+        assert_eq!(scene.level.id, "level0");
+    }
+
+    #[test]
+    fn test_console_completion() {
+        let mut console = Console::new();
+        console.input = "tel".to_string();
+        console.complete();
+        assert_eq!(console.input, "teleport ");
+
+        console.input = "t".to_string();
+        console.complete();
+        assert_eq!(console.input, "t");
+        assert_eq!(console.output.last().unwrap(), "teleport  toggle");
+
+        console.input = "set velocity_m".to_string();
+        console.complete();
+        assert_eq!(console.input, "set velocity_max ");
+
+        console.input = "set v".to_string();
+        console.complete();
+        assert_eq!(console.input, "set velocity_");
+    }
+
+    #[test]
+    fn test_console_history() {
+        let mut scene = Scene::new("level0").unwrap();
+        let mut console = Console::new();
+        for line in ["god", "teleport 3 3", "teleport 3 3", "nope"] {
+            console.input = line.to_string();
+            console.submit(&mut scene);
+        }
+        assert!(console.output.last().unwrap().starts_with("error:"));
+
+        console.history_prev();
+        assert_eq!(console.input, "nope");
+        console.history_prev();
+        console.history_prev();
+        console.history_prev();
+        assert_eq!(console.input, "god");
+        console.history_next();
+        assert_eq!(console.input, "teleport 3 3");
+        console.history_next();
+        console.history_next();
+        assert_eq!(console.input, "");
+    }
+}
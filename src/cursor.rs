@@ -0,0 +1,123 @@
+use crate::{
+    debug::{aabb_lines, LineVertex},
+    geometry::Aabb,
+    level::{Block, Level},
+    observer::Observer,
+    video,
+};
+use cgmath::Point2;
+
+// --------------------------------------------------
+// --- CURSOR ---
+// --------------------------------------------------
+
+/// Mouse cursor over the level: the tile under it is highlighted, and the player
+/// can be sent there with a click (see [`crate::scene::Scene::walk_to_cursor`]).
+pub struct Cursor {
+    /// Hidden e.g. while a menu is open.
+    pub enabled: bool,
+    /// Window position in pixels, `None` while the cursor is outside of the window.
+    pub position: Option<Point2<f32>>,
+    /// Map position of the hovered block, updated by [`Cursor::update`].
+    tile: Option<Point2<usize>>,
+    free: bool,
+
+    buffer: Option<wgpu::Buffer>,
+}
+
+impl Cursor {
+    /// Creates a new cursor outside of the window.
+    /// The buffer is not created here, see [`Cursor::prepare_render`].
+    pub fn new() -> Self {
+        Self { enabled: true, position: None, tile: None, free: false, buffer: None }
+    }
+
+    /// Find the hovered block as seen by the observer in the last rendered frame.
+    pub fn update(&mut self, observer: &Observer, level: &Level) {
+        self.tile = self.world_position(observer).and_then(|it| level.tile_at(it));
+        self.free = self.tile.and_then(|it| level.block(it.x, it.y)) == Some(Block::Free);
+    }
+
+    /// World position under the cursor, if the cursor is inside of the window.
+    pub fn world_position(&self, observer: &Observer) -> Option<Point2<f32>> {
+        self.position.and_then(|it| observer.screen_to_world(it))
+    }
+
+    /// Map position of the hovered block, as of the last [`Cursor::update`].
+    pub fn tile(&self) -> Option<Point2<usize>> {
+        self.tile.filter(|_| self.enabled)
+    }
+
+    /// Upload the highlight outline of the hovered block to the GPU.
+    pub fn prepare_render(&mut self, video: &video::Video) {
+        let Some(tile) = self.tile() else {
+            return;
+        };
+        let buffer = self.buffer.get_or_insert_with(|| {
+            video.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("cursor_vertex_buffer"),
+                size: std::mem::size_of::<[LineVertex; 8]>() as u64,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        });
+        let center = Point2::new(tile.x as f32, tile.y as f32);
+        let color = if self.free { COLOR_FREE } else { COLOR_BLOCKED };
+        let vertices = aabb_lines(&Aabb::from_center(center, HIGHLIGHT_HALF_SIZE), color);
+        video.queue.write_buffer(buffer, 0, bytemuck::cast_slice(&vertices));
+    }
+
+    /// Render the highlight, the debug pipeline and the observer bind group must be set already.
+    pub fn render(&self, rp: &mut wgpu::RenderPass) {
+        if let Some(buffer) = self.buffer.as_ref().filter(|_| self.tile().is_some()) {
+            rp.set_vertex_buffer(0, buffer.slice(..));
+            rp.draw(0..8, 0..1);
+        }
+    }
+}
+
+const COLOR_FREE: [f32; 4] = [1.0, 1.0, 1.0, 0.7];
+const COLOR_BLOCKED: [f32; 4] = [1.0, 0.2, 0.2, 0.5];
+/// Slightly smaller than a block, so that the outline isn't cut by the neighbouring tiles.
+const HIGHLIGHT_HALF_SIZE: f32 = 0.48;
+
+// --------------------------------------------------
+// --- TEST ---
+// --------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::Cursor;
+    use crate::scene::Scene;
+    use cgmath::Point2;
+
+    #[test]
+    fn test_cursor_hovered_tile() {
+        let mut scene = Scene::new("level0").unwrap();
+        scene.observer.handle_resize((1280, 720));
+        let mut cursor = Cursor::new();
+        cursor.update(&scene.observer, &scene.level);
+        assert_eq!(cursor.tile(), None);
+
+        // the observer starts above the player spawn at (2, 2), a tile is 160 pixels wide
+        cursor.position = Some(Point2::new(640.0 + 160.0, 360.0 - 10.0));
+        cursor.update(&scene.observer, &scene.level);
+        assert_eq!(cursor.tile(), Some(Point2::new(3, 2)));
+        assert!(cursor.free);
+
+        cursor.position = Some(Point2::new(640.0, 360.0 - 2.0 * 160.0));
+        cursor.update(&scene.observer, &scene.level);
+        assert_eq!(cursor.tile(), Some(Point2::new(2, 0)));
+        assert!(!cursor.free);
+
+        // outside of the map
+        cursor.position = Some(Point2::new(0.0, 0.0));
+        cursor.update(&scene.observer, &scene.level);
+        assert_eq!(cursor.tile(), None);
+
+        cursor.enabled = false;
+        cursor.position = Some(Point2::new(640.0, 360.0));
+        cursor.update(&scene.observer, &scene.level);
+        assert_eq!(cursor.tile(), None);
+    }
+}
@@ -0,0 +1,209 @@
+use crate::{
+    combat::{body, Melee},
+    enemy::Enemy,
+    geometry::Aabb,
+    item::Item,
+    level::Level,
+    player::Player,
+    video,
+};
+use cgmath::Point2;
+
+// --------------------------------------------------
+// --- OVERLAY ---
+// --------------------------------------------------
+
+/// Toggleable debug overlay: draws level collision AABBs, character bodies,
+/// attack hitboxes and contact normals as lines on top of the scene.
+pub struct Overlay {
+    pub enabled: bool,
+    vertices: Vec<LineVertex>,
+
+    buffer: Option<wgpu::Buffer>,
+    buffer_capacity: usize,
+}
+
+impl Overlay {
+    /// Creates a new (disabled) debug overlay. The buffer is not created here,
+    /// see [`Overlay::prepare_render`].
+    pub fn new() -> Self {
+        Self { enabled: false, vertices: vec![], buffer: None, buffer_capacity: 0 }
+    }
+
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+        log::info!("debug overlay {}", if self.enabled { "enabled" } else { "disabled" });
+    }
+
+    /// Collect the lines to draw from the current state of the scene.
+    pub fn build(&mut self, level: &Level, player: &Player, enemies: &[Enemy], items: &[Item]) {
+        self.vertices.clear();
+
+        level.collision.aabbs.iter().for_each(|aabb| self.push_aabb(aabb, COLOR_LEVEL));
+        items.iter().for_each(|item| self.push_aabb(&item.body(), COLOR_ITEM));
+
+        for enemy in enemies.iter() {
+            let color = if enemy.is_alive() { COLOR_ENEMY } else { COLOR_DEAD };
+            self.push_aabb(&body(enemy.animation.position), color);
+            if enemy.melee.is_swinging() {
+                let hitbox = Melee::hitbox(enemy.animation.position, enemy.facing());
+                self.push_aabb(&hitbox, Self::hitbox_color(enemy.animation.is_hit_frame()));
+            }
+        }
+
+        let position = player.animation.position;
+        self.push_aabb(&body(position), COLOR_PLAYER);
+        if player.melee.is_swinging() {
+            let hitbox = Melee::hitbox(position, player.facing());
+            self.push_aabb(&hitbox, Self::hitbox_color(player.animation.is_hit_frame()));
+        }
+        for normal in player.contact_normals.iter() {
+            self.push_line(position, position + normal * NORMAL_LENGTH, COLOR_NORMAL);
+        }
+    }
+
+    /// Upload the lines to the GPU, the vertex buffer grows when the lines don't fit into it.
+    pub fn prepare_render(&mut self, video: &video::Video) {
+        if self.buffer.is_none() || self.buffer_capacity < self.vertices.len() {
+            self.buffer_capacity = self.vertices.len().next_power_of_two();
+            self.buffer = Some(video.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("debug_overlay_vertex_buffer"),
+                size: (self.buffer_capacity * std::mem::size_of::<LineVertex>()) as u64,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }));
+        }
+        if let Some(buffer) = self.buffer.as_ref().filter(|_| !self.vertices.is_empty()) {
+            video.queue.write_buffer(buffer, 0, bytemuck::cast_slice(&self.vertices));
+        }
+    }
+
+    /// Render the lines, the debug pipeline and the observer bind group must be set already.
+    pub fn render(&self, rp: &mut wgpu::RenderPass) {
+        if let Some(buffer) = &self.buffer {
+            rp.set_vertex_buffer(0, buffer.slice(..));
+            rp.draw(0..self.vertices.len() as u32, 0..1);
+        }
+    }
+
+    /// Little helper function.
+    fn hitbox_color(hit_frame: bool) -> [f32; 4] {
+        if hit_frame {
+            COLOR_HITBOX_ACTIVE
+        } else {
+            COLOR_HITBOX
+        }
+    }
+
+    /// Push 4 lines of the AABB outline.
+    fn push_aabb(&mut self, aabb: &Aabb, color: [f32; 4]) {
+        self.vertices.extend(aabb_lines(aabb, color));
+    }
+
+    fn push_line(&mut self, a: Point2<f32>, b: Point2<f32>, color: [f32; 4]) {
+        self.vertices.extend(line(a, b, color));
+    }
+}
+
+/// Vertices of 4 lines of the AABB outline.
+pub fn aabb_lines(aabb: &Aabb, color: [f32; 4]) -> [LineVertex; 8] {
+    let (a, b) = (aabb.min, aabb.max);
+    let corners = [a, Point2::new(b.x, a.y), b, Point2::new(a.x, b.y)];
+    let mut vertices = [LineVertex::new([0.0; 3], color); 8];
+    for i in 0..corners.len() {
+        let [from, to] = line(corners[i], corners[(i + 1) % corners.len()], color);
+        vertices[i * 2] = from;
+        vertices[i * 2 + 1] = to;
+    }
+    vertices
+}
+
+/// Vertices of a single line in the world (XZ plane).
+fn line(a: Point2<f32>, b: Point2<f32>, color: [f32; 4]) -> [LineVertex; 2] {
+    [
+        LineVertex::new([a.x, LINE_Y_COORD, a.y], color),
+        LineVertex::new([b.x, LINE_Y_COORD, b.y], color),
+    ]
+}
+
+const COLOR_LEVEL: [f32; 4] = [0.2, 1.0, 0.2, 0.8];
+const COLOR_ITEM: [f32; 4] = [1.0, 0.9, 0.2, 0.8];
+const COLOR_PLAYER: [f32; 4] = [0.2, 0.6, 1.0, 1.0];
+const COLOR_ENEMY: [f32; 4] = [1.0, 0.5, 0.1, 1.0];
+const COLOR_DEAD: [f32; 4] = [0.5, 0.5, 0.5, 0.6];
+const COLOR_HITBOX: [f32; 4] = [1.0, 0.2, 0.2, 0.4];
+const COLOR_HITBOX_ACTIVE: [f32; 4] = [1.0, 0.1, 0.1, 1.0];
+const COLOR_NORMAL: [f32; 4] = [1.0, 0.2, 1.0, 1.0];
+const NORMAL_LENGTH: f32 = 0.5;
+/// Lines are drawn after everything else, so they are always on top regardless of the height.
+const LINE_Y_COORD: f32 = 0.0;
+
+// --------------------------------------------------
+// --- LINE VERTEX ---
+// --------------------------------------------------
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LineVertex {
+    position: [f32; 3],
+    color: [f32; 4],
+}
+
+impl LineVertex {
+    pub fn new(position: [f32; 3], color: [f32; 4]) -> Self {
+        Self { position, color }
+    }
+
+    pub fn get_layout<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<LineVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+// --------------------------------------------------
+// --- TEST ---
+// --------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::{Overlay, COLOR_HITBOX, COLOR_HITBOX_ACTIVE, COLOR_NORMAL};
+    use crate::{
+        input::{Action, Input},
+        scene::Scene,
+    };
+    use cgmath::Point2;
+
+    #[test]
+    fn test_overlay_lines() {
+        let mut scene = Scene::new("level0").unwrap();
+        let mut overlay = Overlay::new();
+        overlay.build(&scene.level, &scene.player, &scene.enemies, &scene.items);
+        let boxes = scene.level.collision.aabbs.len() + scene.enemies.len() + scene.items.len() + 1;
+        assert_eq!(overlay.vertices.len(), boxes * 8);
+
+        // walk into the top wall and swing
+        scene.player.teleport(Point2::new(4.0, 1.0));
+        let mut input = Input::new();
+        input.set(Action::MoveUp, true);
+        input.set(Action::Attack, true);
+        (0..10).for_each(|_| scene.advance(&mut input));
+        overlay.build(&scene.level, &scene.player, &scene.enemies, &scene.items);
+        let colors: Vec<_> = overlay.vertices.iter().map(|v| v.color).collect();
+        assert!(colors.contains(&COLOR_NORMAL));
+        assert!(colors.contains(&COLOR_HITBOX) || colors.contains(&COLOR_HITBOX_ACTIVE));
+    }
+}
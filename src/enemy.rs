@@ -0,0 +1,388 @@
+use crate::{
+    combat::{Hit, Melee},
+    geometry::Direction,
+    level::{gen::Rng, tile_at, Level, SpawnKind},
+    sprite::{Action, Animation, Mesh, ANIM_LOOP_SECS},
+    stats::Stats,
+    video,
+};
+use cgmath::{InnerSpace, MetricSpace, Point2, Vector2};
+
+// --------------------------------------------------
+// --- ENEMY ---
+// --------------------------------------------------
+
+/// Represents a single enemy character.
+/// All the speeds are per second, all the timers are in seconds.
+pub struct Enemy {
+    pub kind: EnemyKind,
+    /// Map position of the level spawn marker, `None` for the enemies spawned otherwise.
+    pub spawn: Option<Point2<usize>>,
+    pub position: Point2<f32>,
+    prev_position: Point2<f32>,
+    facing: Direction,
+    velocity: Vector2<f32>,
+    knockback: Vector2<f32>,
+
+    home: Point2<f32>,
+    pub state: EnemyState,
+    rng: Rng,
+    /// Waypoints of the chase around the walls (the next one first), empty when the target
+    /// can be reached in a straight line. See [`Enemy::navigate`].
+    path: Vec<Point2<f32>>,
+    /// Block of the target and the navigation grid generation the path was found for.
+    path_goal: Option<(Point2<usize>, u64)>,
+
+    pub stats: Stats,
+    pub melee: Melee,
+
+    pub animation: Animation,
+    pub mesh: Option<Mesh>,
+}
+
+impl Enemy {
+    /// Creates a new enemy instance. The seed drives its (otherwise deterministic) wandering.
+    /// The mesh is not created here, see [`Enemy::prepare_render`].
+    pub fn new(kind: EnemyKind, position: Point2<f32>, seed: u64) -> Self {
+        Self {
+            kind,
+            spawn: None,
+            position,
+            prev_position: position,
+            facing: Direction::Down,
+            velocity: Vector2::new(0.0, 0.0),
+            knockback: Vector2::new(0.0, 0.0),
+
+            home: position,
+            state: EnemyState::Idle { timer: IDLE_TIME_MIN },
+            rng: Rng::new(seed),
+            path: vec![],
+            path_goal: None,
+
+            stats: Stats::new(ENEMY_HP, 0.0, 0.0, 0.0),
+            melee: Melee::new(ENEMY_MELEE_DAMAGE, ENEMY_MELEE_KNOCKBACK, ENEMY_MELEE_COOLDOWN),
+
+            animation: Animation::new(),
+            mesh: None,
+        }
+    }
+
+    /// Apply the gameplay tunables, the enemy gets fully healed.
+    pub fn apply_config(&mut self, config: &crate::config::GameplayConfig) {
+        self.stats = Stats::new(config.enemy_max_hp, 0.0, 0.0, 0.0);
+        self.melee =
+            Melee::new(config.enemy_melee_damage, ENEMY_MELEE_KNOCKBACK, ENEMY_MELEE_COOLDOWN);
+    }
+
+    /// Creates all the enemies placed by the level spawn markers.
+    pub fn spawn_all(level: &Level) -> Vec<Self> {
+        let mut enemies = vec![];
+        for spawn in level.spawns.iter() {
+            let SpawnKind::Enemy(glyph) = spawn.kind else {
+                continue;
+            };
+            let Some(kind) = EnemyKind::from_glyph(glyph) else {
+                log::warn!("unknown enemy {:?} at {:?}", glyph, spawn.position);
+                continue;
+            };
+            let seed = (spawn.position.x as u64) << 32 | spawn.position.y as u64;
+            let enemy = Self::new(kind, spawn.world_position(), seed);
+            enemies.push(Self { spawn: Some(spawn.position), ..enemy });
+        }
+        enemies
+    }
+
+    /// Create the rendering resources, if they are not created yet.
+    /// The mesh is drawn between the previous and the current tick positions, `alpha` is in `0..1`.
+    pub fn prepare_render(&mut self, video: &video::Video, alpha: f32) {
+        if self.mesh.is_none() {
+            self.mesh = Some(Mesh::new(video, &format!("enemy_{:?}", self.kind)));
+        }
+        self.animation.position = self.prev_position + (self.position - self.prev_position) * alpha;
+    }
+
+    /// Advance the enemy state machine, physics, etc. by `dt` seconds.
+    /// `target` is the position of the character the enemy is hostile to, if there is one alive.
+    pub fn advance(&mut self, target: Option<Point2<f32>>, dt: f32) {
+        self.prev_position = self.position;
+        self.state = self.think(target, dt);
+        self.melee.advance(dt);
+
+        // hostile states are picked only when there is a target
+        let target = target.unwrap_or(self.position);
+
+        self.velocity = match self.state {
+            EnemyState::Wander { target, .. } => self.heading(target) * WANDER_SPEED,
+            EnemyState::Chase => {
+                self.heading(self.path.first().copied().unwrap_or(target)) * CHASE_SPEED
+            }
+            _ => Vector2::new(0.0, 0.0),
+        };
+        self.position += (self.velocity + self.knockback) * dt;
+        self.knockback *= KNOCKBACK_SLOWDOWN.powf(dt);
+
+        let walk = Animation::is_walking(self.velocity);
+        match self.state {
+            EnemyState::Attack | EnemyState::Chase => {
+                self.facing = Direction::from_velocity(target - self.position)
+            }
+            _ if walk => self.facing = Direction::from_velocity(self.velocity),
+            _ => {}
+        }
+        if self.state == EnemyState::Attack && self.melee.start() {
+            self.animation.restart();
+        }
+
+        let action = match self.state {
+            EnemyState::Dead => Action::Death,
+            EnemyState::Hurt { .. } => Action::Hurt,
+            _ if self.melee.is_swinging() => Action::Attack,
+            _ => Action::None,
+        };
+        self.animation.advance(self.facing, walk, action, dt);
+    }
+
+    /// Find the way to the target while chasing it, must be called before [`Enemy::advance`].
+    /// The path is found again only when the target moves to another block, the level changes,
+    /// or the enemy gets pushed off the path (e.g. knocked back) and can't walk straight
+    /// to the next waypoint anymore.
+    pub fn navigate(&mut self, target: Option<Point2<f32>>, nav: &mut crate::nav::Nav) {
+        let target = target.filter(|_| self.state == EnemyState::Chase);
+        let Some(target) = target.filter(|it| !nav.is_clear(self.position, *it)) else {
+            self.path.clear();
+            self.path_goal = None;
+            return;
+        };
+        let goal = tile_at(target, nav.size()).map(|it| (it, nav.generation()));
+        let off_path = self.path.first().is_some_and(|next| {
+            let tiles = tile_at(self.position, nav.size()).zip(tile_at(*next, nav.size()));
+            let near = tiles.is_some_and(|(a, b)| a.x.abs_diff(b.x) <= 1 && a.y.abs_diff(b.y) <= 1);
+            !near && !nav.is_clear(self.position, *next)
+        });
+        if goal != self.path_goal || off_path {
+            self.path = nav.path(self.position, target).unwrap_or_default();
+            self.path_goal = goal;
+        }
+        while self.path.first().is_some_and(|it| self.position.distance(*it) <= WAYPOINT_REACHED) {
+            self.path.remove(0);
+        }
+    }
+
+    /// Apply a hit landed by the player, the enemy gets knocked back or dies.
+    pub fn take_hit(&mut self, hit: Hit) {
+        self.stats.take_damage(hit.damage);
+        self.knockback = hit.knockback;
+        self.melee.cancel();
+        self.state = if self.is_alive() {
+            EnemyState::Hurt { timer: ANIM_LOOP_SECS }
+        } else {
+            EnemyState::Dead
+        };
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.stats.is_alive()
+    }
+
+    /// Direction the enemy looks at, towards the player during a fight.
+    pub fn facing(&self) -> Direction {
+        self.facing
+    }
+
+    /// Pick the next state of the state machine.
+    fn think(&mut self, target: Option<Point2<f32>>, dt: f32) -> EnemyState {
+        match self.state {
+            EnemyState::Dead => return EnemyState::Dead,
+            EnemyState::Hurt { timer } if timer > dt => {
+                return EnemyState::Hurt { timer: timer - dt }
+            }
+            _ => {}
+        }
+
+        if let Some(target) = target {
+            let distance = self.position.distance(target);
+            let hostile = matches!(self.state, EnemyState::Chase | EnemyState::Attack);
+            if distance <= ATTACK_RANGE {
+                return EnemyState::Attack;
+            }
+            if distance <= CHASE_RANGE || (hostile && distance <= CHASE_LOSE_RANGE) {
+                return EnemyState::Chase;
+            }
+        }
+
+        match self.state {
+            EnemyState::Idle { timer } if timer > dt => EnemyState::Idle { timer: timer - dt },
+            EnemyState::Idle { .. } => {
+                let x = self.home.x + (self.rng.unit() * 2.0 - 1.0) * WANDER_RADIUS;
+                let y = self.home.y + (self.rng.unit() * 2.0 - 1.0) * WANDER_RADIUS;
+                EnemyState::Wander { target: Point2::new(x, y), timer: WANDER_TIME }
+            }
+            EnemyState::Wander { target, timer }
+                if timer > dt && self.position.distance(target) > WANDER_REACHED =>
+            {
+                EnemyState::Wander { target, timer: timer - dt }
+            }
+            EnemyState::Wander { .. }
+            | EnemyState::Chase
+            | EnemyState::Attack
+            | EnemyState::Hurt { .. }
+            | EnemyState::Dead => {
+                let timer = IDLE_TIME_MIN + self.rng.unit() * (IDLE_TIME_MAX - IDLE_TIME_MIN);
+                EnemyState::Idle { timer }
+            }
+        }
+    }
+
+    /// Little helper function.
+    fn heading(&self, target: Point2<f32>) -> Vector2<f32> {
+        let delta = target - self.position;
+        if delta.magnitude2() > f32::EPSILON {
+            delta.normalize()
+        } else {
+            Vector2::new(0.0, 0.0)
+        }
+    }
+
+    pub fn sync_level_collision(&mut self, collision: &crate::level::Collision) {
+        self.position = collision.push_out(self.position, crate::combat::BODY_HALF_SIZE);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EnemyKind {
+    Orc3,
+}
+
+impl EnemyKind {
+    /// Enemy kind placed by the given level file glyph.
+    pub fn from_glyph(glyph: char) -> Option<Self> {
+        match glyph {
+            'O' => Some(Self::Orc3),
+            _ => None,
+        }
+    }
+
+    /// Enemy kind by its name, as used by the console commands.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "orc3" => Some(Self::Orc3),
+            _ => None,
+        }
+    }
+}
+
+/// Enemy AI state machine states.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EnemyState {
+    Idle { timer: f32 },
+    Wander { target: Point2<f32>, timer: f32 },
+    Chase,
+    Attack,
+    Hurt { timer: f32 },
+    Dead,
+}
+
+const IDLE_TIME_MIN: f32 = 1.0;
+const IDLE_TIME_MAX: f32 = 3.0;
+const WANDER_RADIUS: f32 = 3.0;
+const WANDER_TIME: f32 = 4.0;
+const WANDER_REACHED: f32 = 0.1;
+const WAYPOINT_REACHED: f32 = 0.1;
+const WANDER_SPEED: f32 = 0.6;
+const CHASE_SPEED: f32 = 1.0;
+const CHASE_RANGE: f32 = 4.0;
+const CHASE_LOSE_RANGE: f32 = 6.0;
+const ATTACK_RANGE: f32 = 1.0;
+pub const ENEMY_HP: f32 = 50.0;
+pub const ENEMY_MELEE_DAMAGE: f32 = 10.0;
+const ENEMY_MELEE_KNOCKBACK: f32 = 2.0;
+const ENEMY_MELEE_COOLDOWN: f32 = 1.2;
+/// Fraction of the knockback speed which is left after a second.
+const KNOCKBACK_SLOWDOWN: f32 = 0.002;
+
+// --------------------------------------------------
+// --- TEST ---
+// --------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::{Enemy, EnemyKind, EnemyState};
+    use crate::level::Level;
+    use cgmath::{MetricSpace, Point2};
+
+    const DT: f32 = 1.0 / 60.0;
+
+    #[test]
+    fn test_enemy_idle_then_wander() {
+        let mut enemy = Enemy::new(EnemyKind::Orc3, Point2::new(0.0, 0.0), 1);
+        let far = Point2::new(100.0, 100.0);
+        enemy.advance(Some(far), DT);
+        assert!(matches!(enemy.state, EnemyState::Idle { .. }));
+        (0..120).for_each(|_| enemy.advance(Some(far), DT));
+        assert!(matches!(enemy.state, EnemyState::Wander { .. }));
+    }
+
+    #[test]
+    fn test_enemy_chase_and_attack() {
+        let mut enemy = Enemy::new(EnemyKind::Orc3, Point2::new(0.0, 0.0), 1);
+        let player = Point2::new(3.0, 0.0);
+        enemy.advance(Some(player), DT);
+        assert_eq!(enemy.state, EnemyState::Chase);
+        assert!(enemy.position.x > 0.0);
+
+        (0..180).for_each(|_| enemy.advance(Some(player), DT));
+        assert_eq!(enemy.state, EnemyState::Attack);
+        assert!(enemy.position.distance(player) <= 1.0);
+    }
+
+    #[test]
+    fn test_enemy_chases_around_walls() {
+        let mut level = Level::new("level0").unwrap();
+        // in the corridor, the player is behind the corner of the wall
+        let mut enemy = Enemy::new(EnemyKind::Orc3, Point2::new(10.5, 5.0), 1);
+        let player = Point2::new(8.0, 2.0);
+        assert!(!level.nav.is_clear(enemy.position, player));
+        for _ in 0..300 {
+            enemy.navigate(Some(player), &mut level.nav);
+            enemy.advance(Some(player), DT);
+            enemy.sync_level_collision(&level.collision);
+        }
+        assert_eq!(enemy.state, EnemyState::Attack);
+    }
+
+    #[test]
+    fn test_enemy_repaths_when_pushed_off() {
+        let mut level = Level::new("level0").unwrap();
+        let mut enemy = Enemy::new(EnemyKind::Orc3, Point2::new(10.5, 5.0), 1);
+        let player = Point2::new(8.0, 2.0);
+        enemy.advance(Some(player), DT);
+        enemy.navigate(Some(player), &mut level.nav);
+        let next = enemy.path[0];
+
+        // knocked back into the room below, the old path can't be followed from there
+        enemy.position = Point2::new(3.0, 11.0);
+        assert!(!level.nav.is_clear(enemy.position, next));
+        enemy.navigate(Some(player), &mut level.nav);
+        assert_ne!(enemy.path[0], next);
+        assert!(level.nav.is_clear(enemy.position, enemy.path[0]));
+    }
+
+    #[test]
+    fn test_enemy_gives_up_chase() {
+        let mut enemy = Enemy::new(EnemyKind::Orc3, Point2::new(0.0, 0.0), 1);
+        enemy.advance(Some(Point2::new(3.0, 0.0)), DT);
+        enemy.advance(Some(Point2::new(5.0, 0.0)), DT);
+        assert_eq!(enemy.state, EnemyState::Chase);
+        enemy.advance(Some(Point2::new(50.0, 0.0)), DT);
+        assert!(matches!(enemy.state, EnemyState::Idle { .. }));
+    }
+
+    #[test]
+    fn test_enemy_ignores_dead_player() {
+        let mut enemy = Enemy::new(EnemyKind::Orc3, Point2::new(0.0, 0.0), 1);
+        enemy.advance(Some(Point2::new(0.5, 0.0)), DT);
+        assert_eq!(enemy.state, EnemyState::Attack);
+        enemy.advance(None, DT);
+        assert!(matches!(enemy.state, EnemyState::Idle { .. }));
+    }
+}
@@ -0,0 +1,167 @@
+use crate::input::{Button, Input};
+use cgmath::{InnerSpace, Vector2, Zero};
+
+// --------------------------------------------------
+// --- GAMEPAD ---
+// --------------------------------------------------
+
+/// Gamepads (with the `gamepad` feature), polled every frame into the [`Input`].
+/// A single gamepad is active: the first one connected, when it's disconnected
+/// another connected one takes over.
+pub struct Gamepads {
+    #[cfg_attr(not(feature = "gamepad"), allow(dead_code))]
+    deadzone: f32,
+    #[cfg(feature = "gamepad")]
+    gilrs: Option<gilrs::Gilrs>,
+    #[cfg(feature = "gamepad")]
+    active: Option<gilrs::GamepadId>,
+}
+
+impl Gamepads {
+    #[cfg(feature = "gamepad")]
+    pub fn new(config: &crate::config::GamepadConfig) -> Self {
+        let gilrs = match gilrs::Gilrs::new() {
+            Ok(gilrs) if config.enabled => Some(gilrs),
+            Ok(_) => None,
+            Err(e) => {
+                log::warn!("gamepads are not available: {}", e);
+                None
+            }
+        };
+        let active = gilrs.as_ref().and_then(|it| it.gamepads().next()).map(|(id, pad)| {
+            log::info!("gamepad {} active: {}", id, pad.name());
+            id
+        });
+        Self { deadzone: config.deadzone, gilrs, active }
+    }
+
+    #[cfg(not(feature = "gamepad"))]
+    pub fn new(config: &crate::config::GamepadConfig) -> Self {
+        if config.enabled {
+            log::info!("built without the gamepad feature, gamepads are not available");
+        }
+        Self { deadzone: config.deadzone }
+    }
+
+    /// Feed the gamepad events since the last poll into the input. The pressed buttons
+    /// are returned too, so the menus can be navigated with a gamepad.
+    #[cfg(feature = "gamepad")]
+    pub fn poll(&mut self, input: &mut Input) -> Vec<Button> {
+        use gilrs::{Axis, EventType};
+
+        let mut pressed = vec![];
+        let Some(gilrs) = &mut self.gilrs else {
+            return pressed;
+        };
+        while let Some(gilrs::Event { id, event, .. }) = gilrs.next_event() {
+            match event {
+                EventType::Connected => {
+                    log::info!("gamepad {} connected: {}", id, gilrs.gamepad(id).name());
+                    if self.active.is_none() {
+                        log::info!("gamepad {} active", id);
+                        self.active = Some(id);
+                    }
+                }
+                EventType::Disconnected => {
+                    log::info!("gamepad {} disconnected", id);
+                    if self.active == Some(id) {
+                        input.release_gamepad();
+                        self.active = gilrs.gamepads().map(|(it, _)| it).find(|it| *it != id);
+                        if let Some(active) = self.active {
+                            log::info!("gamepad {} active", active);
+                        }
+                    }
+                }
+                _ if self.active != Some(id) => {}
+                EventType::ButtonPressed(button, _) => {
+                    if let Some(button) = map_button(button) {
+                        input.handle_button(button, true);
+                        pressed.push(button);
+                    }
+                }
+                EventType::ButtonReleased(button, _) => {
+                    if let Some(button) = map_button(button) {
+                        input.handle_button(button, false);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // the sticks are read every time, so they survive the input being reset
+        if let Some(pad) = self.active.and_then(|id| gilrs.connected_gamepad(id)) {
+            // the stick Y axis goes up, the world Y axis goes down
+            let stick = |x, y| Vector2::new(pad.value(x), -pad.value(y));
+            input.move_stick =
+                apply_deadzone(stick(Axis::LeftStickX, Axis::LeftStickY), self.deadzone);
+            input.camera_stick =
+                apply_deadzone(stick(Axis::RightStickX, Axis::RightStickY), self.deadzone);
+        }
+        pressed
+    }
+
+    #[cfg(not(feature = "gamepad"))]
+    pub fn poll(&mut self, _input: &mut Input) -> Vec<Button> {
+        vec![]
+    }
+}
+
+/// Radial deadzone: the stick counts as centered below it, the rest of the range
+/// is rescaled to `0..=1` so that the speed doesn't jump at the deadzone edge.
+#[cfg_attr(not(feature = "gamepad"), allow(dead_code))]
+pub fn apply_deadzone(stick: Vector2<f32>, deadzone: f32) -> Vector2<f32> {
+    let length = stick.magnitude();
+    if length <= deadzone || length == 0.0 {
+        return Vector2::zero();
+    }
+    let scaled = ((length - deadzone) / (1.0 - deadzone)).min(1.0);
+    stick * (scaled / length)
+}
+
+#[cfg(feature = "gamepad")]
+fn map_button(button: gilrs::Button) -> Option<Button> {
+    match button {
+        gilrs::Button::South => Some(Button::South),
+        gilrs::Button::East => Some(Button::East),
+        gilrs::Button::North => Some(Button::North),
+        gilrs::Button::West => Some(Button::West),
+        gilrs::Button::LeftTrigger => Some(Button::LeftBumper),
+        gilrs::Button::RightTrigger => Some(Button::RightBumper),
+        gilrs::Button::LeftTrigger2 => Some(Button::LeftTrigger),
+        gilrs::Button::RightTrigger2 => Some(Button::RightTrigger),
+        gilrs::Button::Select => Some(Button::Select),
+        gilrs::Button::Start => Some(Button::Start),
+        gilrs::Button::LeftThumb => Some(Button::LeftThumb),
+        gilrs::Button::RightThumb => Some(Button::RightThumb),
+        gilrs::Button::DPadUp => Some(Button::DPadUp),
+        gilrs::Button::DPadDown => Some(Button::DPadDown),
+        gilrs::Button::DPadLeft => Some(Button::DPadLeft),
+        gilrs::Button::DPadRight => Some(Button::DPadRight),
+        _ => None,
+    }
+}
+
+// --------------------------------------------------
+// --- TEST ---
+// --------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::apply_deadzone;
+    use approx::assert_relative_eq;
+    use cgmath::{InnerSpace, Vector2};
+
+    #[test]
+    fn test_deadzone() {
+        assert_eq!(apply_deadzone(Vector2::new(0.1, -0.1), 0.2), Vector2::new(0.0, 0.0));
+        assert_eq!(apply_deadzone(Vector2::new(0.0, 0.0), 0.0), Vector2::new(0.0, 0.0));
+
+        // rescaled past the deadzone, keeping the direction
+        let stick = apply_deadzone(Vector2::new(0.0, 0.6), 0.2);
+        assert_relative_eq!(stick.y, 0.5);
+        assert_eq!(stick.x, 0.0);
+        let stick = apply_deadzone(Vector2::new(1.0, 1.0), 0.2);
+        assert_relative_eq!(stick.magnitude(), 1.0);
+        assert_relative_eq!(stick.x, stick.y);
+    }
+}
@@ -4,6 +4,7 @@ use cgmath::{AbsDiffEq, InnerSpace, Point2, RelativeEq, Vector2};
 // --- AABB ---
 // --------------------------------------------------
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Aabb {
     pub min: Point2<f32>,
     pub max: Point2<f32>,
@@ -14,6 +15,22 @@ impl Aabb {
         Self { min, max }
     }
 
+    /// Create a square AABB from its center and half size.
+    pub fn from_center(center: Point2<f32>, half_size: f32) -> Self {
+        Self::new(
+            Point2::new(center.x - half_size, center.y - half_size),
+            Point2::new(center.x + half_size, center.y + half_size),
+        )
+    }
+
+    /// Quick overlap test, without computing the contact details.
+    pub fn intersects(&self, other: &Self) -> bool {
+        self.min.x < other.max.x
+            && other.min.x < self.max.x
+            && self.min.y < other.max.y
+            && other.min.y < self.max.y
+    }
+
     pub fn check_contact(&self, other: &Self) -> AabbContact {
         // mtv == minimum translation vector
         let mut mtv_dist = f32::MAX;
@@ -161,6 +178,7 @@ impl RelativeEq for AabbContact {
 // --- Direction ---
 // --------------------------------------------------
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Direction {
     Up,
     Down,
@@ -169,6 +187,16 @@ pub enum Direction {
 }
 
 impl Direction {
+    /// Unit vector pointing in the direction.
+    pub fn to_vector(self) -> Vector2<f32> {
+        match self {
+            Direction::Up => Vector2::new(0.0, -1.0),
+            Direction::Down => Vector2::new(0.0, 1.0),
+            Direction::Left => Vector2::new(-1.0, 0.0),
+            Direction::Right => Vector2::new(1.0, 0.0),
+        }
+    }
+
     pub fn from_velocity(v: Vector2<f32>) -> Self {
         if v.x < -VEL_THRESHOLD && v.x.abs() > v.y.abs() {
             return Direction::Left;
@@ -184,7 +212,8 @@ impl Direction {
     }
 }
 
-const VEL_THRESHOLD: f32 = 0.0001;
+/// Velocity (units per second) below which there is no direction change.
+const VEL_THRESHOLD: f32 = 0.006;
 
 // --------------------------------------------------
 // --- Tests ---
@@ -239,6 +268,14 @@ mod tests {
         assert_relative_eq!(b.check_contact(&a), AabbContact::new(1.5015, Vector2::new(0.0, 1.0)));
     }
 
+    #[test]
+    fn test_aabb_intersects() {
+        let a = Aabb::from_center(Point2::new(0.0, 0.0), 1.0);
+        assert!(a.intersects(&Aabb::from_center(Point2::new(1.5, 1.5), 1.0)));
+        assert!(!a.intersects(&Aabb::from_center(Point2::new(2.0, 0.0), 1.0)));
+        assert!(!a.intersects(&Aabb::from_center(Point2::new(0.0, -2.5), 1.0)));
+    }
+
     #[test]
     fn test_aabb_intersection_case3() {
         let a = Aabb::new(Point2::new(10.0, 10.0), Point2::new(20.0, 20.0));
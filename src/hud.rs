@@ -0,0 +1,406 @@
+use crate::{
+    debug::LineVertex,
+    inventory::EquipSlot,
+    item::{self, ItemKind},
+    level::{Block, Level},
+    observer::Observer,
+    player::Player,
+    stats::Stats,
+    text::{Text, GLYPH_HEIGHT, GLYPH_WIDTH},
+    video::{self, Vertex},
+};
+use cgmath::{Matrix4, Point2, SquareMatrix, Vector2};
+use wgpu::util::DeviceExt;
+
+// --------------------------------------------------
+// --- HUD ---
+// --------------------------------------------------
+
+/// Screen space heads-up display: HP/MP bars, the inventory hotbar and the minimap.
+/// Rebuilt every frame from the scene; the labels go to the shared [`Text`] renderer.
+pub struct Hud {
+    pub enabled: bool,
+    /// Darken the whole screen, e.g. under a menu.
+    pub dimmed: bool,
+    /// Show the inventory panel with the item names above the hotbar.
+    pub inventory_open: bool,
+
+    /// Colored triangles, drawn with the HUD pipeline.
+    shapes: Vec<LineVertex>,
+    /// Item icons, drawn with the main pipeline and the item sheet texture.
+    icons: Vec<Vertex>,
+    binding: Option<HudBinding>,
+}
+
+impl Hud {
+    /// Creates a new empty HUD. The GPU resources are not created here,
+    /// see [`Hud::prepare_render`].
+    pub fn new() -> Self {
+        Self {
+            enabled: true,
+            dimmed: false,
+            inventory_open: false,
+            shapes: vec![],
+            icons: vec![],
+            binding: None,
+        }
+    }
+
+    /// Lay out the HUD for the current window size (see [`Observer::screen_size`]).
+    pub fn build(&mut self, observer: &Observer, level: &Level, player: &Player, text: &mut Text) {
+        self.shapes.clear();
+        self.icons.clear();
+        let (width, height) = observer.screen_size();
+        if width == 0 || height == 0 {
+            return;
+        }
+        let screen = Vector2::new(width as f32, height as f32);
+        let scale = ui_scale(height);
+        if self.dimmed {
+            self.push_rect(Point2::new(0.0, 0.0), screen, COLOR_DIM);
+        }
+        if !self.enabled {
+            return;
+        }
+
+        self.build_bars(&player.stats, scale, text);
+        self.build_hotbar(player, screen, scale, text);
+        self.build_minimap(level, player.position, screen, scale);
+        if self.inventory_open {
+            self.build_inventory(player, screen, scale, text);
+        }
+    }
+
+    /// Upload the HUD to the GPU, buffers are created or grown when needed.
+    pub fn prepare_render(&mut self, video: &video::Video) {
+        let binding = self.binding.get_or_insert_with(|| HudBinding::new(video));
+        binding.screen.update(video);
+        upload(video, &mut binding.shapes, bytemuck::cast_slice(&self.shapes), "hud_shapes");
+        upload(video, &mut binding.icons, bytemuck::cast_slice(&self.icons), "hud_icons");
+    }
+
+    /// Render the bars, slots and the minimap, the HUD pipeline must be set already.
+    pub fn render_shapes(&self, rp: &mut wgpu::RenderPass) {
+        let Some(binding) = &self.binding else {
+            return;
+        };
+        if let Some(buffer) = binding.shapes.as_ref().filter(|_| !self.shapes.is_empty()) {
+            rp.set_bind_group(video::BIND_GROUP_OBSERVER, &binding.screen.bind_group, &[]);
+            rp.set_vertex_buffer(0, buffer.slice(..));
+            rp.draw(0..self.shapes.len() as u32, 0..1);
+        }
+    }
+
+    /// Render the item icons, the main pipeline must be set already.
+    pub fn render_icons(&self, rp: &mut wgpu::RenderPass, sheet: &item::Sheet) {
+        let Some(binding) = &self.binding else {
+            return;
+        };
+        if let Some(buffer) = binding.icons.as_ref().filter(|_| !self.icons.is_empty()) {
+            rp.set_bind_group(video::BIND_GROUP_OBSERVER, &binding.screen.bind_group, &[]);
+            rp.set_bind_group(video::BIND_GROUP_TRANSFORM, &binding.transform_bind_group, &[]);
+            rp.set_bind_group(video::BIND_GROUP_TEXTURE, &sheet.texture.bind_group, &[]);
+            rp.set_vertex_buffer(0, buffer.slice(..));
+            rp.draw(0..self.icons.len() as u32, 0..1);
+        }
+    }
+
+    /// HP and MP bars in the top left corner.
+    fn build_bars(&mut self, stats: &Stats, scale: f32, text: &mut Text) {
+        let size = Vector2::new(HUD_BAR_WIDTH * scale, GLYPH_HEIGHT * scale);
+        let bars =
+            [("HP", stats.hp, stats.max_hp, COLOR_HP), ("MP", stats.mp, stats.max_mp, COLOR_MP)];
+        for (i, (label, value, max, color)) in bars.into_iter().enumerate() {
+            let y = HUD_MARGIN * scale + i as f32 * (size.y + HUD_GAP * scale);
+            let min = Point2::new(HUD_MARGIN * scale, y);
+            let fill = if max > 0.0 { (value / max).clamp(0.0, 1.0) } else { 0.0 };
+            self.push_rect(min, size, COLOR_BACKGROUND);
+            self.push_rect(min, Vector2::new(size.x * fill, size.y), color);
+
+            let label = format!("{} {}/{}", label, value.ceil(), max.ceil());
+            let position = min + Vector2::new(GLYPH_WIDTH * scale * 0.5, 0.0);
+            text.queue(&label, position, scale, COLOR_TEXT);
+        }
+    }
+
+    /// Inventory slots and the equipped items, centered at the bottom of the window.
+    fn build_hotbar(&mut self, player: &Player, screen: Vector2<f32>, scale: f32, text: &mut Text) {
+        let inventory = &player.inventory;
+        let (slot, gap) = (HUD_SLOT_SIZE * scale, HUD_GAP * scale);
+        let equip_slots = [EquipSlot::Weapon, EquipSlot::Armor];
+        let count = inventory.capacity() + equip_slots.len();
+        // the equipped items are separated from the inventory by an extra slot wide gap
+        let width = count as f32 * (slot + gap) - gap + slot;
+        let mut min = Point2::new((screen.x - width) / 2.0, screen.y - HUD_MARGIN * scale - slot);
+
+        for i in 0..inventory.capacity() {
+            self.push_rect(min, Vector2::new(slot, slot), COLOR_SLOT);
+            if let Some(stack) = inventory.slot(i) {
+                self.push_icon(min, slot, stack.kind);
+                if stack.count > 1 {
+                    let count = stack.count.to_string();
+                    let offset = Vector2::new(
+                        slot - count.len() as f32 * GLYPH_WIDTH * scale,
+                        slot - GLYPH_HEIGHT * scale,
+                    );
+                    text.queue(&count, min + offset, scale, COLOR_TEXT);
+                }
+            }
+            // number of the key which uses the slot
+            text.queue(&(i + 1).to_string(), min, scale, COLOR_SLOT_KEY);
+            min.x += slot + gap;
+        }
+
+        min.x += slot;
+        for equip_slot in equip_slots {
+            self.push_rect(min, Vector2::new(slot, slot), COLOR_EQUIP_SLOT);
+            if let Some(kind) = inventory.equipped(equip_slot) {
+                self.push_icon(min, slot, kind);
+            }
+            min.x += slot + gap;
+        }
+    }
+
+    /// Inventory panel: a line per slot and the equipped items, above the hotbar.
+    fn build_inventory(
+        &mut self,
+        player: &Player,
+        screen: Vector2<f32>,
+        scale: f32,
+        text: &mut Text,
+    ) {
+        let inventory = &player.inventory;
+        let mut lines: Vec<_> = (0..inventory.capacity())
+            .map(|i| match inventory.slot(i) {
+                Some(stack) => format!("{} {:?} x{}", i + 1, stack.kind, stack.count),
+                None => format!("{} -", i + 1),
+            })
+            .collect();
+        for (name, equip_slot) in [("Weapon", EquipSlot::Weapon), ("Armor", EquipSlot::Armor)] {
+            match inventory.equipped(equip_slot) {
+                Some(kind) => lines.push(format!("{}: {:?}", name, kind)),
+                None => lines.push(format!("{}: -", name)),
+            }
+        }
+
+        let columns = lines.iter().map(String::len).max().unwrap_or(0);
+        let (padding, line) = (HUD_GAP * 2.0 * scale, GLYPH_HEIGHT * scale);
+        let size = Vector2::new(
+            columns as f32 * GLYPH_WIDTH * scale + 2.0 * padding,
+            lines.len() as f32 * line + 2.0 * padding,
+        );
+        let bottom = screen.y - 2.0 * HUD_MARGIN * scale - HUD_SLOT_SIZE * scale;
+        let min = Point2::new((screen.x - size.x) / 2.0, bottom - size.y);
+        self.push_rect(min, size, COLOR_BACKGROUND);
+        for (i, label) in lines.iter().enumerate() {
+            let position = min + Vector2::new(padding, padding + i as f32 * line);
+            text.queue(label, position, scale, COLOR_TEXT);
+        }
+    }
+
+    /// Explored part of the level in the top right corner, with the player as a dot.
+    fn build_minimap(
+        &mut self,
+        level: &Level,
+        position: Point2<f32>,
+        screen: Vector2<f32>,
+        scale: f32,
+    ) {
+        let (w, h) = level.size();
+        if w == 0 || h == 0 {
+            return;
+        }
+        let cell = (HUD_MINIMAP_SIZE * scale / w.max(h) as f32).floor().max(1.0);
+        let size = Vector2::new(w as f32 * cell, h as f32 * cell);
+        let origin = Point2::new(screen.x - HUD_MARGIN * scale - size.x, HUD_MARGIN * scale);
+        self.push_rect(origin, size, COLOR_MINIMAP_BACKGROUND);
+
+        for x in 0..w {
+            for y in 0..h {
+                if !level.is_explored(x, y) {
+                    continue;
+                }
+                let color = match level.block(x, y) {
+                    _ if level.door(Point2::new(x, y)).is_some() => COLOR_MINIMAP_DOOR,
+                    Some(Block::Wall) => COLOR_MINIMAP_WALL,
+                    Some(Block::Free) => COLOR_MINIMAP_FLOOR,
+                    _ => continue,
+                };
+                let min = origin + Vector2::new(x as f32 * cell, y as f32 * cell);
+                self.push_rect(min, Vector2::new(cell, cell), color);
+            }
+        }
+
+        // blocks are centered at the integer world coordinates
+        let dot = cell.max(HUD_MINIMAP_DOT * scale);
+        let center = origin + Vector2::new(position.x + 0.5, position.y + 0.5) * cell;
+        let min = center - Vector2::new(dot, dot) / 2.0;
+        self.push_rect(min, Vector2::new(dot, dot), COLOR_MINIMAP_PLAYER);
+    }
+
+    /// Push 2 triangles of a filled rectangle.
+    fn push_rect(&mut self, min: Point2<f32>, size: Vector2<f32>, color: [f32; 4]) {
+        for [x, y] in quad(min, size) {
+            self.shapes.push(LineVertex::new([x, y, 0.0], color));
+        }
+    }
+
+    /// Push 2 triangles of an item icon, inset into the slot.
+    fn push_icon(&mut self, slot_min: Point2<f32>, slot_size: f32, kind: ItemKind) {
+        let inset = slot_size * HUD_ICON_INSET;
+        let min = slot_min + Vector2::new(inset, inset);
+        let size = Vector2::new(slot_size - 2.0 * inset, slot_size - 2.0 * inset);
+        let (a, b) = kind.get_texture_rect();
+        let tex_coords = [a, Point2::new(a.x, b.y), b, a, b, Point2::new(b.x, a.y)];
+        for ([x, y], uv) in quad(min, size).into_iter().zip(tex_coords) {
+            self.icons.push(Vertex::new((x, y, 0.0).into(), uv));
+        }
+    }
+}
+
+/// Scale of the screen space UI (HUD, menus) for the given window height,
+/// integer so that the bitmap font stays sharp.
+pub fn ui_scale(height: u32) -> f32 {
+    (height as f32 / HUD_REFERENCE_HEIGHT).floor().max(1.0)
+}
+
+/// Corners of the 2 triangles covering a rectangle, counter-clockwise once projected
+/// (the screen space Y axis goes down).
+fn quad(min: Point2<f32>, size: Vector2<f32>) -> [[f32; 2]; 6] {
+    let (x0, y0, x1, y1) = (min.x, min.y, min.x + size.x, min.y + size.y);
+    [[x0, y0], [x0, y1], [x1, y1], [x0, y0], [x1, y1], [x1, y0]]
+}
+
+/// Upload the vertices, the buffer is (re)created when they don't fit into it.
+fn upload(video: &video::Video, buffer: &mut Option<wgpu::Buffer>, bytes: &[u8], label: &str) {
+    if buffer.as_ref().is_none_or(|it| (it.size() as usize) < bytes.len()) {
+        *buffer = Some(video.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(&format!("{}_vertex_buffer", label)),
+            size: bytes.len().max(HUD_MIN_BUFFER_SIZE).next_power_of_two() as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        }));
+    }
+    if let Some(buffer) = buffer.as_ref().filter(|_| !bytes.is_empty()) {
+        video.queue.write_buffer(buffer, 0, bytes);
+    }
+}
+
+/// Window height at which the HUD is drawn 1:1, taller windows get it scaled up.
+const HUD_REFERENCE_HEIGHT: f32 = 720.0;
+/// Sizes in pixels, before scaling.
+const HUD_MARGIN: f32 = 16.0;
+const HUD_GAP: f32 = 4.0;
+const HUD_BAR_WIDTH: f32 = 200.0;
+const HUD_SLOT_SIZE: f32 = 56.0;
+const HUD_MINIMAP_SIZE: f32 = 200.0;
+const HUD_MINIMAP_DOT: f32 = 4.0;
+/// Part of the slot size left empty around an item icon.
+const HUD_ICON_INSET: f32 = 0.15;
+const HUD_MIN_BUFFER_SIZE: usize = 4096;
+
+const COLOR_TEXT: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const COLOR_DIM: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+const COLOR_BACKGROUND: [f32; 4] = [0.1, 0.05, 0.08, 0.8];
+const COLOR_HP: [f32; 4] = [0.75, 0.1, 0.15, 1.0];
+const COLOR_MP: [f32; 4] = [0.15, 0.3, 0.85, 1.0];
+const COLOR_SLOT: [f32; 4] = [0.1, 0.05, 0.08, 0.7];
+const COLOR_SLOT_KEY: [f32; 4] = [0.7, 0.7, 0.7, 1.0];
+const COLOR_EQUIP_SLOT: [f32; 4] = [0.3, 0.2, 0.05, 0.7];
+const COLOR_MINIMAP_BACKGROUND: [f32; 4] = [0.0, 0.0, 0.0, 0.5];
+const COLOR_MINIMAP_WALL: [f32; 4] = [0.6, 0.55, 0.5, 0.9];
+const COLOR_MINIMAP_FLOOR: [f32; 4] = [0.25, 0.2, 0.2, 0.9];
+const COLOR_MINIMAP_DOOR: [f32; 4] = [0.7, 0.45, 0.2, 0.9];
+const COLOR_MINIMAP_PLAYER: [f32; 4] = [0.2, 1.0, 0.4, 1.0];
+
+// --------------------------------------------------
+// --- BINDING ---
+// --------------------------------------------------
+
+/// GPU resources of the HUD. The icons go through the main pipeline,
+/// so they need an (identity) transform in place of a mesh transform.
+struct HudBinding {
+    screen: video::ScreenBinding,
+    #[allow(unused)]
+    transform_buffer: wgpu::Buffer,
+    transform_bind_group: wgpu::BindGroup,
+    shapes: Option<wgpu::Buffer>,
+    icons: Option<wgpu::Buffer>,
+}
+
+impl HudBinding {
+    fn new(video: &video::Video) -> Self {
+        let screen = video::ScreenBinding::new(video, "hud");
+        let identity = video::TransformUniform::new(Matrix4::identity(), [1.0; 3]);
+        let transform_buffer = video.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("hud_transform_buffer"),
+            contents: bytemuck::cast_slice(&[identity]),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let transform_bind_group = video.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("hud_transform_bind_group"),
+            layout: &video.bind_group_layouts[video::BIND_GROUP_TRANSFORM as usize],
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: transform_buffer.as_entire_binding(),
+            }],
+        });
+        Self { screen, transform_buffer, transform_bind_group, shapes: None, icons: None }
+    }
+}
+
+// --------------------------------------------------
+// --- TEST ---
+// --------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::Hud;
+    use crate::{item::ItemKind, scene::Scene, text::Text};
+
+    #[test]
+    fn test_hud_build() {
+        let mut scene = Scene::new("level0").unwrap();
+        let (mut hud, mut text) = (Hud::new(), Text::new());
+
+        // nothing to lay out before the window size is known
+        hud.build(&scene.observer, &scene.level, &scene.player, &mut text);
+        assert!(hud.shapes.is_empty());
+
+        scene.observer.handle_resize((1280, 720));
+        hud.build(&scene.observer, &scene.level, &scene.player, &mut text);
+        let unexplored = hud.shapes.len();
+        assert!(hud.icons.is_empty());
+
+        scene.level.update_fov(scene.player.position, 3.0);
+        scene.player.pick_item(ItemKind::Coin).unwrap();
+        scene.player.pick_item(ItemKind::Torch).unwrap();
+        hud.build(&scene.observer, &scene.level, &scene.player, &mut text);
+        assert!(hud.shapes.len() > unexplored);
+        assert_eq!(hud.icons.len(), 2 * 6);
+
+        // the inventory panel is a single backdrop with the text on it
+        let closed = hud.shapes.len();
+        hud.inventory_open = true;
+        hud.build(&scene.observer, &scene.level, &scene.player, &mut text);
+        assert_eq!(hud.shapes.len(), closed + 6);
+
+        // only the backdrop under a menu
+        (hud.enabled, hud.dimmed) = (false, true);
+        hud.build(&scene.observer, &scene.level, &scene.player, &mut text);
+        assert_eq!((hud.shapes.len(), hud.icons.len()), (6, 0));
+    }
+
+    #[test]
+    fn test_hud_fits_the_screen() {
+        let mut scene = Scene::new("level1").unwrap();
+        for size in [(800, 600), (1920, 1080), (3840, 2160)] {
+            scene.observer.handle_resize(size);
+            let (mut hud, mut text) = (Hud::new(), Text::new());
+            hud.build(&scene.observer, &scene.level, &scene.player, &mut text);
+            let floats: &[[f32; 7]] = bytemuck::cast_slice(&hud.shapes);
+            assert!(floats.iter().all(|v| v[0] >= 0.0 && v[0] <= size.0 as f32));
+            assert!(floats.iter().all(|v| v[1] >= 0.0 && v[1] <= size.1 as f32));
+        }
+    }
+}
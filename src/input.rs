@@ -1,55 +1,553 @@
+use cgmath::{Vector2, Zero};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
 use winit::event::{ElementState, KeyEvent};
 use winit::keyboard::{KeyCode, PhysicalKey::Code};
 
+// --------------------------------------------------
+// --- INPUT ---
+// --------------------------------------------------
+
+/// State of the player actions, fed by the key and gamepad button events through the
+/// [`Bindings`]. Besides whether an action is held down, the presses and releases are remembered
+/// until the end of the next simulation tick, so a short tap between two ticks isn't lost.
 pub struct Input {
-    pub key_up: bool,
-    pub key_down: bool,
-    pub key_left: bool,
-    pub key_right: bool,
+    pub bindings: Bindings,
+    pub button_bindings: Bindings<Button>,
 
-    pub key_w: bool,
-    pub key_s: bool,
-    pub key_a: bool,
-    pub key_d: bool,
+    /// Analog movement from a gamepad stick (past the deadzone, length up to 1),
+    /// zero without a gamepad. It takes over the digital movement actions.
+    pub move_stick: Vector2<f32>,
+    /// Analog camera panning from a gamepad stick, added to the camera actions.
+    pub camera_stick: Vector2<f32>,
 
-    pub key_space: bool,
+    /// Keys held down right now.
+    keys: HashSet<KeyCode>,
+    /// Gamepad buttons held down right now.
+    buttons: HashSet<Button>,
+    /// Actions held down right now.
+    down: HashSet<Action>,
+    /// Actions pressed since the last tick.
+    pressed: HashSet<Action>,
+    /// Actions released since the last tick.
+    released: HashSet<Action>,
 }
 
 impl Input {
     pub fn new() -> Self {
-        Self {
-            key_up: false,
-            key_down: false,
-            key_left: false,
-            key_right: false,
-
-            key_w: false,
-            key_s: false,
-            key_a: false,
-            key_d: false,
+        Self::with_bindings(Bindings::default(), Bindings::default())
+    }
 
-            key_space: false,
+    /// Creates a new input with all the keys released and the given bindings.
+    pub fn with_bindings(bindings: Bindings, button_bindings: Bindings<Button>) -> Self {
+        Self {
+            bindings,
+            button_bindings,
+            move_stick: Vector2::zero(),
+            camera_stick: Vector2::zero(),
+            keys: HashSet::new(),
+            buttons: HashSet::new(),
+            down: HashSet::new(),
+            pressed: HashSet::new(),
+            released: HashSet::new(),
         }
     }
 
     pub fn handle_key_event(&mut self, e: &KeyEvent) {
         if let Code(key_code) = e.physical_key {
-            let t = e.state == ElementState::Pressed;
-            match key_code {
-                KeyCode::ArrowUp => self.key_up = t,
-                KeyCode::ArrowDown => self.key_down = t,
-                KeyCode::ArrowLeft => self.key_left = t,
-                KeyCode::ArrowRight => self.key_right = t,
+            self.handle_key(key_code, e.state == ElementState::Pressed);
+        }
+    }
+
+    /// Press or release a key, every action bound to it follows.
+    pub fn handle_key(&mut self, key_code: KeyCode, down: bool) {
+        if down {
+            self.keys.insert(key_code);
+        } else {
+            self.keys.remove(&key_code);
+        }
+        for action in Action::ALL {
+            if self.bindings.is_bound(action, key_code) {
+                self.refresh(action);
+            }
+        }
+    }
+
+    /// Press or release a gamepad button, every action bound to it follows.
+    #[cfg_attr(not(feature = "gamepad"), allow(dead_code))]
+    pub fn handle_button(&mut self, button: Button, down: bool) {
+        if down {
+            self.buttons.insert(button);
+        } else {
+            self.buttons.remove(&button);
+        }
+        for action in Action::ALL {
+            if self.button_bindings.is_bound(action, button) {
+                self.refresh(action);
+            }
+        }
+    }
+
+    /// Release all the gamepad buttons and center the sticks, e.g. when the gamepad is gone.
+    #[cfg_attr(not(feature = "gamepad"), allow(dead_code))]
+    pub fn release_gamepad(&mut self) {
+        self.buttons.clear();
+        self.move_stick = Vector2::zero();
+        self.camera_stick = Vector2::zero();
+        Action::ALL.into_iter().for_each(|it| self.refresh(it));
+    }
+
+    /// An action is down while any of its keys or buttons is.
+    fn refresh(&mut self, action: Action) {
+        let down = self.bindings.get(action).iter().any(|it| self.keys.contains(it))
+            || self.button_bindings.get(action).iter().any(|it| self.buttons.contains(it));
+        self.set(action, down);
+    }
+
+    /// Press or release an action directly, the edges are tracked the same way as for keys.
+    pub fn set(&mut self, action: Action, down: bool) {
+        if down && self.down.insert(action) {
+            self.pressed.insert(action);
+        }
+        if !down && self.down.remove(&action) {
+            self.released.insert(action);
+        }
+    }
+
+    pub fn is_down(&self, action: Action) -> bool {
+        self.down.contains(&action)
+    }
+
+    /// Whether the action was pressed since the last tick.
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    /// Whether the action was released since the last tick.
+    #[cfg(test)]
+    pub fn just_released(&self, action: Action) -> bool {
+        self.released.contains(&action)
+    }
+
+    /// Like [`Input::just_pressed`], but the press is consumed. Meant for the actions
+    /// handled right in the event loop (toggles), not by the simulation ticks.
+    pub fn take_pressed(&mut self, action: Action) -> bool {
+        self.pressed.remove(&action)
+    }
+
+    /// Forget the presses and releases, called at the end of every simulation tick.
+    pub fn end_tick(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+    }
+}
+
+/// The pressed key of the event, key repeats don't count.
+pub fn pressed_key(e: &KeyEvent) -> Option<KeyCode> {
+    match e.physical_key {
+        Code(key_code) if e.state == ElementState::Pressed && !e.repeat => Some(key_code),
+        _ => None,
+    }
+}
+
+pub const INPUT_SLOT_KEYS: usize = crate::inventory::INVENTORY_CAPACITY;
+
+// --------------------------------------------------
+// --- ACTIONS ---
+// --------------------------------------------------
+
+/// Everything the player can do with the keyboard or a gamepad, see [`Bindings`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Attack,
+    /// Use the closest interactive object of the level.
+    Interact,
+    OpenInventory,
+    CameraUp,
+    CameraDown,
+    CameraLeft,
+    CameraRight,
+    ToggleConsole,
+    ToggleDebug,
+    Pause,
+    UseSlot1,
+    UseSlot2,
+    UseSlot3,
+    UseSlot4,
+    UseSlot5,
+    UseSlot6,
+    UseSlot7,
+    UseSlot8,
+}
+
+impl Action {
+    pub const ALL: [Self; 22] = [
+        Self::MoveUp,
+        Self::MoveDown,
+        Self::MoveLeft,
+        Self::MoveRight,
+        Self::Attack,
+        Self::Interact,
+        Self::OpenInventory,
+        Self::CameraUp,
+        Self::CameraDown,
+        Self::CameraLeft,
+        Self::CameraRight,
+        Self::ToggleConsole,
+        Self::ToggleDebug,
+        Self::Pause,
+        Self::UseSlot1,
+        Self::UseSlot2,
+        Self::UseSlot3,
+        Self::UseSlot4,
+        Self::UseSlot5,
+        Self::UseSlot6,
+        Self::UseSlot7,
+        Self::UseSlot8,
+    ];
+
+    /// Actions which can be rebound in the options menu, the rest only in the config file.
+    pub const REBINDABLE: [Self; 7] = [
+        Self::MoveUp,
+        Self::MoveDown,
+        Self::MoveLeft,
+        Self::MoveRight,
+        Self::Attack,
+        Self::Interact,
+        Self::OpenInventory,
+    ];
+
+    /// Actions moving the player character.
+    pub const MOVEMENT: [Self; 4] = [Self::MoveUp, Self::MoveDown, Self::MoveLeft, Self::MoveRight];
+
+    /// Actions using the inventory slots, in the slot order.
+    pub const SLOTS: [Self; INPUT_SLOT_KEYS] = [
+        Self::UseSlot1,
+        Self::UseSlot2,
+        Self::UseSlot3,
+        Self::UseSlot4,
+        Self::UseSlot5,
+        Self::UseSlot6,
+        Self::UseSlot7,
+        Self::UseSlot8,
+    ];
+
+    /// Name shown in the options menu.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::MoveUp => "Move up",
+            Self::MoveDown => "Move down",
+            Self::MoveLeft => "Move left",
+            Self::MoveRight => "Move right",
+            Self::Attack => "Attack",
+            Self::Interact => "Interact",
+            Self::OpenInventory => "Inventory",
+            Self::CameraUp => "Camera up",
+            Self::CameraDown => "Camera down",
+            Self::CameraLeft => "Camera left",
+            Self::CameraRight => "Camera right",
+            Self::ToggleConsole => "Console",
+            Self::ToggleDebug => "Debug overlay",
+            Self::Pause => "Pause",
+            Self::UseSlot1 => "Slot 1",
+            Self::UseSlot2 => "Slot 2",
+            Self::UseSlot3 => "Slot 3",
+            Self::UseSlot4 => "Slot 4",
+            Self::UseSlot5 => "Slot 5",
+            Self::UseSlot6 => "Slot 6",
+            Self::UseSlot7 => "Slot 7",
+            Self::UseSlot8 => "Slot 8",
+        }
+    }
+}
+
+// --------------------------------------------------
+// --- BINDINGS ---
+// --------------------------------------------------
+
+/// Keys (or gamepad buttons) of every action, any of them triggers it. Persisted in
+/// the config file: actions missing in the file keep their defaults, a single key can be
+/// written without the list.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(
+    from = "BTreeMap<Action, KeyList<T>>",
+    into = "BTreeMap<Action, Vec<T>>",
+    bound(serialize = "T: Binding", deserialize = "T: Binding")
+)]
+pub struct Bindings<T: Binding = KeyCode> {
+    keys: BTreeMap<Action, Vec<T>>,
+}
 
-                KeyCode::KeyW => self.key_w = t,
-                KeyCode::KeyS => self.key_s = t,
-                KeyCode::KeyA => self.key_a = t,
-                KeyCode::KeyD => self.key_d = t,
+impl<T: Binding> Bindings<T> {
+    pub fn get(&self, action: Action) -> &[T] {
+        self.keys.get(&action).map_or(&[], Vec::as_slice)
+    }
 
-                KeyCode::Space => self.key_space = t,
+    pub fn set(&mut self, action: Action, keys: Vec<T>) {
+        self.keys.insert(action, keys);
+    }
 
-                _ => {}
+    /// Replace the first (main) key of the action, the alternative keys stay.
+    /// Another action bound to the key gets the replaced key instead, the keys are swapped.
+    pub fn rebind(&mut self, action: Action, key: T) {
+        let keys = self.keys.entry(action).or_default();
+        if keys.first() == Some(&key) {
+            return;
+        }
+        keys.retain(|it| *it != key);
+        let replaced = match keys.first_mut() {
+            Some(first) => Some(std::mem::replace(first, key)),
+            None => {
+                keys.push(key);
+                None
+            }
+        };
+        for (_, keys) in self.keys.iter_mut().filter(|(it, _)| **it != action) {
+            let Some(i) = keys.iter().position(|it| *it == key) else {
+                continue;
+            };
+            match replaced {
+                Some(replaced) if !keys.contains(&replaced) => keys[i] = replaced,
+                _ => {
+                    keys.remove(i);
+                }
             }
         }
     }
+
+    /// Action the key is bound to, if any.
+    pub fn action_of(&self, key: T) -> Option<Action> {
+        self.keys.iter().find(|(_, keys)| keys.contains(&key)).map(|(action, _)| *action)
+    }
+
+    pub fn is_bound(&self, action: Action, key: T) -> bool {
+        self.get(action).contains(&key)
+    }
+
+    /// Keys of the action for the menus, e.g. `"KeyI, Tab"`.
+    pub fn describe(&self, action: Action) -> String {
+        match self.get(action) {
+            [] => "-".to_string(),
+            keys => keys.iter().map(|it| format!("{:?}", it)).collect::<Vec<_>>().join(", "),
+        }
+    }
+}
+
+impl<T: Binding> Default for Bindings<T> {
+    fn default() -> Self {
+        Self { keys: T::defaults().into_iter().collect() }
+    }
+}
+
+impl<T: Binding> From<BTreeMap<Action, KeyList<T>>> for Bindings<T> {
+    fn from(keys: BTreeMap<Action, KeyList<T>>) -> Self {
+        let mut bindings = Self::default();
+        for (action, keys) in keys {
+            match keys {
+                KeyList::One(key) => bindings.set(action, vec![key]),
+                KeyList::Many(keys) => bindings.set(action, keys),
+            }
+        }
+        bindings
+    }
+}
+
+impl<T: Binding> From<Bindings<T>> for BTreeMap<Action, Vec<T>> {
+    fn from(bindings: Bindings<T>) -> Self {
+        bindings.keys
+    }
+}
+
+/// Keys of an action in the config file: a single key or a list.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum KeyList<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+/// Something an action can be bound to, see [`Bindings`].
+pub trait Binding:
+    Copy + Eq + std::hash::Hash + std::fmt::Debug + Serialize + DeserializeOwned
+{
+    /// Default bindings of the actions.
+    fn defaults() -> Vec<(Action, Vec<Self>)>;
+}
+
+impl Binding for KeyCode {
+    fn defaults() -> Vec<(Action, Vec<Self>)> {
+        use KeyCode::*;
+        vec![
+            (Action::MoveUp, vec![ArrowUp]),
+            (Action::MoveDown, vec![ArrowDown]),
+            (Action::MoveLeft, vec![ArrowLeft]),
+            (Action::MoveRight, vec![ArrowRight]),
+            (Action::Attack, vec![Space]),
+            (Action::Interact, vec![KeyE]),
+            (Action::OpenInventory, vec![KeyI, Tab]),
+            (Action::CameraUp, vec![KeyW]),
+            (Action::CameraDown, vec![KeyS]),
+            (Action::CameraLeft, vec![KeyA]),
+            (Action::CameraRight, vec![KeyD]),
+            (Action::ToggleConsole, vec![Backquote]),
+            (Action::ToggleDebug, vec![F3]),
+            (Action::Pause, vec![Escape]),
+            (Action::UseSlot1, vec![Digit1]),
+            (Action::UseSlot2, vec![Digit2]),
+            (Action::UseSlot3, vec![Digit3]),
+            (Action::UseSlot4, vec![Digit4]),
+            (Action::UseSlot5, vec![Digit5]),
+            (Action::UseSlot6, vec![Digit6]),
+            (Action::UseSlot7, vec![Digit7]),
+            (Action::UseSlot8, vec![Digit8]),
+        ]
+    }
+}
+
+// --------------------------------------------------
+// --- GAMEPAD BUTTONS ---
+// --------------------------------------------------
+
+/// Gamepad buttons, named by the position (`south` is A on Xbox, cross on PlayStation).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Button {
+    South,
+    East,
+    North,
+    West,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    LeftThumb,
+    RightThumb,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+impl Button {
+    /// Key the button stands for in the menus, which are navigated with the keyboard keys.
+    pub fn menu_key(self) -> Option<KeyCode> {
+        match self {
+            Self::DPadUp => Some(KeyCode::ArrowUp),
+            Self::DPadDown => Some(KeyCode::ArrowDown),
+            Self::DPadLeft => Some(KeyCode::ArrowLeft),
+            Self::DPadRight => Some(KeyCode::ArrowRight),
+            Self::South => Some(KeyCode::Enter),
+            Self::East | Self::Start => Some(KeyCode::Escape),
+            _ => None,
+        }
+    }
+}
+
+impl Binding for Button {
+    fn defaults() -> Vec<(Action, Vec<Self>)> {
+        use Button::*;
+        vec![
+            (Action::MoveUp, vec![DPadUp]),
+            (Action::MoveDown, vec![DPadDown]),
+            (Action::MoveLeft, vec![DPadLeft]),
+            (Action::MoveRight, vec![DPadRight]),
+            (Action::Attack, vec![South, RightTrigger]),
+            (Action::Interact, vec![West]),
+            (Action::OpenInventory, vec![North]),
+            (Action::Pause, vec![Start]),
+            (Action::UseSlot1, vec![LeftBumper]),
+            (Action::UseSlot2, vec![RightBumper]),
+        ]
+    }
+}
+
+// --------------------------------------------------
+// --- TEST ---
+// --------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::{Action, Bindings, Button, Input};
+    use cgmath::Vector2;
+    use winit::keyboard::KeyCode;
+
+    #[test]
+    fn test_input_edges() {
+        let mut input = Input::new();
+        input.handle_key(KeyCode::Space, true);
+        assert!(input.is_down(Action::Attack) && input.just_pressed(Action::Attack));
+        input.end_tick();
+        assert!(input.is_down(Action::Attack) && !input.just_pressed(Action::Attack));
+
+        // a tap between two ticks is both pressed and released, but not down
+        input.handle_key(KeyCode::Space, false);
+        input.handle_key(KeyCode::Digit1, true);
+        input.handle_key(KeyCode::Digit1, false);
+        assert!(input.just_released(Action::Attack));
+        assert!(input.just_pressed(Action::UseSlot1) && !input.is_down(Action::UseSlot1));
+        input.end_tick();
+        assert!(!input.just_released(Action::Attack) && !input.just_pressed(Action::UseSlot1));
+
+        input.handle_key(KeyCode::F3, true);
+        assert!(input.take_pressed(Action::ToggleDebug));
+        assert!(!input.take_pressed(Action::ToggleDebug));
+    }
+
+    #[test]
+    fn test_input_multiple_keys() {
+        let mut input = Input::new();
+        input.handle_key(KeyCode::KeyI, true);
+        input.handle_key(KeyCode::Tab, true);
+        input.handle_key(KeyCode::KeyI, false);
+        // still held by the other key
+        assert!(input.is_down(Action::OpenInventory));
+        assert!(!input.just_released(Action::OpenInventory));
+        input.handle_key(KeyCode::Tab, false);
+        assert!(input.just_released(Action::OpenInventory));
+    }
+
+    #[test]
+    fn test_input_gamepad() {
+        let mut input = Input::new();
+        input.handle_key(KeyCode::Space, true);
+        input.handle_button(Button::South, true);
+        input.handle_key(KeyCode::Space, false);
+        // still held by the button
+        assert!(input.is_down(Action::Attack));
+
+        input.move_stick = Vector2::new(0.5, 0.0);
+        input.release_gamepad();
+        assert!(!input.is_down(Action::Attack) && input.just_released(Action::Attack));
+        assert_eq!(input.move_stick, Vector2::new(0.0, 0.0));
+    }
+
+    #[test]
+    fn test_bindings_rebind() {
+        let mut bindings: Bindings = Bindings::default();
+        bindings.rebind(Action::OpenInventory, KeyCode::KeyJ);
+        assert_eq!(bindings.get(Action::OpenInventory), [KeyCode::KeyJ, KeyCode::Tab]);
+        bindings.rebind(Action::OpenInventory, KeyCode::KeyJ);
+        assert_eq!(bindings.get(Action::OpenInventory), [KeyCode::KeyJ, KeyCode::Tab]);
+        bindings.rebind(Action::OpenInventory, KeyCode::Tab);
+        assert_eq!(bindings.get(Action::OpenInventory), [KeyCode::Tab]);
+        assert_eq!(bindings.describe(Action::OpenInventory), "Tab");
+
+        // a key bound to another action is swapped
+        bindings.rebind(Action::Attack, KeyCode::KeyE);
+        assert_eq!(bindings.get(Action::Attack), [KeyCode::KeyE]);
+        assert_eq!(bindings.get(Action::Interact), [KeyCode::Space]);
+        assert_eq!(bindings.action_of(KeyCode::Space), Some(Action::Interact));
+        bindings.rebind(Action::Interact, KeyCode::Tab);
+        assert_eq!(bindings.get(Action::Interact), [KeyCode::Tab]);
+        assert_eq!(bindings.get(Action::OpenInventory), [KeyCode::Space]);
+        bindings.set(Action::Interact, vec![]);
+        assert_eq!(bindings.describe(Action::Interact), "-");
+    }
 }
@@ -0,0 +1,275 @@
+use crate::{item::ItemKind, stats::Stats};
+use thiserror::Error;
+
+// --------------------------------------------------
+// --- INVENTORY ---
+// --------------------------------------------------
+
+/// Items carried by a character: a fixed number of slots holding stacks of the same kind,
+/// plus the equipped weapon and armor.
+pub struct Inventory {
+    slots: Vec<Option<Stack>>,
+    weapon: Option<ItemKind>,
+    armor: Option<ItemKind>,
+}
+
+impl Inventory {
+    /// Creates a new empty inventory with the given number of slots.
+    pub fn new(capacity: usize) -> Self {
+        Self { slots: vec![None; capacity], weapon: None, armor: None }
+    }
+
+    /// Put an item into the inventory, returns the slot it ended up in.
+    /// Items go onto an existing stack of the same kind first, then into the first empty slot.
+    pub fn add(&mut self, kind: ItemKind) -> Result<usize, InventoryError> {
+        let stack = self.slots.iter().position(|it| {
+            it.is_some_and(|stack| stack.kind == kind && stack.count < kind.max_stack())
+        });
+        if let Some(slot) = stack {
+            if let Some(stack) = self.slots[slot].as_mut() {
+                stack.count += 1;
+            }
+            return Ok(slot);
+        }
+
+        let slot = self.slots.iter().position(Option::is_none).ok_or(InventoryError::Full)?;
+        self.slots[slot] = Some(Stack { kind, count: 1 });
+        Ok(slot)
+    }
+
+    /// Take a single item out of the given slot.
+    pub fn take(&mut self, slot: usize) -> Result<ItemKind, InventoryError> {
+        let stack = self.slots.get_mut(slot).ok_or(InventoryError::InvalidSlot(slot))?;
+        let Some(Stack { kind, count }) = *stack else {
+            return Err(InventoryError::EmptySlot(slot));
+        };
+        *stack = (count > 1).then_some(Stack { kind, count: count - 1 });
+        Ok(kind)
+    }
+
+    /// Number of the slots, empty or not.
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Stack of items in the given slot, `None` for an empty or invalid slot.
+    pub fn slot(&self, slot: usize) -> Option<Stack> {
+        self.slots.get(slot).copied().flatten()
+    }
+
+    /// Total number of the items of the given kind, in all the slots.
+    pub fn count(&self, kind: ItemKind) -> u32 {
+        self.slots.iter().flatten().filter(|it| it.kind == kind).map(|it| it.count).sum()
+    }
+
+    /// Move an item from the given slot into its equip slot.
+    /// The previously equipped item (if any) goes back into the inventory.
+    pub fn equip(&mut self, slot: usize) -> Result<ItemKind, InventoryError> {
+        let kind = self.peek(slot)?;
+        let equip_slot = kind.equip_slot().ok_or(InventoryError::NotEquippable(kind))?;
+        let stack = self.slots[slot];
+        self.take(slot)?;
+
+        let previous = self.equipped_mut(equip_slot).replace(kind);
+        if let Some(previous) = previous {
+            if let Err(e) = self.add(previous) {
+                // no room for the previous item, undo everything
+                *self.equipped_mut(equip_slot) = Some(previous);
+                self.slots[slot] = stack;
+                return Err(e);
+            }
+        }
+        Ok(kind)
+    }
+
+    /// Use an item from the given slot: drink a potion, equip a weapon, etc.
+    /// Returns the kind of the used item.
+    pub fn use_item(&mut self, slot: usize, stats: &mut Stats) -> Result<ItemKind, InventoryError> {
+        let kind = self.peek(slot)?;
+        match kind {
+            ItemKind::HealthPotion => stats.heal(POTION_HP),
+            ItemKind::ManaPotion => stats.restore_mp(POTION_MP),
+            ItemKind::Torch | ItemKind::Shield => return self.equip(slot),
+            ItemKind::Coin | ItemKind::Key => return Err(InventoryError::NotUsable(kind)),
+        }
+        self.take(slot)
+    }
+
+    /// Item equipped in the given equip slot.
+    pub fn equipped(&self, equip_slot: EquipSlot) -> Option<ItemKind> {
+        match equip_slot {
+            EquipSlot::Weapon => self.weapon,
+            EquipSlot::Armor => self.armor,
+        }
+    }
+
+    /// Extra damage dealt thanks to the equipped weapon.
+    pub fn damage_bonus(&self) -> f32 {
+        self.weapon.map_or(0.0, |it| it.damage_bonus())
+    }
+
+    /// Damage absorbed by the equipped armor.
+    pub fn defense(&self) -> f32 {
+        self.armor.map_or(0.0, |it| it.defense())
+    }
+
+    /// Kind of the items in the given slot.
+    fn peek(&self, slot: usize) -> Result<ItemKind, InventoryError> {
+        let stack = self.slots.get(slot).ok_or(InventoryError::InvalidSlot(slot))?;
+        stack.map(|it| it.kind).ok_or(InventoryError::EmptySlot(slot))
+    }
+
+    /// Little helper function.
+    fn equipped_mut(&mut self, equip_slot: EquipSlot) -> &mut Option<ItemKind> {
+        match equip_slot {
+            EquipSlot::Weapon => &mut self.weapon,
+            EquipSlot::Armor => &mut self.armor,
+        }
+    }
+}
+
+/// Some items of the same kind occupying a single inventory slot.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stack {
+    pub kind: ItemKind,
+    pub count: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EquipSlot {
+    Weapon,
+    Armor,
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum InventoryError {
+    #[error("inventory is full")]
+    Full,
+
+    #[error("invalid slot {0}")]
+    InvalidSlot(usize),
+
+    #[error("slot {0} is empty")]
+    EmptySlot(usize),
+
+    #[error("{0:?} cannot be used")]
+    NotUsable(ItemKind),
+
+    #[error("{0:?} cannot be equipped")]
+    NotEquippable(ItemKind),
+}
+
+pub const INVENTORY_CAPACITY: usize = 8;
+const POTION_HP: f32 = 40.0;
+const POTION_MP: f32 = 25.0;
+
+// --------------------------------------------------
+// --- TEST ---
+// --------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::{EquipSlot, Inventory, InventoryError, Stack};
+    use crate::{item::ItemKind, stats::Stats};
+
+    #[test]
+    fn test_inventory_stacking() {
+        let mut inventory = Inventory::new(2);
+        assert_eq!(inventory.add(ItemKind::Coin), Ok(0));
+        assert_eq!(inventory.add(ItemKind::Key), Ok(1));
+        assert_eq!(inventory.add(ItemKind::Coin), Ok(0));
+        assert_eq!(inventory.slots[0], Some(Stack { kind: ItemKind::Coin, count: 2 }));
+        assert_eq!(inventory.add(ItemKind::HealthPotion), Err(InventoryError::Full));
+        assert_eq!(inventory.count(ItemKind::Coin), 2);
+    }
+
+    #[test]
+    fn test_inventory_full_stack_takes_new_slot() {
+        let mut inventory = Inventory::new(3);
+        let max = ItemKind::HealthPotion.max_stack();
+        for _ in 0..max {
+            assert_eq!(inventory.add(ItemKind::HealthPotion), Ok(0));
+        }
+        assert_eq!(inventory.add(ItemKind::HealthPotion), Ok(1));
+        assert_eq!(inventory.count(ItemKind::HealthPotion), max + 1);
+    }
+
+    #[test]
+    fn test_inventory_take() {
+        let mut inventory = Inventory::new(2);
+        inventory.add(ItemKind::Key).unwrap();
+        inventory.add(ItemKind::Key).unwrap();
+        assert_eq!(inventory.take(0), Ok(ItemKind::Key));
+        assert_eq!(inventory.take(0), Ok(ItemKind::Key));
+        assert_eq!(inventory.slots[0], None);
+        assert_eq!(inventory.take(0), Err(InventoryError::EmptySlot(0)));
+        assert_eq!(inventory.take(5), Err(InventoryError::InvalidSlot(5)));
+    }
+
+    #[test]
+    fn test_inventory_use_potions() {
+        let mut inventory = Inventory::new(4);
+        let mut stats = Stats::new(100.0, 0.0, 50.0, 0.0);
+        stats.take_damage(60.0);
+        stats.mp = 0.0;
+        inventory.add(ItemKind::HealthPotion).unwrap();
+        inventory.add(ItemKind::ManaPotion).unwrap();
+
+        assert_eq!(inventory.use_item(0, &mut stats), Ok(ItemKind::HealthPotion));
+        assert_eq!(stats.hp, 80.0);
+        assert_eq!(inventory.use_item(1, &mut stats), Ok(ItemKind::ManaPotion));
+        assert_eq!(stats.mp, 25.0);
+        assert_eq!(inventory.slots, vec![None; 4]);
+    }
+
+    #[test]
+    fn test_inventory_use_not_usable() {
+        let mut inventory = Inventory::new(1);
+        let mut stats = Stats::new(100.0, 0.0, 50.0, 0.0);
+        inventory.add(ItemKind::Coin).unwrap();
+        let actual = inventory.use_item(0, &mut stats);
+        assert_eq!(actual, Err(InventoryError::NotUsable(ItemKind::Coin)));
+        assert_eq!(inventory.count(ItemKind::Coin), 1);
+        assert_eq!(inventory.equip(0), Err(InventoryError::NotEquippable(ItemKind::Coin)));
+    }
+
+    #[test]
+    fn test_inventory_equip() {
+        let mut inventory = Inventory::new(2);
+        let mut stats = Stats::new(100.0, 0.0, 50.0, 0.0);
+        inventory.add(ItemKind::Torch).unwrap();
+        inventory.add(ItemKind::Shield).unwrap();
+
+        assert_eq!(inventory.use_item(0, &mut stats), Ok(ItemKind::Torch));
+        assert_eq!(inventory.equip(1), Ok(ItemKind::Shield));
+        assert_eq!(inventory.equipped(EquipSlot::Weapon), Some(ItemKind::Torch));
+        assert_eq!(inventory.equipped(EquipSlot::Armor), Some(ItemKind::Shield));
+        assert_eq!(inventory.slots, vec![None; 2]);
+        assert_eq!(inventory.damage_bonus(), ItemKind::Torch.damage_bonus());
+        assert_eq!(inventory.defense(), ItemKind::Shield.defense());
+
+        // the previously equipped weapon goes back into the inventory
+        inventory.add(ItemKind::Torch).unwrap();
+        assert_eq!(inventory.equip(0), Ok(ItemKind::Torch));
+        assert_eq!(inventory.count(ItemKind::Torch), 1);
+    }
+
+    #[test]
+    fn test_inventory_equip_no_room() {
+        let mut inventory = Inventory::new(1);
+        inventory.add(ItemKind::Torch).unwrap();
+        inventory.equip(0).unwrap();
+        inventory.add(ItemKind::Torch).unwrap();
+        // the slot is freed by the new weapon, so the old one fits
+        assert_eq!(inventory.equip(0), Ok(ItemKind::Torch));
+
+        let mut inventory = Inventory::new(1);
+        inventory.add(ItemKind::Torch).unwrap();
+        inventory.equip(0).unwrap();
+        // not possible with the current item kinds, but a stack of weapons never frees the slot
+        inventory.slots[0] = Some(Stack { kind: ItemKind::Torch, count: 2 });
+        assert_eq!(inventory.equip(0), Err(InventoryError::Full));
+        assert_eq!(inventory.slots[0], Some(Stack { kind: ItemKind::Torch, count: 2 }));
+        assert_eq!(inventory.equipped(EquipSlot::Weapon), Some(ItemKind::Torch));
+    }
+}
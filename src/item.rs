@@ -0,0 +1,319 @@
+use crate::{
+    geometry::Aabb,
+    inventory::EquipSlot,
+    level::{Level, SpawnKind},
+    video::{self, TextureGroup, Vertex},
+};
+use cgmath::{Matrix4, Point2};
+use thiserror::Error;
+use wgpu::util::DeviceExt;
+
+// --------------------------------------------------
+// --- ITEM ---
+// --------------------------------------------------
+
+/// Represents a single item lying on the floor, waiting to be picked up.
+pub struct Item {
+    pub kind: ItemKind,
+    /// Map position of the level spawn marker, `None` for the items placed otherwise.
+    pub spawn: Option<Point2<usize>>,
+    pub position: Point2<f32>,
+    pub mesh: Option<Mesh>,
+}
+
+impl Item {
+    /// Creates a new item instance. The mesh is not created here, see [`Item::prepare_render`].
+    pub fn new(kind: ItemKind, position: Point2<f32>) -> Self {
+        Self { kind, spawn: None, position, mesh: None }
+    }
+
+    /// Creates all the items placed by the level spawn markers.
+    pub fn spawn_all(level: &Level) -> Vec<Self> {
+        let mut items = vec![];
+        for spawn in level.spawns.iter() {
+            let SpawnKind::Item(glyph) = spawn.kind else {
+                continue;
+            };
+            let Some(kind) = ItemKind::from_glyph(glyph) else {
+                log::warn!("unknown item {:?} at {:?}", glyph, spawn.position);
+                continue;
+            };
+            items.push(Self {
+                spawn: Some(spawn.position),
+                ..Self::new(kind, spawn.world_position())
+            });
+        }
+        items
+    }
+
+    /// Create the rendering resources, if they are not created yet.
+    pub fn prepare_render(&mut self, video: &video::Video) {
+        if self.mesh.is_none() {
+            self.mesh = Some(Mesh::new(video, &format!("item_{:?}", self.kind)));
+        }
+    }
+
+    /// Area which has to be touched to pick the item up.
+    pub fn body(&self) -> Aabb {
+        Aabb::from_center(self.position, ITEM_HALF_SIZE)
+    }
+}
+
+/// Remove all the items touched by the given body, returns the removed ones.
+pub fn pick_up(items: &mut Vec<Item>, body: &Aabb) -> Vec<Item> {
+    let (picked, left) = items.drain(..).partition(|it| it.body().intersects(body));
+    *items = left;
+    picked
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ItemKind {
+    Coin,
+    HealthPotion,
+    ManaPotion,
+    Key,
+    Torch,
+    Shield,
+}
+
+impl ItemKind {
+    const ALL: [Self; 6] =
+        [Self::Coin, Self::HealthPotion, Self::ManaPotion, Self::Key, Self::Torch, Self::Shield];
+
+    /// Item kind placed by the given level file glyph.
+    pub fn from_glyph(glyph: char) -> Option<Self> {
+        match glyph {
+            'c' => Some(Self::Coin),
+            'h' => Some(Self::HealthPotion),
+            'm' => Some(Self::ManaPotion),
+            'k' => Some(Self::Key),
+            't' => Some(Self::Torch),
+            's' => Some(Self::Shield),
+            _ => None,
+        }
+    }
+
+    /// How many items of the kind fit into a single inventory slot.
+    pub fn max_stack(&self) -> u32 {
+        match self {
+            Self::Coin => 99,
+            Self::HealthPotion | Self::ManaPotion => 5,
+            Self::Key => 9,
+            Self::Torch | Self::Shield => 1,
+        }
+    }
+
+    /// Equip slot the item goes to, if it can be equipped at all.
+    pub fn equip_slot(&self) -> Option<EquipSlot> {
+        match self {
+            Self::Torch => Some(EquipSlot::Weapon),
+            Self::Shield => Some(EquipSlot::Armor),
+            _ => None,
+        }
+    }
+
+    /// Extra melee damage dealt while the item is equipped.
+    pub fn damage_bonus(&self) -> f32 {
+        match self {
+            Self::Torch => 15.0,
+            _ => 0.0,
+        }
+    }
+
+    /// Damage absorbed from every hit while the item is equipped.
+    pub fn defense(&self) -> f32 {
+        match self {
+            Self::Shield => 4.0,
+            _ => 0.0,
+        }
+    }
+
+    fn index(&self) -> usize {
+        Self::ALL.iter().position(|it| it == self).unwrap_or_default()
+    }
+
+    /// Texture coordinates of the top left and the bottom right corner of the item tile.
+    pub fn get_texture_rect(&self) -> (Point2<f32>, Point2<f32>) {
+        let (m, n) = (TEX_SIZE as f32, TEX_TILE_SIZE as f32);
+        let (u, v) = self.get_texture_position();
+        let (u, v) = (u as f32, v as f32);
+        (
+            Point2::new((n * u) / m, (n * v) / m),
+            Point2::new((n * (u + 1.0)) / m, (n * (v + 1.0)) / m),
+        )
+    }
+
+    /// Tile position (column, row) of the item in the dungeon tileset.
+    fn get_texture_position(&self) -> (u32, u32) {
+        match self {
+            Self::Coin => (6, 8),
+            Self::HealthPotion => (9, 8),
+            Self::ManaPotion => (7, 8),
+            Self::Key => (9, 9),
+            Self::Torch => (0, 9),
+            Self::Shield => (4, 7),
+        }
+    }
+}
+
+/// Half size of the item pickup area.
+const ITEM_HALF_SIZE: f32 = 0.25;
+
+// --------------------------------------------------
+// --- SHEET ---
+// --------------------------------------------------
+
+/// Shared rendering resources of the items: the tileset texture and a quad per item kind.
+pub struct Sheet {
+    pub texture: TextureGroup,
+    pub vertex_buffer: wgpu::Buffer,
+    pub index_buffer: wgpu::Buffer,
+}
+
+impl Sheet {
+    /// Creates a new item sprite sheet instance.
+    pub fn new(video: &video::Video) -> Result<Self, SheetError> {
+        let texture = TextureGroup::new(
+            video,
+            include_bytes!("../assets/dungeon/Dungeon_Tileset.png"),
+            "items",
+        )?;
+
+        let vertices = Self::build_vertices();
+        let vertex_buffer = video.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("item_sheet_vertex_buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let indices: Vec<u16> = [0, 2, 3, 0, 1, 2].into();
+        let index_buffer = video.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("item_sheet_index_buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        Ok(Self { texture, vertex_buffer, index_buffer })
+    }
+
+    /// Build vertices vector to be used to create a new vertex buffer, 4 vertices per item kind.
+    /// Internal helper for [`new`].
+    fn build_vertices() -> Vec<Vertex> {
+        let mut vertices = Vec::with_capacity(ItemKind::ALL.len() * VERTS_PER_ITEM as usize);
+        for kind in ItemKind::ALL {
+            let (a, b) = kind.get_texture_rect();
+            vertices.push(Vertex::new((-VERT_XZ_COORD, VERT_Y_COORD, -VERT_XZ_COORD).into(), a));
+            vertices.push(Vertex::new(
+                (-VERT_XZ_COORD, VERT_Y_COORD, VERT_XZ_COORD).into(),
+                Point2::new(a.x, b.y),
+            ));
+            vertices.push(Vertex::new((VERT_XZ_COORD, VERT_Y_COORD, VERT_XZ_COORD).into(), b));
+            vertices.push(Vertex::new(
+                (VERT_XZ_COORD, VERT_Y_COORD, -VERT_XZ_COORD).into(),
+                Point2::new(b.x, a.y),
+            ));
+        }
+        vertices
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum SheetError {
+    #[error("texture error: {0}")]
+    Texture(#[from] crate::video::TextureError),
+}
+
+const TEX_SIZE: u32 = 160;
+const TEX_TILE_SIZE: u32 = 16;
+const VERTS_PER_ITEM: u32 = 4;
+const VERT_XZ_COORD: f32 = 0.3;
+/// Items lie between the floor and the characters.
+const VERT_Y_COORD: f32 = -0.4;
+
+// --------------------------------------------------
+// --- MESH ---
+// --------------------------------------------------
+
+/// Represents a single item instance on the screen: its own transform uniform.
+/// Texture and geometry come from the shared [`Sheet`].
+pub struct Mesh {
+    pub buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl Mesh {
+    /// Creates a new item mesh instance.
+    pub fn new(video: &video::Video, label: &str) -> Self {
+        let buffer = video.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(&format!("{}_mesh_buffer", label)),
+            size: std::mem::size_of::<crate::video::TransformUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = video.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&format!("{}_mesh_bind_group", label)),
+            layout: &video.bind_group_layouts[crate::video::BIND_GROUP_TRANSFORM as usize],
+            entries: &[wgpu::BindGroupEntry { binding: 0, resource: buffer.as_entire_binding() }],
+        });
+        Self { buffer, bind_group }
+    }
+
+    /// Render mesh of the given item based on provided video instance and render pass.
+    pub fn render(
+        &self,
+        vid: &video::Video,
+        rp: &mut wgpu::RenderPass,
+        sheet: &Sheet,
+        item: &Item,
+        tint: [f32; 3],
+    ) {
+        rp.set_bind_group(crate::video::BIND_GROUP_TRANSFORM, &self.bind_group, &[]);
+        rp.set_bind_group(crate::video::BIND_GROUP_TEXTURE, &sheet.texture.bind_group, &[]);
+
+        rp.set_vertex_buffer(0, sheet.vertex_buffer.slice(..));
+        rp.set_index_buffer(sheet.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+
+        let m = video::TransformUniform::new(
+            Matrix4::from_translation((item.position.x, 0.0, item.position.y).into()),
+            tint,
+        );
+        vid.queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[m]));
+
+        let base_vertex = (item.kind.index() as u32 * VERTS_PER_ITEM) as i32;
+        rp.draw_indexed(0..6, base_vertex, 0..1);
+    }
+}
+
+// --------------------------------------------------
+// --- TEST ---
+// --------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::{pick_up, Item, ItemKind};
+    use crate::geometry::Aabb;
+    use crate::level::Level;
+    use cgmath::Point2;
+
+    #[test]
+    fn test_item_pick_up() {
+        let mut items = vec![
+            Item::new(ItemKind::Coin, Point2::new(1.0, 1.0)),
+            Item::new(ItemKind::Key, Point2::new(3.0, 1.0)),
+            Item::new(ItemKind::HealthPotion, Point2::new(1.5, 1.4)),
+        ];
+        let picked = pick_up(&mut items, &Aabb::from_center(Point2::new(1.0, 1.0), 0.375));
+        let picked: Vec<ItemKind> = picked.iter().map(|it| it.kind).collect();
+        assert_eq!(picked, vec![ItemKind::Coin, ItemKind::HealthPotion]);
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].kind, ItemKind::Key);
+    }
+
+    #[test]
+    fn test_item_spawn_all() {
+        let level = Level::new("level0").unwrap();
+        let items = Item::spawn_all(&level);
+        assert!(items.iter().any(|it| it.kind == ItemKind::Coin));
+        assert!(items.iter().all(|it| level.collision.push_out(it.position, 0.25) == it.position));
+    }
+}
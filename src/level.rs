@@ -1,10 +1,14 @@
 use crate::geometry::Aabb;
 use crate::video::{TextureGroup, Vertex, Video};
-use cgmath::{Matrix4, Point2};
-use std::collections::HashSet;
+use cgmath::{Matrix4, Point2, Vector2};
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 use thiserror::Error;
 use wgpu::util::DeviceExt;
 
+pub mod fov;
+pub mod gen;
+
 // --------------------------------------------------
 // --- BLOCKS ---
 // --------------------------------------------------
@@ -23,62 +27,727 @@ pub enum Block {
 // --------------------------------------------------
 
 pub struct Level {
-    #[allow(dead_code)]
+    pub id: String,
+    pub name: String,
     blocks: Blocks,
+    /// What the player sees and has seen, same layout as `blocks`.
+    visibility: Vec<Vec<Visibility>>,
+    /// Block the field of view was computed from, see [`Level::update_fov`].
+    fov_origin: Option<Point2<usize>>,
+    /// Region of the mesh tints which don't match the visibility or the lighting anymore.
+    tints_dirty: Option<TileRegion>,
+    pub spawns: Vec<Spawn>,
+    stairs_down: Option<String>,
+    stairs_up: Option<String>,
     pub collision: Collision,
-    pub mesh: Mesh,
+    /// Autotiled look of the blocks, same layout as `blocks`.
+    tiles: DungeonTiles,
+    /// Regions of the tiles changed since the mesh was updated, see [`Level::set_block`].
+    mesh_dirty: Vec<TileRegion>,
+    /// Navigation grid of the player-sized characters.
+    pub nav: crate::nav::Nav,
+    pub lighting: crate::light::Lighting,
+    pub mesh: Option<Mesh>,
 }
 
 impl Level {
     /// Create a new level instance.
-    pub fn new(video: &Video) -> Result<Self, LevelError> {
-        let blocks = Self::read_blocks("./assets/level0.txt")?;
+    /// Accepts a level id (such as `level1`), a path to a level file,
+    /// or a generated level id (such as `gen:rooms:1234`, see [`LevelFile::load`]).
+    /// The mesh is not created here, see [`Level::prepare_render`].
+    pub fn new(id: &str) -> Result<Self, LevelError> {
+        let LevelFile { name, blocks, spawns, stairs_down, stairs_up, ambient, torch_light } =
+            LevelFile::load(id)?;
+        if !spawns.iter().any(|s| s.kind == SpawnKind::Player) {
+            return Err(LevelError::MissingPlayerSpawn);
+        }
         let collision = Collision::new(&blocks);
-        let mesh = Mesh::new(video, &DungeonTile::map_blocks_to_dungeon_tiles(&blocks))?;
-        Ok(Self { blocks, collision, mesh })
-    }
-
-    /// Load level blocks from a file.
-    fn read_blocks(file_path: &str) -> Result<Blocks, std::io::Error> {
-        let lines = std::fs::read_to_string(file_path)?;
-        let lines: Vec<_> = lines.lines().collect();
-        let mut blocks: Blocks = vec![vec![Block::Void; lines.len()]; lines[0].len()];
-        for (y, line) in lines.iter().enumerate() {
-            for (x, ch) in line.bytes().enumerate() {
-                blocks[x][y] = match ch {
-                    b'.' => Block::Free,
-                    b'#' => Block::Wall,
-                    b' ' => Block::Void,
-                    _ => Block::Void,
-                };
+        let tiles = DungeonTile::map_blocks_to_dungeon_tiles(&blocks);
+        let nav = crate::nav::Nav::new(&blocks, crate::combat::BODY_HALF_SIZE);
+        let torches = spawns.iter().filter(|s| s.kind == SpawnKind::Torch);
+        let torches = torches.map(|it| torch_light.at(it.world_position())).collect();
+        let size = (blocks.len(), blocks.first().map_or(0, Vec::len));
+        let lighting = crate::light::Lighting::new(size, ambient, torches);
+        let id = id.to_string();
+        let tints_dirty = Some((0..size.0, 0..size.1));
+        let visibility =
+            blocks.iter().map(|column| vec![Visibility::Unknown; column.len()]).collect();
+        Ok(Self {
+            id,
+            name,
+            blocks,
+            visibility,
+            fov_origin: None,
+            tints_dirty,
+            spawns,
+            stairs_down,
+            stairs_up,
+            collision,
+            tiles,
+            mesh_dirty: vec![],
+            nav,
+            lighting,
+            mesh: None,
+        })
+    }
+
+    /// Create the rendering resources, if they are not created yet,
+    /// update the changed tiles of the mesh, and the tints of the tiles where the field of view
+    /// or the lighting has changed.
+    pub fn prepare_render(&mut self, video: &Video) -> Result<(), LevelError> {
+        if self.mesh.is_none() {
+            self.mesh = Some(Mesh::new(video, &self.tiles)?);
+            self.mesh_dirty.clear();
+            self.tints_dirty = Some((0..self.size().0, 0..self.size().1));
+        }
+        if let Some(mesh) = self.mesh.as_mut() {
+            for region in self.mesh_dirty.drain(..) {
+                mesh.set_tiles(video, &self.tiles, region);
+            }
+        }
+        if let Some((xs, ys)) = self.tints_dirty.take() {
+            let tints: Vec<_> = xs
+                .clone()
+                .flat_map(|x| ys.clone().map(move |y| (x, y)))
+                .map(|(x, y)| self.tints(x, y))
+                .collect();
+            if let Some(mesh) = self.mesh.as_mut() {
+                mesh.set_tints(video, (xs, ys), tints.into_iter());
+            }
+        }
+        Ok(())
+    }
+
+    /// Colors the corners of the block texture are multiplied by (in the mesh vertex order):
+    /// the fog of war, or the light at the corners for the blocks in the field of view.
+    pub fn tints(&self, x: usize, y: usize) -> [[f32; 3]; 4] {
+        match self.visibility(x, y) {
+            Visibility::Unknown => [FOG_TINT_UNKNOWN; 4],
+            Visibility::Explored => [FOG_TINT_EXPLORED; 4],
+            Visibility::Visible => MESH_TILE_CORNERS.map(|(dx, dy)| {
+                let corner = Point2::new(x as f32 + dx, y as f32 + dy);
+                self.lighting.at_tile(Point2::new(x, y), corner)
+            }),
+        }
+    }
+
+    /// Light at the given world position, see [`crate::light::Lighting::at`].
+    pub fn light_at(&self, position: Point2<f32>) -> [f32; 3] {
+        self.lighting.at(position)
+    }
+
+    /// Turn a level id into a level file path. Anything which looks like a path is kept as is.
+    pub fn resolve_path(id: &str) -> String {
+        if id.contains('/') || id.contains('\\') || id.ends_with(LEVEL_FILE_EXTENSION) {
+            id.to_string()
+        } else {
+            format!("{}{}{}", LEVEL_PATH_PREFIX, id, LEVEL_FILE_EXTENSION)
+        }
+    }
+
+    /// World position of the (first) player spawn marker.
+    pub fn player_spawn(&self) -> Point2<f32> {
+        let spawn = self.spawns.iter().find(|s| s.kind == SpawnKind::Player);
+        spawn.map(Spawn::world_position).unwrap_or(Point2::new(0.0, 0.0))
+    }
+
+    /// Size of the level map in blocks (width, height).
+    pub fn size(&self) -> (usize, usize) {
+        (self.blocks.len(), self.blocks.first().map_or(0, Vec::len))
+    }
+
+    /// Block at the given map position, `None` outside of the map.
+    pub fn block(&self, x: usize, y: usize) -> Option<Block> {
+        self.blocks.get(x).and_then(|column| column.get(y)).copied()
+    }
+
+    /// Visibility of the block at the given map position, unknown outside of the map.
+    pub fn visibility(&self, x: usize, y: usize) -> Visibility {
+        let visibility = self.visibility.get(x).and_then(|column| column.get(y)).copied();
+        visibility.unwrap_or(Visibility::Unknown)
+    }
+
+    /// Whether the block at the given map position has been seen by the player.
+    pub fn is_explored(&self, x: usize, y: usize) -> bool {
+        self.visibility(x, y) != Visibility::Unknown
+    }
+
+    /// Whether the block under the given world position is in the field of view.
+    pub fn is_visible(&self, position: Point2<f32>) -> bool {
+        self.tile_at(position).is_some_and(|it| self.visibility(it.x, it.y) == Visibility::Visible)
+    }
+
+    /// Compute the field of view from the block under the given world position, see
+    /// [`fov::compute`]. The blocks which were visible before are remembered as explored.
+    /// Nothing is done while the position stays on the same block.
+    pub fn update_fov(&mut self, position: Point2<f32>, radius: f32) {
+        let origin = self.tile_at(position);
+        if origin == self.fov_origin {
+            return;
+        }
+        self.fov_origin = origin;
+        let previous = self.visibility.clone();
+        for visibility in self.visibility.iter_mut().flatten() {
+            if *visibility == Visibility::Visible {
+                *visibility = Visibility::Explored;
+            }
+        }
+        if let Some(origin) = origin {
+            let origin = Point2::new(origin.x as i64, origin.y as i64);
+            self.reveal(origin, radius);
+        }
+        let (w, h) = self.size();
+        let changed = (0..w).flat_map(|x| (0..h).map(move |y| Point2::new(x, y)));
+        let changed = changed.filter(|it| previous[it.x][it.y] != self.visibility[it.x][it.y]);
+        if let Some(region) = bounding_region(changed) {
+            self.mark_tints_dirty(region);
+        }
+    }
+
+    /// Mark the blocks in the field of view from the given map position as visible.
+    /// Internal helper for [`Level::update_fov`].
+    fn reveal(&mut self, origin: Point2<i64>, radius: f32) {
+        let (blocks, visibility) = (&self.blocks, &mut self.visibility);
+        fov::compute(
+            origin,
+            radius,
+            |x, y| is_opaque(blocks, x, y),
+            |x, y| {
+                let tile =
+                    visibility.get_mut(x as usize).and_then(|column| column.get_mut(y as usize));
+                if let Some(tile) = tile.filter(|_| x >= 0 && y >= 0) {
+                    *tile = Visibility::Visible;
+                }
+            },
+        );
+    }
+
+    /// Light up the level with the given lights (e.g. the player light), the flashes
+    /// and the torches nearest to the first given light, see [`crate::light::Lighting::update`].
+    pub fn update_lighting(&mut self, lights: &[crate::light::Light]) {
+        let Some(focus) = lights.first().map(|it| it.position) else {
+            return;
+        };
+        let blocks = &self.blocks;
+        if let Some(region) = self.lighting.update(lights, focus, |x, y| is_opaque(blocks, x, y)) {
+            self.mark_tints_dirty(region);
+        }
+    }
+
+    /// Have the tints of the given region updated by the next [`Level::prepare_render`].
+    fn mark_tints_dirty(&mut self, region: TileRegion) {
+        let region = match self.tints_dirty.take() {
+            Some(dirty) => merge_regions(dirty, region),
+            None => region,
+        };
+        self.tints_dirty = Some(region);
+    }
+
+    /// Map position of the block under the given world position, `None` outside of the map.
+    pub fn tile_at(&self, position: Point2<f32>) -> Option<Point2<usize>> {
+        tile_at(position, self.size())
+    }
+
+    /// Change the block at the given map position, the collision, the navigation grid,
+    /// the field of view, the lighting and the mesh follow. Only the collision chunk of the block,
+    /// and the navigation grid and the tiles around it are rebuilt.
+    /// Returns whether the block has changed.
+    pub fn set_block(&mut self, x: usize, y: usize, block: Block) -> bool {
+        match self.blocks.get_mut(x).and_then(|column| column.get_mut(y)) {
+            Some(it) if *it != block => *it = block,
+            _ => return false,
+        }
+        self.collision.update(&self.blocks, x, y);
+        self.nav.update(&self.blocks, x, y);
+        let region = DungeonTile::affected_region(Point2::new(x, y), self.size());
+        DungeonTile::update_dungeon_tiles(&self.blocks, &mut self.tiles, region.clone());
+        self.mesh_dirty.push(region);
+        // recomputed by the next `update_fov`, wherever the player stands
+        // the tints follow only where the visibility or the light actually changes
+        self.fov_origin = None;
+        self.lighting.invalidate();
+        true
+    }
+
+    /// Whether the door at the given map position is open, `None` when there is no door.
+    pub fn door(&self, tile: Point2<usize>) -> Option<bool> {
+        let door = self.spawns.iter().any(|s| s.kind == SpawnKind::Door && s.position == tile);
+        door.then(|| self.block(tile.x, tile.y) == Some(Block::Free))
+    }
+
+    /// Map positions of all the doors of the level, and whether they are open.
+    pub fn doors(&self) -> impl Iterator<Item = (Point2<usize>, bool)> + '_ {
+        let doors = self.spawns.iter().filter(|s| s.kind == SpawnKind::Door);
+        doors.map(|s| (s.position, self.block(s.position.x, s.position.y) == Some(Block::Free)))
+    }
+
+    /// Open or close the door at the given map position (an open door is a free block,
+    /// a closed one is a wall). Returns whether the door has changed.
+    pub fn set_door(&mut self, tile: Point2<usize>, open: bool) -> bool {
+        if self.door(tile).is_none() {
+            return false;
+        }
+        self.set_block(tile.x, tile.y, if open { Block::Free } else { Block::Wall })
+    }
+
+    /// Doors and the explored blocks, to be restored with [`Level::restore`].
+    pub fn state(&self) -> LevelState {
+        let explored = self
+            .visibility
+            .iter()
+            .map(|column| column.iter().map(|it| *it != Visibility::Unknown).collect());
+        LevelState {
+            doors: self.doors().collect(),
+            explored: explored.collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Put the doors and the explored blocks back as they were when the player left the level.
+    pub fn restore(&mut self, state: &LevelState) {
+        for (tile, open) in state.doors.iter() {
+            self.set_door(*tile, *open);
+        }
+        let explored = self.visibility.iter_mut().flatten().zip(state.explored.iter().flatten());
+        for (visibility, _) in explored.filter(|(_, explored)| **explored) {
+            *visibility = Visibility::Explored;
+        }
+        self.fov_origin = None;
+        self.mark_tints_dirty((0..self.size().0, 0..self.size().1));
+    }
+
+    /// Where the player arrives coming from the given level: next to the stairs leading back
+    /// to it, or at the player spawn when there are no such stairs.
+    pub fn arrival_from(&self, level_id: &str) -> Point2<f32> {
+        let stairs =
+            self.spawns.iter().filter(|s| self.exit_at(s.world_position()) == Some(level_id));
+        for spawn in stairs {
+            for (dx, dy) in ARRIVAL_OFFSETS {
+                let (x, y) = (spawn.position.x as i64 + dx, spawn.position.y as i64 + dy);
+                let tile = Point2::new(x as usize, y as usize);
+                let position = Point2::new(x as f32, y as f32);
+                if x >= 0
+                    && y >= 0
+                    && self.block(tile.x, tile.y) == Some(Block::Free)
+                    && self.exit_at(position).is_none()
+                    && self.door(tile).is_none()
+                {
+                    return position;
+                }
             }
         }
-        Ok(blocks)
+        self.player_spawn()
+    }
+
+    /// Level id the exit under the given world position leads to, if there is any.
+    pub fn exit_at(&self, position: Point2<f32>) -> Option<&str> {
+        let tile = self.tile_at(position)?;
+        let spawn = self.spawns.iter().find(|s| s.position == tile)?;
+        match spawn.kind {
+            SpawnKind::StairsDown => self.stairs_down.as_deref(),
+            SpawnKind::StairsUp => self.stairs_up.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+/// Map position of the block under the given world position, `None` outside of a map
+/// of the given size (width, height).
+pub fn tile_at(position: Point2<f32>, size: (usize, usize)) -> Option<Point2<usize>> {
+    let (x, y) = (position.x.round(), position.y.round());
+    if x < 0.0 || y < 0.0 || x >= size.0 as f32 || y >= size.1 as f32 {
+        return None;
     }
+    Some(Point2::new(x as usize, y as usize))
 }
 
+/// Smallest region containing all the given map positions, `None` when there are none.
+pub fn bounding_region(tiles: impl IntoIterator<Item = Point2<usize>>) -> Option<TileRegion> {
+    tiles.into_iter().fold(None, |region, it| {
+        let tile = (it.x..it.x + 1, it.y..it.y + 1);
+        Some(region.map_or(tile.clone(), |region| merge_regions(region, tile)))
+    })
+}
+
+/// Smallest region containing both the regions.
+fn merge_regions(a: TileRegion, b: TileRegion) -> TileRegion {
+    let xs = a.0.start.min(b.0.start)..a.0.end.max(b.0.end);
+    let ys = a.1.start.min(b.1.start)..a.1.end.max(b.1.end);
+    (xs, ys)
+}
+
+/// Blocks next to the stairs the player can arrive at, see [`Level::arrival_from`].
+const ARRIVAL_OFFSETS: [(i64, i64); 8] =
+    [(-1, 0), (1, 0), (0, -1), (0, 1), (-1, -1), (1, -1), (-1, 1), (1, 1)];
+
+/// What has changed on a level while the player was there. Kept by the scene for the levels
+/// the player has left, see [`crate::scene::Scene::load_level`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LevelState {
+    /// Map positions of the doors and whether they are open.
+    pub doors: Vec<(Point2<usize>, bool)>,
+    /// Blocks seen by the player, same layout as the blocks.
+    pub explored: Vec<Vec<bool>>,
+    /// Spawn positions of the killed enemies.
+    pub killed: HashSet<Point2<usize>>,
+    /// Spawn positions of the picked items.
+    pub picked: HashSet<Point2<usize>>,
+}
+
+/// Whether the block at the given map position blocks the sight and the light.
+/// Anything but the floor does, and so does the outside of the map.
+fn is_opaque(blocks: &Blocks, x: i64, y: i64) -> bool {
+    let block = blocks.get(x as usize).and_then(|column| column.get(y as usize));
+    x < 0 || y < 0 || block.is_none_or(|it| *it != Block::Free)
+}
+
+/// Fog of war state of a block.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Visibility {
+    /// Never seen, drawn black.
+    Unknown,
+    /// Seen before, but not in the field of view now, drawn darkened.
+    Explored,
+    /// In the field of view.
+    Visible,
+}
+
+const FOG_TINT_UNKNOWN: [f32; 3] = [0.0, 0.0, 0.0];
+const FOG_TINT_EXPLORED: [f32; 3] = [0.2, 0.2, 0.3];
+
+pub const DEFAULT_LEVEL_ID: &str = "level0";
+const LEVEL_PATH_PREFIX: &str = "./assets/";
+const LEVEL_FILE_EXTENSION: &str = ".txt";
+const LEVEL_GEN_PREFIX: &str = "gen:";
+
 #[derive(Error, Debug)]
 pub enum LevelError {
     #[error("read blocks error: {0}")]
     ReadBlocks(#[from] std::io::Error),
 
+    #[error("invalid header at line {line}: {reason}")]
+    InvalidHeader { line: usize, reason: String },
+
+    #[error("unknown glyph {glyph:?} at line {line}, column {column}")]
+    UnknownGlyph { glyph: char, line: usize, column: usize },
+
+    #[error("size mismatch: header says {expected:?}, map is {actual:?}")]
+    SizeMismatch { expected: (usize, usize), actual: (usize, usize) },
+
+    #[error("invalid level id: {0}")]
+    InvalidId(String),
+
+    #[error("level map is empty")]
+    EmptyMap,
+
+    #[error("level has no player spawn marker")]
+    MissingPlayerSpawn,
+
     #[error("mesh error: {0}")]
     Mesh(#[from] MeshError),
 }
 
+// --------------------------------------------------
+// --- LEVEL FILE ---
+// --------------------------------------------------
+
+/// Parsed contents of a level file.
+///
+/// A level file is an optional header followed by the map itself.
+/// The header is a list of `key: value` lines terminated by a `---` line:
+///
+/// ```text
+/// name: Entrance
+/// size: 21x14
+/// stairs_down: level1
+/// ambient: 0.4 0.4 0.45
+/// torch_light: 1.0 0.6 0.3 4.5
+/// legend: ~ = void
+/// legend: O = free enemy
+/// legend: | = wall door
+/// ---
+/// #####...
+/// ```
+///
+/// The `ambient` light is a single gray level or an `r g b` color, `torch_light`
+/// is the `r g b radius` of the wall torches (`*` glyphs by default). Doors are closed (`+`)
+/// or open (`/`) by default, a `wall door` or a `free door` legend entry adds another glyph.
+///
+/// Files without the `---` separator are treated as a bare map.
+pub struct LevelFile {
+    pub name: String,
+    pub blocks: Blocks,
+    pub spawns: Vec<Spawn>,
+    pub stairs_down: Option<String>,
+    pub stairs_up: Option<String>,
+    pub ambient: [f32; 3],
+    pub torch_light: crate::light::LightSpec,
+}
+
+impl LevelFile {
+    /// Load a level by its id. Generated levels have `gen:<style>:<seed>` ids,
+    /// anything else is resolved to a level file path with [`Level::resolve_path`].
+    pub fn load(id: &str) -> Result<Self, LevelError> {
+        let Some(generated) = id.strip_prefix(LEVEL_GEN_PREFIX) else {
+            return Self::read(&Level::resolve_path(id));
+        };
+        let invalid = || LevelError::InvalidId(id.to_string());
+        let (style, seed) = generated.split_once(':').ok_or_else(invalid)?;
+        let style = gen::Style::from_name(style).ok_or_else(invalid)?;
+        let seed = seed.parse().map_err(|_| invalid())?;
+        let (width, height) = gen::GEN_DEFAULT_SIZE;
+        Ok(gen::generate(style, width, height, seed))
+    }
+
+    /// Read and parse a level file from the disk.
+    pub fn read(file_path: &str) -> Result<Self, LevelError> {
+        Self::parse(&std::fs::read_to_string(file_path)?)
+    }
+
+    /// Parse level file contents, see [`LevelFile`] for the format description.
+    pub fn parse(source: &str) -> Result<Self, LevelError> {
+        let lines: Vec<_> = source.lines().collect();
+        let separator = lines.iter().position(|l| l.trim_end() == LEVEL_FILE_SEPARATOR);
+        let (header, map, map_offset) = match separator {
+            Some(idx) => (&lines[..idx], &lines[idx + 1..], idx + 1),
+            None => (&lines[..0], &lines[..], 0),
+        };
+
+        // header
+        let (mut name, mut size, mut legend) = (String::new(), None, Self::default_legend());
+        let (mut stairs_down, mut stairs_up) = (None, None);
+        let (mut ambient, mut torch_light) =
+            (crate::light::LIGHT_AMBIENT, crate::light::TORCH_LIGHT);
+        for (idx, line) in header.iter().enumerate() {
+            let line_no = idx + 1;
+            if line.trim().is_empty() || line.trim_start().starts_with(';') {
+                continue;
+            }
+            let invalid = |reason: &str| LevelError::InvalidHeader {
+                line: line_no,
+                reason: reason.to_string(),
+            };
+            let (key, value) =
+                line.split_once(':').ok_or_else(|| invalid("expected key: value"))?;
+            match key.trim() {
+                "name" => name = value.trim().to_string(),
+                "stairs_down" => stairs_down = Some(value.trim().to_string()),
+                "stairs_up" => stairs_up = Some(value.trim().to_string()),
+                "size" => {
+                    let (w, h) = value.trim().split_once('x').ok_or_else(|| invalid("bad size"))?;
+                    let w = w.trim().parse().map_err(|_| invalid("bad size width"))?;
+                    let h = h.trim().parse().map_err(|_| invalid("bad size height"))?;
+                    size = Some((w, h));
+                }
+                "ambient" => {
+                    ambient = match Self::parse_floats(value).as_deref() {
+                        Some(&[gray]) => [gray; 3],
+                        Some(&[r, g, b]) => [r, g, b],
+                        _ => return Err(invalid("bad ambient")),
+                    };
+                }
+                "torch_light" => {
+                    let Some(&[r, g, b, radius]) = Self::parse_floats(value).as_deref() else {
+                        return Err(invalid("bad torch_light"));
+                    };
+                    if radius <= 0.0 {
+                        return Err(invalid("bad torch_light radius"));
+                    }
+                    torch_light = crate::light::LightSpec { color: [r, g, b], radius };
+                }
+                "legend" => {
+                    let (glyph, entry) =
+                        Self::parse_legend(value).ok_or_else(|| invalid("bad legend"))?;
+                    legend.insert(glyph, entry);
+                }
+                _ => return Err(invalid("unknown key")),
+            }
+        }
+
+        // map -- trailing empty lines are not part of the map
+        let height = map.iter().rposition(|l| !l.is_empty()).map_or(0, |i| i + 1);
+        let map = &map[..height];
+        let width = map.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+        if width == 0 || height == 0 {
+            return Err(LevelError::EmptyMap);
+        }
+        if let Some(expected) = size {
+            if expected != (width, height) {
+                return Err(LevelError::SizeMismatch { expected, actual: (width, height) });
+            }
+        }
+
+        let (mut blocks, mut spawns): (Blocks, _) =
+            (vec![vec![Block::Void; height]; width], vec![]);
+        for (y, line) in map.iter().enumerate() {
+            for (x, glyph) in line.chars().enumerate() {
+                let (block, kind) = *legend.get(&glyph).ok_or(LevelError::UnknownGlyph {
+                    glyph,
+                    line: map_offset + y + 1,
+                    column: x + 1,
+                })?;
+                blocks[x][y] = block;
+                if let Some(kind) = kind {
+                    spawns.push(Spawn { kind, position: Point2::new(x, y) });
+                }
+            }
+        }
+
+        Ok(Self { name, blocks, spawns, stairs_down, stairs_up, ambient, torch_light })
+    }
+
+    /// Parse whitespace separated non-negative finite numbers, such as `1.0 0.6 0.3`.
+    fn parse_floats(value: &str) -> Option<Vec<f32>> {
+        let numbers = value.split_whitespace().map(|it| it.parse::<f32>().ok());
+        let numbers: Option<Vec<_>> = numbers.collect();
+        numbers.filter(|it| it.iter().all(|it| it.is_finite() && *it >= 0.0))
+    }
+
+    /// Glyphs understood without any legend in the header.
+    fn default_legend() -> HashMap<char, (Block, Option<SpawnKind>)> {
+        let mut legend = HashMap::from([
+            ('.', (Block::Free, None)),
+            ('#', (Block::Wall, None)),
+            (' ', (Block::Void, None)),
+            ('@', (Block::Free, Some(SpawnKind::Player))),
+            ('>', (Block::Free, Some(SpawnKind::StairsDown))),
+            ('<', (Block::Free, Some(SpawnKind::StairsUp))),
+            ('*', (Block::Wall, Some(SpawnKind::Torch))),
+            ('+', (Block::Wall, Some(SpawnKind::Door))),
+            ('/', (Block::Free, Some(SpawnKind::Door))),
+        ]);
+        legend.extend(('A'..='Z').map(|c| (c, (Block::Free, Some(SpawnKind::Enemy(c))))));
+        legend.extend(('a'..='z').map(|c| (c, (Block::Free, Some(SpawnKind::Item(c))))));
+        legend
+    }
+
+    /// Parse a single legend value such as `~ = void` or `O = free enemy`.
+    fn parse_legend(value: &str) -> Option<(char, (Block, Option<SpawnKind>))> {
+        let mut chars = value.trim_start().chars();
+        let glyph = chars.next()?;
+        let entry = chars.as_str().trim_start().strip_prefix('=')?;
+        let mut words = entry.split_whitespace();
+        let block = match words.next()? {
+            "free" => Block::Free,
+            "wall" => Block::Wall,
+            "void" => Block::Void,
+            _ => return None,
+        };
+        let kind = match words.next() {
+            None => None,
+            Some("player") => Some(SpawnKind::Player),
+            Some("stairs_down") => Some(SpawnKind::StairsDown),
+            Some("stairs_up") => Some(SpawnKind::StairsUp),
+            Some("enemy") => Some(SpawnKind::Enemy(glyph)),
+            Some("item") => Some(SpawnKind::Item(glyph)),
+            Some("torch") => Some(SpawnKind::Torch),
+            Some("door") => Some(SpawnKind::Door),
+            Some(_) => return None,
+        };
+        if words.next().is_some() {
+            return None;
+        }
+        Some((glyph, (block, kind)))
+    }
+}
+
+/// A typed marker placed on the level map.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Spawn {
+    pub kind: SpawnKind,
+    pub position: Point2<usize>,
+}
+
+impl Spawn {
+    /// Position of the marker in the world (XZ plane) coordinates.
+    pub fn world_position(&self) -> Point2<f32> {
+        Point2::new(self.position.x as f32, self.position.y as f32)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SpawnKind {
+    Player,
+    StairsDown,
+    StairsUp,
+    Enemy(char),
+    Item(char),
+    /// Wall torch, a light of the level.
+    Torch,
+    /// Door, closed when its block is a wall and open when it's free.
+    Door,
+}
+
+const LEVEL_FILE_SEPARATOR: &str = "---";
+
 // --------------------------------------------------
 // --- COLLISION ---
 // --------------------------------------------------
 
 pub struct Collision {
     pub aabbs: Vec<Aabb>,
+    /// AABBs of every chunk of [`COLLISION_CHUNK_SIZE`] blocks (columns first). The walls
+    /// are merged only within a chunk, so that a chunk can be rebuilt alone.
+    chunks: Vec<Vec<Aabb>>,
+    /// Number of the chunks along the y axis.
+    chunks_h: usize,
 }
 
 impl Collision {
     /// Create a new level collision.
     pub fn new(blocks: &Blocks) -> Self {
-        let aabbs = Self::build_aabbs_from_block_sets(&Self::find_block_sets(blocks));
-        Self { aabbs }
+        let (w, h) = (blocks.len(), blocks.first().map_or(0, Vec::len));
+        let chunks_h = h.div_ceil(COLLISION_CHUNK_SIZE);
+        let chunks: Vec<_> = (0..w.div_ceil(COLLISION_CHUNK_SIZE))
+            .flat_map(|cx| (0..chunks_h).map(move |cy| (cx, cy)))
+            .map(|(cx, cy)| Self::build_chunk(blocks, cx, cy))
+            .collect();
+        Self { aabbs: chunks.concat(), chunks, chunks_h }
+    }
+
+    /// Rebuild the chunk of the (changed) block at the given map position.
+    /// The result is the same as of creating the whole collision anew.
+    pub fn update(&mut self, blocks: &Blocks, x: usize, y: usize) {
+        let (cx, cy) = (x / COLLISION_CHUNK_SIZE, y / COLLISION_CHUNK_SIZE);
+        if let Some(chunk) = self.chunks.get_mut(cx * self.chunks_h + cy) {
+            *chunk = Self::build_chunk(blocks, cx, cy);
+            self.aabbs = self.chunks.concat();
+        }
+    }
+
+    /// AABBs of the walls of the given chunk.
+    fn build_chunk(blocks: &Blocks, cx: usize, cy: usize) -> Vec<Aabb> {
+        let (w, h) = (blocks.len(), blocks.first().map_or(0, Vec::len));
+        let n = COLLISION_CHUNK_SIZE;
+        let bounds = (cx * n, cy * n, ((cx + 1) * n).min(w), ((cy + 1) * n).min(h));
+        let bounds = (bounds.0 as i32, bounds.1 as i32, bounds.2 as i32, bounds.3 as i32);
+        Self::build_aabbs_from_block_sets(&Self::find_block_sets(blocks, bounds))
+    }
+
+    /// Push a square box (given by its center and half size) out of every colliding AABB.
+    /// Returns the resolved center position of the box.
+    pub fn push_out(&self, position: Point2<f32>, half_size: f32) -> Point2<f32> {
+        self.push_out_with_normals(position, half_size).0
+    }
+
+    /// Same as [`Collision::push_out`], also returns the contact normals (pointing out of the walls).
+    pub fn push_out_with_normals(
+        &self,
+        position: Point2<f32>,
+        half_size: f32,
+    ) -> (Point2<f32>, Vec<Vector2<f32>>) {
+        let mut position = position;
+        let mut normals = vec![];
+        let object = Aabb::from_center(position, half_size);
+
+        self.aabbs.iter().for_each(|aabb| {
+            let contact = aabb.check_contact(&object);
+            if contact.intersects {
+                position -= contact.min_trans * contact.penetration;
+                normals.push(-contact.min_trans);
+            }
+        });
+        (position, normals)
     }
 
     /// Take a slice of block sets and convert them into AABBs vector.
@@ -103,18 +772,18 @@ impl Collision {
             .collect()
     }
 
-    /// Takes raw blocks and builds a vector of block sets.
+    /// Takes raw blocks and builds a vector of block sets within the given bounds
+    /// (the same form as a block set).
     /// This is done by some kind of breadth first search algorithm.
     /// A block set is a 4 elements tuple of signed 32 bit integers.
     /// First two elements are x and y of min point,
     /// and second two elements are x and y of max point.
-    fn find_block_sets(blocks: &Blocks) -> Vec<(i32, i32, i32, i32)> {
-        let (w, h) = (blocks.len() as i32, blocks[0].len() as i32);
+    fn find_block_sets(blocks: &Blocks, bounds: (i32, i32, i32, i32)) -> Vec<(i32, i32, i32, i32)> {
         let (mut visited, mut result) = (HashSet::new(), vec![]);
-        for x in 0..w {
-            for y in 0..h {
+        for x in bounds.0..bounds.2 {
+            for y in bounds.1..bounds.3 {
                 if !visited.contains(&(x, y)) && blocks[x as usize][y as usize] == Block::Wall {
-                    result.push(Self::find_block_sets_internal(blocks, w, h, &mut visited, x, y));
+                    result.push(Self::find_block_sets_internal(blocks, bounds, &mut visited, x, y));
                 }
             }
         }
@@ -124,23 +793,30 @@ impl Collision {
     /// Internal helper for the [`find_block_sets`].
     fn find_block_sets_internal(
         blocks: &Blocks,
-        w: i32,
-        h: i32,
+        bounds: (i32, i32, i32, i32),
         visited: &mut HashSet<(i32, i32)>,
         x: i32,
         y: i32,
     ) -> (i32, i32, i32, i32) {
         let mut bs = (x, y, x + 1, y + 1);
-        while (bs.0 - 1) >= 0 && Self::is_block_set(blocks, visited, bs.0 - 1, bs.1, bs.2, bs.3) {
+        while (bs.0 - 1) >= bounds.0
+            && Self::is_block_set(blocks, visited, bs.0 - 1, bs.1, bs.2, bs.3)
+        {
             bs.0 -= 1;
         }
-        while (bs.2 + 1) <= w && Self::is_block_set(blocks, visited, bs.0, bs.1, bs.2 + 1, bs.3) {
+        while (bs.2 + 1) <= bounds.2
+            && Self::is_block_set(blocks, visited, bs.0, bs.1, bs.2 + 1, bs.3)
+        {
             bs.2 += 1;
         }
-        while (bs.1 - 1) >= 0 && Self::is_block_set(blocks, visited, bs.0, bs.1 - 1, bs.2, bs.3) {
+        while (bs.1 - 1) >= bounds.1
+            && Self::is_block_set(blocks, visited, bs.0, bs.1 - 1, bs.2, bs.3)
+        {
             bs.1 -= 1;
         }
-        while (bs.3 + 1) <= h && Self::is_block_set(blocks, visited, bs.0, bs.1, bs.2, bs.3 + 1) {
+        while (bs.3 + 1) <= bounds.3
+            && Self::is_block_set(blocks, visited, bs.0, bs.1, bs.2, bs.3 + 1)
+        {
             bs.3 += 1;
         }
         for i in bs.0..bs.2 {
@@ -171,11 +847,16 @@ impl Collision {
     }
 }
 
+/// Size (in blocks) of the square chunks the collision is built and updated by.
+const COLLISION_CHUNK_SIZE: usize = 16;
+
 // --------------------------------------------------
 // --- DUNGEON TILE ---
 // --------------------------------------------------
 
 type DungeonTiles = Vec<Vec<DungeonTile>>;
+/// Ranges of the x and y map positions of a rectangle of tiles.
+pub type TileRegion = (Range<usize>, Range<usize>);
 
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub enum DungeonTile {
@@ -324,19 +1005,36 @@ impl DungeonTile {
     pub fn map_blocks_to_dungeon_tiles(blocks: &Blocks) -> DungeonTiles {
         let (w, h) = (blocks.len(), blocks[0].len());
         let mut result: DungeonTiles = vec![vec![DungeonTile::Void; h]; w];
+        Self::update_dungeon_tiles(blocks, &mut result, (0..w, 0..h));
+        result
+    }
+
+    /// Map the blocks in the given region to the dungeon tiles again, the tiles outside
+    /// of the region have to be up to date already. Pass 2 only checks whether
+    /// the neighbours are flat, so the final tiles around the region are as good as
+    /// the pass 1 ones.
+    pub fn update_dungeon_tiles(blocks: &Blocks, tiles: &mut DungeonTiles, region: TileRegion) {
+        let (xs, ys) = region;
         for p in 0..3 {
-            for x in 0..w {
-                for y in 0..h {
-                    result[x][y] = match p {
+            for x in xs.clone() {
+                for y in ys.clone() {
+                    tiles[x][y] = match p {
                         0 => Self::pass0(blocks, (x, y).into()),
-                        1 => Self::pass1(&result, (x, y).into()),
-                        2 => Self::pass2(&result, (x, y).into()),
+                        1 => Self::pass1(tiles, (x, y).into()),
+                        2 => Self::pass2(tiles, (x, y).into()),
                         _ => panic!("incorrect pass"),
                     }
                 }
             }
         }
-        result
+    }
+
+    /// Tiles which may change with the block at the given map position: the block and its
+    /// direct neighbours (the passes look no further), within a map of the given size.
+    pub fn affected_region(point: Point2<usize>, size: (usize, usize)) -> TileRegion {
+        let xs = point.x.saturating_sub(1)..(point.x + 2).min(size.0);
+        let ys = point.y.saturating_sub(1)..(point.y + 2).min(size.1);
+        (xs, ys)
     }
 
     /// Internal helper for [`map_blocks_to_dungeon_tiles`], pass 0.
@@ -531,6 +1229,10 @@ pub struct Mesh {
     pub vertex_buffer: wgpu::Buffer,
     #[allow(dead_code)]
     vertex_count: u32,
+    /// Kept around, so that the tints can be updated, see [`Mesh::set_tints`].
+    vertices: Vec<Vertex>,
+    /// Height of the map in tiles, the vertices go a column of tiles after another.
+    height: usize,
 
     pub index_buffer: wgpu::Buffer,
     pub index_count: u32,
@@ -554,7 +1256,7 @@ impl Mesh {
         let vertex_buffer = video.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("level_mesh_vertex_buffer"),
             contents: bytemuck::cast_slice(&vertices),
-            usage: wgpu::BufferUsages::VERTEX,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
         });
 
         // geometry -- indices
@@ -568,7 +1270,7 @@ impl Mesh {
         // WGPU buffer and bind group
         let buffer = video.device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("level_mesh_buffer"),
-            size: std::mem::size_of::<[crate::video::MatrixUniform; 1]>() as u64,
+            size: std::mem::size_of::<crate::video::TransformUniform>() as u64,
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
@@ -582,6 +1284,8 @@ impl Mesh {
             texture,
             vertex_buffer,
             vertex_count,
+            vertices,
+            height: dungeon_tiles.first().map_or(0, Vec::len),
             index_buffer,
             index_count,
             buffer,
@@ -589,38 +1293,99 @@ impl Mesh {
         })
     }
 
+    /// Tint the corners of the tiles in the given region (in the blocks order, columns first)
+    /// and upload them.
+    pub fn set_tints(
+        &mut self,
+        video: &Video,
+        region: TileRegion,
+        tints: impl Iterator<Item = [[f32; 3]; 4]>,
+    ) {
+        let n = MESH_VERTICES_PER_TILE as usize;
+        let tiles = region.0.clone().flat_map(|x| region.1.clone().map(move |y| (x, y)));
+        for ((x, y), tints) in tiles.zip(tints) {
+            let start = (x * self.height + y) * n;
+            let tile = self.vertices[start..start + n].iter_mut();
+            tile.zip(tints).for_each(|(it, tint)| it.set_tint(tint));
+        }
+        self.upload(video, region);
+    }
+
+    /// Rebuild the vertices of the tiles in the given region (the tints are kept),
+    /// and upload them.
+    pub fn set_tiles(&mut self, video: &Video, dungeon_tiles: &DungeonTiles, region: TileRegion) {
+        Self::update_vertices(&mut self.vertices, dungeon_tiles, &region);
+        self.upload(video, region);
+    }
+
+    /// Upload the vertices of the tiles in the given region, a column of the region at a time.
+    fn upload(&self, video: &Video, region: TileRegion) {
+        let (h, n) = (self.height, MESH_VERTICES_PER_TILE as usize);
+        for x in region.0 {
+            let range = (x * h + region.1.start) * n..(x * h + region.1.end) * n;
+            let offset = (range.start * std::mem::size_of::<Vertex>()) as u64;
+            let vertices = bytemuck::cast_slice(&self.vertices[range]);
+            video.queue.write_buffer(&self.vertex_buffer, offset, vertices);
+        }
+    }
+
     /// Build vertices vector to be used to create a new vertex buffer.
     /// Internal helper for [`new`].
     fn build_vertices(dungeon_tiles: &DungeonTiles) -> (Vec<Vertex>, u32) {
-        let (m, n) = (MESH_TEXTURE_SIZE as f32, MESH_TEXTURE_TILE_SIZE as f32);
         let (mut vertices, mut vertex_count) = (vec![], 0);
         for (x, its) in dungeon_tiles.iter().enumerate() {
             for (y, it) in its.iter().enumerate() {
-                let (x, y) = (x as f32, y as f32);
-                let (u, v) = DungeonTile::get_texture_position(it).into();
-                let (u, v) = (u as f32, v as f32);
-                vertices.push(Vertex::new(
-                    (x - MESH_XZ_COORD, MESH_Y_COORD, y - MESH_XZ_COORD).into(),
-                    ((n * u) / m, (n * v) / m).into(),
-                ));
-                vertices.push(Vertex::new(
-                    (x - MESH_XZ_COORD, MESH_Y_COORD, y + MESH_XZ_COORD).into(),
-                    ((n * u) / m, (n * (v + 1.0)) / m).into(),
-                ));
-                vertices.push(Vertex::new(
-                    (x + MESH_XZ_COORD, MESH_Y_COORD, y + MESH_XZ_COORD).into(),
-                    ((n * (u + 1.0)) / m, (n * (v + 1.0)) / m).into(),
-                ));
-                vertices.push(Vertex::new(
-                    (x + MESH_XZ_COORD, MESH_Y_COORD, y - MESH_XZ_COORD).into(),
-                    ((n * (u + 1.0)) / m, (n * v) / m).into(),
-                ));
+                vertices.extend(Self::build_tile_vertices(x, y, it));
                 vertex_count += MESH_VERTICES_PER_TILE;
             }
         }
         (vertices, vertex_count)
     }
 
+    /// Build the vertices of the tiles in the given region again, the tints are kept.
+    /// Internal helper for [`set_tiles`].
+    fn update_vertices(vertices: &mut [Vertex], dungeon_tiles: &DungeonTiles, region: &TileRegion) {
+        let h = dungeon_tiles.first().map_or(0, Vec::len);
+        let n = MESH_VERTICES_PER_TILE as usize;
+        for x in region.0.clone() {
+            for y in region.1.clone() {
+                let tile = Self::build_tile_vertices(x, y, &dungeon_tiles[x][y]);
+                let start = (x * h + y) * n;
+                for (vertex, mut new) in vertices[start..start + n].iter_mut().zip(tile) {
+                    new.set_tint(vertex.tint());
+                    *vertex = new;
+                }
+            }
+        }
+    }
+
+    /// Vertices of a single tile at the given map position.
+    /// Internal helper for [`build_vertices`] and [`update_vertices`].
+    fn build_tile_vertices(x: usize, y: usize, tile: &DungeonTile) -> [Vertex; 4] {
+        let (m, n) = (MESH_TEXTURE_SIZE as f32, MESH_TEXTURE_TILE_SIZE as f32);
+        let (x, y) = (x as f32, y as f32);
+        let (u, v) = DungeonTile::get_texture_position(tile).into();
+        let (u, v) = (u as f32, v as f32);
+        [
+            Vertex::new(
+                (x - MESH_XZ_COORD, MESH_Y_COORD, y - MESH_XZ_COORD).into(),
+                ((n * u) / m, (n * v) / m).into(),
+            ),
+            Vertex::new(
+                (x - MESH_XZ_COORD, MESH_Y_COORD, y + MESH_XZ_COORD).into(),
+                ((n * u) / m, (n * (v + 1.0)) / m).into(),
+            ),
+            Vertex::new(
+                (x + MESH_XZ_COORD, MESH_Y_COORD, y + MESH_XZ_COORD).into(),
+                ((n * (u + 1.0)) / m, (n * (v + 1.0)) / m).into(),
+            ),
+            Vertex::new(
+                (x + MESH_XZ_COORD, MESH_Y_COORD, y - MESH_XZ_COORD).into(),
+                ((n * (u + 1.0)) / m, (n * v) / m).into(),
+            ),
+        ]
+    }
+
     /// Build indices vector to be used to create a new index buffer.
     /// Internal helper for [`new`].
     fn build_indices(vertex_count: u32) -> (Vec<u16>, u32) {
@@ -641,10 +1406,11 @@ impl Mesh {
         rp.set_vertex_buffer(0, self.vertex_buffer.slice(..));
         rp.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
 
-        let uni = crate::video::MatrixUniform {
-            matrix: Matrix4::from_translation((0.0, 0.0, 0.0).into()).into(),
-        };
-        vid.queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&uni.matrix));
+        let uni = crate::video::TransformUniform::new(
+            Matrix4::from_translation((0.0, 0.0, 0.0).into()),
+            [1.0; 3],
+        );
+        vid.queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[uni]));
 
         rp.draw_indexed(0..self.index_count, 0, 0..1);
     }
@@ -662,6 +1428,13 @@ const MESH_TEXTURE_SIZE: u32 = 160;
 const MESH_TEXTURE_TILE_SIZE: u32 = 16;
 const MESH_VERTICES_PER_TILE: u32 = 4;
 const MESH_INDICES_PER_TILE: u32 = 6;
+/// Corner offsets from the block center, in the vertex order of [`Mesh::build_vertices`].
+const MESH_TILE_CORNERS: [(f32, f32); 4] = [
+    (-MESH_XZ_COORD, -MESH_XZ_COORD),
+    (-MESH_XZ_COORD, MESH_XZ_COORD),
+    (MESH_XZ_COORD, MESH_XZ_COORD),
+    (MESH_XZ_COORD, -MESH_XZ_COORD),
+];
 
 // --------------------------------------------------
 // --- TEST ---
@@ -669,7 +1442,12 @@ const MESH_INDICES_PER_TILE: u32 = 6;
 
 #[cfg(test)]
 mod tests {
-    use super::{Block, Collision};
+    use super::{
+        Block, Collision, DungeonTile, Level, LevelError, LevelFile, Mesh, SpawnKind, Visibility,
+        FOG_TINT_UNKNOWN,
+    };
+    use crate::video::Vertex;
+    use cgmath::{Point2, Vector2};
 
     #[test]
     fn test_collision_case1() {
@@ -679,7 +1457,7 @@ mod tests {
             vec![Block::Free, Block::Wall, Block::Wall, Block::Free],
             vec![Block::Free, Block::Wall, Block::Free, Block::Free],
         ];
-        let actual = Collision::find_block_sets(&blocks);
+        let actual = Collision::find_block_sets(&blocks, (0, 0, 4, 4));
         let expected = vec![(0, 0, 1, 1), (1, 1, 4, 2), (1, 2, 3, 3), (1, 3, 2, 4)];
         assert_eq!(actual, expected);
     }
@@ -692,7 +1470,7 @@ mod tests {
             vec![Block::Wall, Block::Wall, Block::Wall, Block::Wall],
             vec![Block::Free, Block::Free, Block::Free, Block::Wall],
         ];
-        let actual = Collision::find_block_sets(&blocks);
+        let actual = Collision::find_block_sets(&blocks, (0, 0, 4, 4));
         let expected = vec![(0, 1, 3, 2), (1, 0, 3, 1), (1, 2, 3, 4), (3, 3, 4, 4)];
         assert_eq!(actual, expected);
     }
@@ -705,7 +1483,7 @@ mod tests {
             vec![Block::Wall, Block::Wall, Block::Wall, Block::Wall],
             vec![Block::Wall, Block::Wall, Block::Wall, Block::Wall],
         ];
-        let actual = Collision::find_block_sets(&blocks);
+        let actual = Collision::find_block_sets(&blocks, (0, 0, 4, 4));
         let expected = vec![(0, 0, 4, 4)];
         assert_eq!(actual, expected);
     }
@@ -718,8 +1496,286 @@ mod tests {
             vec![Block::Wall, Block::Free, Block::Free, Block::Wall],
             vec![Block::Wall, Block::Wall, Block::Wall, Block::Wall],
         ];
-        let actual = Collision::find_block_sets(&blocks);
+        let actual = Collision::find_block_sets(&blocks, (0, 0, 4, 4));
         let expected = vec![(0, 0, 4, 1), (0, 1, 1, 4), (1, 3, 4, 4), (3, 1, 4, 3)];
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn test_collision_push_out_normals() {
+        let blocks = vec![
+            vec![Block::Wall, Block::Wall, Block::Wall, Block::Wall],
+            vec![Block::Wall, Block::Free, Block::Free, Block::Wall],
+            vec![Block::Wall, Block::Free, Block::Free, Block::Wall],
+            vec![Block::Wall, Block::Wall, Block::Wall, Block::Wall],
+        ];
+        let collision = Collision::new(&blocks);
+        let (position, normals) = collision.push_out_with_normals(Point2::new(1.0, 0.8), 0.375);
+        assert!((position.y - 0.875).abs() < 0.01 && position.x == 1.0);
+        assert_eq!(normals, vec![Vector2::new(0.0, 1.0)]);
+
+        let (_, normals) = collision.push_out_with_normals(Point2::new(1.5, 1.5), 0.375);
+        assert!(normals.is_empty());
+    }
+
+    #[test]
+    fn test_level_fov() {
+        let mut level = Level::new("level0").unwrap();
+        let spawn = level.player_spawn();
+        let (x, y) = (spawn.x as usize, spawn.y as usize);
+        assert_eq!(level.visibility(x, y), Visibility::Unknown);
+        level.update_fov(spawn, 2.0);
+        assert!(level.is_explored(x, y) && level.is_explored(x + 2, y));
+        assert!(!level.is_explored(x + 2, y + 2));
+        assert!(!level.is_explored(usize::MAX, 0));
+        assert!(level.is_visible(Point2::new(4.0, 2.0)));
+
+        // what was visible before is remembered
+        level.update_fov(Point2::new(12.0, 10.0), 20.0);
+        assert_eq!(level.visibility(x + 2, y), Visibility::Explored);
+        assert_eq!(level.visibility(12, 12), Visibility::Visible);
+        // the walls block the view, but they are seen themselves
+        assert_eq!(level.visibility(8, 10), Visibility::Visible);
+        assert_eq!(level.visibility(7, 10), Visibility::Unknown);
+        assert_eq!(level.visibility(2, 10), Visibility::Unknown);
+
+        // around the map corner doesn't go out of bounds
+        level.update_fov(Point2::new(1.0, 1.0), 3.0);
+        assert!(level.is_explored(0, 0));
+        level.update_fov(Point2::new(-5.0, -5.0), 3.0);
+        assert_eq!(level.visibility(1, 1), Visibility::Explored);
+    }
+
+    #[test]
+    fn test_level_lighting() {
+        let mut level = Level::new("level0").unwrap();
+        assert_eq!(level.lighting.ambient, [0.3, 0.3, 0.35]);
+        assert_eq!(level.lighting.torches.len(), 5);
+        let player = crate::light::PLAYER_LIGHT.at(Point2::new(2.0, 2.0));
+        level.update_fov(player.position, 6.0);
+        level.update_lighting(&[player]);
+
+        // unknown blocks stay black, the visible ones are lit up near the lights
+        assert_eq!(level.tints(12, 12), [FOG_TINT_UNKNOWN; 4]);
+        let near = level.light_at(Point2::new(2.0, 2.0));
+        let far = level.light_at(Point2::new(5.0, 2.0));
+        assert!(near[0] > far[0] && far[0] > level.lighting.ambient[0]);
+        // the corners nearer to the player are brighter
+        let corners = level.tints(4, 2);
+        assert!(corners[0][2] > corners[3][2] && corners[1][2] > corners[2][2]);
+        // the torch at (3, 0) lights the room, but not the corridor behind the wall
+        let torch = level.light_at(Point2::new(3.0, 1.0));
+        level.update_lighting(&[crate::light::PLAYER_LIGHT.at(Point2::new(16.0, 2.0))]);
+        assert!(level.light_at(Point2::new(3.0, 1.0))[0] > level.lighting.ambient[0]);
+        assert!(level.light_at(Point2::new(3.0, 1.0))[2] < torch[2]);
+        assert_eq!(level.light_at(Point2::new(3.0, 5.0)), level.lighting.ambient);
+    }
+
+    #[test]
+    fn test_level_lighting_unchanged() {
+        let mut level = Level::new("level0").unwrap();
+        let tick = |level: &mut Level, position: Point2<f32>| {
+            level.update_fov(position, 6.0);
+            level.lighting.advance(1.0 / 60.0);
+            level.update_lighting(&[crate::light::PLAYER_LIGHT.at(position)]);
+        };
+        tick(&mut level, Point2::new(2.0, 2.0));
+        level.tints_dirty = None;
+
+        // moving within the same block changes nothing
+        tick(&mut level, Point2::new(2.2, 1.9));
+        assert_eq!(level.tints_dirty, None);
+        tick(&mut level, Point2::new(3.0, 2.0));
+        assert!(level.tints_dirty.is_some());
+        level.tints_dirty = None;
+
+        // neither does a flash going on, until it fades out by a step
+        level.lighting.flash(crate::light::PLAYER_LIGHT.at(Point2::new(4.0, 2.0)), 1.0);
+        tick(&mut level, Point2::new(3.0, 2.0));
+        assert!(level.tints_dirty.is_some());
+        level.tints_dirty = None;
+        tick(&mut level, Point2::new(3.0, 2.0));
+        assert_eq!(level.tints_dirty, None);
+    }
+
+    #[test]
+    fn test_level_set_block_tints() {
+        let mut level = Level::new("level1").unwrap();
+        let tick = |level: &mut Level| {
+            level.update_fov(Point2::new(2.0, 2.0), 6.0);
+            level.update_lighting(&[crate::light::PLAYER_LIGHT.at(Point2::new(2.0, 2.0))]);
+        };
+        tick(&mut level);
+        level.tints_dirty = None;
+
+        // out of sight and out of the light, the tints stay as they are
+        assert!(level.set_block(30, 30, Block::Wall));
+        tick(&mut level);
+        assert_eq!(level.tints_dirty, None);
+
+        // in sight, only the tiles whose visibility or light has changed are tinted again:
+        // the ones in the shadow of the new wall
+        assert!(level.set_block(4, 2, Block::Wall));
+        tick(&mut level);
+        assert_eq!(level.tints_dirty, Some((5..9, 1..4)));
+    }
+
+    #[test]
+    fn test_level_set_block_drops_corner_cutting_path() {
+        let mut level = Level::new("level0").unwrap();
+        let (start, goal) = (Point2::new(1, 1), Point2::new(3, 3));
+        let diagonal = vec![start, Point2::new(2, 2), goal];
+        assert_eq!(level.nav.find_path(start, goal), Some(diagonal.clone()));
+
+        // both ends of the first step stay walkable, but it would cut the corner of the wall
+        level.set_block(2, 1, Block::Wall);
+        let path = level.nav.find_path(start, goal).unwrap();
+        assert_ne!(path, diagonal);
+        assert!(!path.windows(2).any(|step| step[0] == start && step[1] == Point2::new(2, 2)));
+    }
+
+    #[test]
+    fn test_level_incremental_rebuild() {
+        let mut level = Level::new("level1").unwrap();
+        let (mut vertices, _) = Mesh::build_vertices(&level.tiles);
+        vertices.iter_mut().for_each(|it| it.set_tint([0.5; 3]));
+        let (w, h) = level.size();
+        // map corners and edges, chunk borders, next to the walls and in the open,
+        // then some more spread over the whole map
+        let changes = [(0, 0), (36, 34), (1, 1), (15, 15), (16, 15), (16, 16), (2, 2), (3, 2)];
+        let spread = (0..200).map(|i| (i * 7919 % w, i * 104_729 % h));
+        for (x, y) in changes.into_iter().chain(spread) {
+            let free = level.block(x, y) == Some(Block::Free);
+            assert!(level.set_block(x, y, if free { Block::Wall } else { Block::Free }));
+            assert_eq!(level.collision.aabbs, Collision::new(&level.blocks).aabbs);
+            assert_eq!(level.tiles, DungeonTile::map_blocks_to_dungeon_tiles(&level.blocks));
+        }
+        assert!(!level.set_block(0, 0, level.block(0, 0).unwrap()));
+        assert!(!level.set_block(w, 0, Block::Wall));
+
+        for region in level.mesh_dirty.iter() {
+            Mesh::update_vertices(&mut vertices, &level.tiles, region);
+        }
+        let (mut expected, _) = Mesh::build_vertices(&level.tiles);
+        expected.iter_mut().for_each(|it| it.set_tint([0.5; 3]));
+        let floats = |it: &[Vertex]| bytemuck::cast_slice::<_, f32>(it).to_vec();
+        assert_eq!(floats(&vertices), floats(&expected));
+    }
+
+    #[test]
+    fn test_level_file_bare_map() {
+        let file = LevelFile::parse("#.#\n#@\n").unwrap();
+        assert_eq!(file.name, "");
+        assert_eq!(
+            file.blocks,
+            vec![
+                vec![Block::Wall, Block::Wall],
+                vec![Block::Free, Block::Free],
+                vec![Block::Wall, Block::Void],
+            ]
+        );
+        assert_eq!(file.spawns.len(), 1);
+        assert_eq!(file.spawns[0].kind, SpawnKind::Player);
+        assert_eq!(file.spawns[0].position, Point2::new(1, 1));
+    }
+
+    #[test]
+    fn test_level_file_header_and_markers() {
+        let source = "name: Test\nsize: 4x2\nlegend: ~ = void\nlegend: * = wall\n---\n~*>O\n<c@#\n";
+        let file = LevelFile::parse(source).unwrap();
+        assert_eq!(file.name, "Test");
+        assert_eq!(file.blocks[0], vec![Block::Void, Block::Free]);
+        assert_eq!(file.blocks[1], vec![Block::Wall, Block::Free]);
+        let kinds: Vec<_> = file.spawns.iter().map(|s| (s.kind, s.position)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (SpawnKind::StairsDown, Point2::new(2, 0)),
+                (SpawnKind::Enemy('O'), Point2::new(3, 0)),
+                (SpawnKind::StairsUp, Point2::new(0, 1)),
+                (SpawnKind::Item('c'), Point2::new(1, 1)),
+                (SpawnKind::Player, Point2::new(2, 1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_level_file_unknown_glyph() {
+        let err = LevelFile::parse("name: Test\n---\n###\n#?#\n").err().unwrap();
+        assert!(matches!(err, LevelError::UnknownGlyph { glyph: '?', line: 4, column: 2 }));
+    }
+
+    #[test]
+    fn test_level_file_invalid_header() {
+        let err = LevelFile::parse("name: Test\nsize: big\n---\n#\n").err().unwrap();
+        assert!(matches!(err, LevelError::InvalidHeader { line: 2, .. }));
+        let err = LevelFile::parse("size: 3x3\n---\n###\n").err().unwrap();
+        assert!(matches!(err, LevelError::SizeMismatch { expected: (3, 3), actual: (3, 1) }));
+    }
+
+    #[test]
+    fn test_level_file_lighting() {
+        let file = LevelFile::parse("#*#\n#@#\n").unwrap();
+        assert_eq!(file.ambient, crate::light::LIGHT_AMBIENT);
+        assert_eq!(file.blocks[1][0], Block::Wall);
+        assert_eq!(file.spawns[0].kind, SpawnKind::Torch);
+
+        let source = "ambient: 0.2\ntorch_light: 0.5 0.5 1 3\nlegend: t = wall torch\n---\nt@\n";
+        let file = LevelFile::parse(source).unwrap();
+        assert_eq!(file.ambient, [0.2; 3]);
+        assert_eq!(file.torch_light.color, [0.5, 0.5, 1.0]);
+        assert_eq!(file.torch_light.radius, 3.0);
+        assert_eq!(file.spawns[0].kind, SpawnKind::Torch);
+
+        for header in
+            ["ambient: 0.2 0.2", "ambient: -1", "torch_light: 1 1 1", "torch_light: 1 1 1 0"]
+        {
+            let err = LevelFile::parse(&format!("{}\n---\n@\n", header)).err().unwrap();
+            assert!(matches!(err, LevelError::InvalidHeader { line: 1, .. }), "{}", header);
+        }
+    }
+
+    #[test]
+    fn test_level_doors() {
+        let file = LevelFile::parse("+/@\n").unwrap();
+        assert_eq!(file.blocks, vec![vec![Block::Wall], vec![Block::Free], vec![Block::Free]]);
+        assert_eq!(file.spawns[0].kind, SpawnKind::Door);
+        assert_eq!(file.spawns[1].kind, SpawnKind::Door);
+
+        let mut level = Level::new("level0").unwrap();
+        let generation = level.nav.generation();
+        let door = Point2::new(10, 4);
+        assert_eq!(level.doors().count(), 2);
+        assert!(level.set_door(door, false));
+        assert!(!level.set_door(door, false));
+        assert!(!level.set_door(Point2::new(2, 2), false));
+        assert_eq!(level.door(door), Some(false));
+        let doorway = Point2::new(10.0, 4.0);
+        assert_ne!(level.collision.push_out(doorway, 0.3), doorway);
+        assert_ne!(level.nav.generation(), generation);
+        assert!(!level.nav.is_walkable(door));
+
+        let state = level.state();
+        let mut level = Level::new("level0").unwrap();
+        level.restore(&state);
+        assert_eq!(level.door(door), Some(false));
+        assert_eq!(level.block(10, 4), Some(Block::Wall));
+    }
+
+    #[test]
+    fn test_level_file_stairs() {
+        let file = LevelFile::parse("stairs_down: level1\nstairs_up: level0\n---\n<@>\n").unwrap();
+        assert_eq!(file.stairs_down.as_deref(), Some("level1"));
+        assert_eq!(file.stairs_up.as_deref(), Some("level0"));
+        assert_eq!(Level::resolve_path("level1"), "./assets/level1.txt");
+        assert_eq!(Level::resolve_path("./custom/level.txt"), "./custom/level.txt");
+    }
+
+    #[test]
+    fn test_level_file_assets() {
+        for path in ["./assets/level0.txt", "./assets/level1.txt", "./assets/level2.txt"] {
+            let file = LevelFile::read(path).unwrap();
+            assert!(file.spawns.iter().any(|s| s.kind == SpawnKind::Player));
+        }
+    }
 }
@@ -0,0 +1,175 @@
+use cgmath::Point2;
+
+// --------------------------------------------------
+// --- FOV ---
+// --------------------------------------------------
+
+/// Field of view by symmetric shadowcasting (<https://www.albertford.com/shadowcasting/>):
+/// every block within the radius around the origin the origin can see gets revealed.
+/// It's symmetric, the origin can see a floor block exactly when the block can see the origin.
+/// Opaque blocks are revealed too (their faces are lit), anything else lets the sight through.
+pub fn compute(
+    origin: Point2<i64>,
+    radius: f32,
+    is_opaque: impl Fn(i64, i64) -> bool,
+    mut reveal: impl FnMut(i64, i64),
+) {
+    reveal(origin.x, origin.y);
+    let max_depth = radius.ceil() as i64;
+    for quadrant in QUADRANTS {
+        let mut rows = vec![Row { depth: 1, start: Slope::new(-1, 1), end: Slope::new(1, 1) }];
+        while let Some(mut row) = rows.pop() {
+            if row.depth > max_depth {
+                continue;
+            }
+            let mut prev_opaque = None;
+            for col in row.cols() {
+                let (x, y) = quadrant.transform(origin, row.depth, col);
+                let opaque = is_opaque(x, y);
+                let (dx, dy) = ((x - origin.x) as f32, (y - origin.y) as f32);
+                if (opaque || row.is_symmetric(col)) && dx * dx + dy * dy <= radius * radius {
+                    reveal(x, y);
+                }
+                if prev_opaque == Some(true) && !opaque {
+                    row.start = Slope::of(row.depth, col);
+                }
+                if prev_opaque == Some(false) && opaque {
+                    let end = Slope::of(row.depth, col);
+                    rows.push(Row { depth: row.depth + 1, start: row.start, end });
+                }
+                prev_opaque = Some(opaque);
+            }
+            if prev_opaque == Some(false) {
+                rows.push(Row { depth: row.depth + 1, ..row });
+            }
+        }
+    }
+}
+
+/// One of the 4 quarters of the view around the origin, the rows go away from the origin.
+#[derive(Clone, Copy)]
+enum Quadrant {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Quadrant {
+    /// Map position of the block in the given row (depth) and column of the quadrant.
+    fn transform(self, origin: Point2<i64>, depth: i64, col: i64) -> (i64, i64) {
+        match self {
+            Self::North => (origin.x + col, origin.y - depth),
+            Self::South => (origin.x + col, origin.y + depth),
+            Self::East => (origin.x + depth, origin.y + col),
+            Self::West => (origin.x - depth, origin.y + col),
+        }
+    }
+}
+
+const QUADRANTS: [Quadrant; 4] = [Quadrant::North, Quadrant::East, Quadrant::South, Quadrant::West];
+
+/// Exact slope (a fraction with a positive denominator), floats would get the ties wrong.
+#[derive(Clone, Copy, Debug)]
+struct Slope {
+    num: i64,
+    den: i64,
+}
+
+impl Slope {
+    fn new(num: i64, den: i64) -> Self {
+        Self { num, den }
+    }
+
+    /// Slope of the left edge of the block in the given row and column.
+    fn of(depth: i64, col: i64) -> Self {
+        Self::new(2 * col - 1, 2 * depth)
+    }
+}
+
+/// A row of blocks of a quadrant, between the start and end slopes.
+#[derive(Clone, Copy, Debug)]
+struct Row {
+    depth: i64,
+    start: Slope,
+    end: Slope,
+}
+
+impl Row {
+    /// Columns of the blocks (at least partially) within the slopes: `depth * start`
+    /// rounded half up, to `depth * end` rounded half down.
+    fn cols(&self) -> std::ops::RangeInclusive<i64> {
+        let (s, e) = (self.start, self.end);
+        let min = (2 * self.depth * s.num + s.den).div_euclid(2 * s.den);
+        let max = -(e.den - 2 * self.depth * e.num).div_euclid(2 * e.den);
+        min..=max
+    }
+
+    /// Whether the center of the block in the column is within the slopes.
+    fn is_symmetric(&self, col: i64) -> bool {
+        col * self.start.den >= self.depth * self.start.num
+            && col * self.end.den <= self.depth * self.end.num
+    }
+}
+
+// --------------------------------------------------
+// --- TEST ---
+// --------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::compute;
+    use crate::level::{Block, LevelFile};
+    use cgmath::Point2;
+    use std::collections::HashSet;
+
+    fn visible(blocks: &[Vec<Block>], origin: (usize, usize), radius: f32) -> HashSet<(i64, i64)> {
+        let is_opaque = |x: i64, y: i64| {
+            let block = blocks.get(x as usize).and_then(|column| column.get(y as usize));
+            x < 0 || y < 0 || block.is_none_or(|it| *it != Block::Free)
+        };
+        let mut result = HashSet::new();
+        let origin = Point2::new(origin.0 as i64, origin.1 as i64);
+        compute(origin, radius, is_opaque, |x, y| {
+            result.insert((x, y));
+        });
+        result
+    }
+
+    #[test]
+    fn test_fov_room() {
+        let blocks = LevelFile::load("level0").unwrap().blocks;
+        let seen = visible(&blocks, (2, 2), 20.0);
+        // the whole first room, its walls included
+        for x in 0..=5 {
+            for y in 0..=4 {
+                assert!(seen.contains(&(x, y)), "{:?}", (x, y));
+            }
+        }
+        // nothing behind the walls
+        assert!(!seen.contains(&(10, 11)));
+        assert!(!seen.contains(&(10, 6)));
+        assert!(seen.iter().all(|(_, y)| *y <= 4));
+
+        // limited by the radius
+        let seen = visible(&blocks, (2, 2), 2.0);
+        assert!(seen.contains(&(4, 2)) && !seen.contains(&(5, 2)) && !seen.contains(&(4, 4)));
+    }
+
+    #[test]
+    fn test_fov_symmetric() {
+        let blocks = LevelFile::load("level1").unwrap().blocks;
+        let floor: Vec<_> = (0..blocks.len())
+            .flat_map(|x| (0..blocks[x].len()).map(move |y| (x, y)))
+            .filter(|(x, y)| blocks[*x][*y] == Block::Free)
+            .step_by(7)
+            .collect();
+        let seen: Vec<_> = floor.iter().map(|it| visible(&blocks, *it, 50.0)).collect();
+        for (i, a) in floor.iter().enumerate() {
+            for (j, b) in floor.iter().enumerate() {
+                let (a, b) = ((a.0 as i64, a.1 as i64), (b.0 as i64, b.1 as i64));
+                assert_eq!(seen[i].contains(&b), seen[j].contains(&a), "{:?} {:?}", a, b);
+            }
+        }
+    }
+}
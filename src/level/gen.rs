@@ -0,0 +1,418 @@
+use super::{Block, Blocks, LevelFile, Spawn, SpawnKind};
+use cgmath::Point2;
+use std::collections::VecDeque;
+
+// --------------------------------------------------
+// --- GENERATOR ---
+// --------------------------------------------------
+
+/// Dungeon layout styles supported by the generator.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Style {
+    /// Rectangular rooms connected by corridors, laid out by binary space partitioning.
+    Rooms,
+    /// Organic caves grown by a cellular automaton.
+    Caves,
+}
+
+impl Style {
+    /// Parse a style name as used in generated level ids.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "rooms" => Some(Self::Rooms),
+            "caves" => Some(Self::Caves),
+            _ => None,
+        }
+    }
+}
+
+/// Generate a level of the given style and size from a seed.
+/// The same arguments always produce the same level.
+/// All free blocks are connected and the player spawn is always on a free block.
+pub fn generate(style: Style, width: usize, height: usize, seed: u64) -> LevelFile {
+    let (width, height) = (width.max(GEN_MIN_SIZE), height.max(GEN_MIN_SIZE));
+    let mut rng = Rng::new(seed);
+    let (mut blocks, spawn) = match style {
+        Style::Rooms => Rooms::generate(&mut rng, width, height),
+        Style::Caves => Caves::generate(&mut rng, width, height),
+    };
+    build_walls(&mut blocks);
+
+    let mut spawns = vec![Spawn { kind: SpawnKind::Player, position: spawn }];
+    spawns.extend(place_items(&mut rng, &blocks, spawn));
+
+    LevelFile {
+        name: format!("{:?} #{}", style, seed),
+        blocks,
+        spawns,
+        stairs_down: None,
+        stairs_up: None,
+        ambient: crate::light::LIGHT_AMBIENT,
+        torch_light: crate::light::TORCH_LIGHT,
+    }
+}
+
+/// Scatter some items over free blocks, one per [`GEN_ITEM_AREA`] free blocks.
+/// Items never share a block with each other or with the player spawn.
+fn place_items(rng: &mut Rng, blocks: &Blocks, spawn: Point2<usize>) -> Vec<Spawn> {
+    let mut free = vec![];
+    for (x, column) in blocks.iter().enumerate() {
+        for (y, block) in column.iter().enumerate() {
+            if *block == Block::Free && (x, y) != (spawn.x, spawn.y) {
+                free.push((x, y));
+            }
+        }
+    }
+    let count = free.len() / GEN_ITEM_AREA;
+    let mut items = Vec::with_capacity(count);
+    for _ in 0..count {
+        let (x, y) = free.swap_remove(rng.range(0, free.len()));
+        let kind = SpawnKind::Item(*rng.pick(&GEN_ITEM_GLYPHS));
+        items.push(Spawn { kind, position: Point2::new(x, y) });
+    }
+    items
+}
+
+/// Turn every non-free block touching a free block (diagonals included) into a wall,
+/// everything else which is not free becomes void.
+fn build_walls(blocks: &mut Blocks) {
+    let (w, h) = (blocks.len(), blocks[0].len());
+    for x in 0..w {
+        for y in 0..h {
+            if blocks[x][y] == Block::Free {
+                continue;
+            }
+            let near_free = neighbours8(x, y, w, h).any(|(i, j)| blocks[i][j] == Block::Free);
+            blocks[x][y] = if near_free { Block::Wall } else { Block::Void };
+        }
+    }
+}
+
+/// Coordinates of the neighbours of a cell (diagonals included) which are inside the map.
+fn neighbours8(x: usize, y: usize, w: usize, h: usize) -> impl Iterator<Item = (usize, usize)> {
+    (-1..=1)
+        .flat_map(|dx| (-1..=1).map(move |dy| (dx, dy)))
+        .filter(|&d| d != (0, 0))
+        .map(move |(dx, dy)| (x as i32 + dx, y as i32 + dy))
+        .filter(move |&(i, j)| i >= 0 && j >= 0 && i < w as i32 && j < h as i32)
+        .map(|(i, j)| (i as usize, j as usize))
+}
+
+/// Flood fill free blocks (4-way) starting at the given point, returns all the reached points.
+fn flood_fill(blocks: &Blocks, start: (usize, usize)) -> Vec<(usize, usize)> {
+    let (w, h) = (blocks.len(), blocks[0].len());
+    let mut visited = vec![vec![false; h]; w];
+    let (mut queue, mut result) = (VecDeque::from([start]), vec![]);
+    visited[start.0][start.1] = true;
+    while let Some((x, y)) = queue.pop_front() {
+        result.push((x, y));
+        let next = [(x.wrapping_sub(1), y), (x + 1, y), (x, y.wrapping_sub(1)), (x, y + 1)];
+        for (i, j) in next {
+            if i < w && j < h && !visited[i][j] && blocks[i][j] == Block::Free {
+                visited[i][j] = true;
+                queue.push_back((i, j));
+            }
+        }
+    }
+    result
+}
+
+// --------------------------------------------------
+// --- ROOMS ---
+// --------------------------------------------------
+
+/// Rooms-and-corridors generator based on binary space partitioning.
+struct Rooms;
+
+/// A rectangle in block coordinates, `x`/`y` is the top-left corner.
+#[derive(Clone, Copy, Debug)]
+struct Rect {
+    x: usize,
+    y: usize,
+    w: usize,
+    h: usize,
+}
+
+impl Rect {
+    fn center(&self) -> (usize, usize) {
+        (self.x + self.w / 2, self.y + self.h / 2)
+    }
+}
+
+impl Rooms {
+    fn generate(rng: &mut Rng, width: usize, height: usize) -> (Blocks, Point2<usize>) {
+        let mut blocks = vec![vec![Block::Void; height]; width];
+        // keep a margin of one block on every side, so that the walls fit into the map
+        let area = Rect { x: 1, y: 1, w: width - 2, h: height - 2 };
+        let rooms = Self::split(rng, &mut blocks, area, 0);
+        let (x, y) = rooms[0].center();
+        (blocks, Point2::new(x, y))
+    }
+
+    /// Recursively split an area, carve rooms in the leaves and connect the siblings.
+    /// Returns the carved rooms of the area, the first one is always a leaf of the left branch.
+    fn split(rng: &mut Rng, blocks: &mut Blocks, area: Rect, depth: u32) -> Vec<Rect> {
+        let can_split_x = area.w >= ROOMS_MIN_LEAF * 2;
+        let can_split_y = area.h >= ROOMS_MIN_LEAF * 2;
+        if depth >= ROOMS_MAX_DEPTH || (!can_split_x && !can_split_y) {
+            return vec![Self::carve_room(rng, blocks, area)];
+        }
+
+        let split_x = match (can_split_x, can_split_y) {
+            (true, true) => area.w > area.h || (area.w == area.h && rng.chance(0.5)),
+            (split_x, _) => split_x,
+        };
+        let (a, b) = if split_x {
+            let at = rng.range(ROOMS_MIN_LEAF, area.w - ROOMS_MIN_LEAF + 1);
+            (Rect { w: at, ..area }, Rect { x: area.x + at, w: area.w - at, ..area })
+        } else {
+            let at = rng.range(ROOMS_MIN_LEAF, area.h - ROOMS_MIN_LEAF + 1);
+            (Rect { h: at, ..area }, Rect { y: area.y + at, h: area.h - at, ..area })
+        };
+
+        let mut rooms = Self::split(rng, blocks, a, depth + 1);
+        let other = Self::split(rng, blocks, b, depth + 1);
+        let (from, to) = (rng.pick(&rooms).center(), rng.pick(&other).center());
+        Self::carve_corridor(rng, blocks, from, to);
+        rooms.extend(other);
+        rooms
+    }
+
+    /// Carve a randomly sized room inside a leaf area.
+    fn carve_room(rng: &mut Rng, blocks: &mut Blocks, leaf: Rect) -> Rect {
+        // a leaf keeps a gap of one block to its neighbours, so that rooms never merge
+        let (max_w, max_h) = (leaf.w - 1, leaf.h - 1);
+        let w = rng.range(ROOMS_MIN_ROOM.min(max_w), max_w + 1);
+        let h = rng.range(ROOMS_MIN_ROOM.min(max_h), max_h + 1);
+        let x = leaf.x + rng.range(0, max_w - w + 1);
+        let y = leaf.y + rng.range(0, max_h - h + 1);
+        let room = Rect { x, y, w, h };
+        for column in blocks.iter_mut().skip(x).take(w) {
+            column.iter_mut().skip(y).take(h).for_each(|b| *b = Block::Free);
+        }
+        room
+    }
+
+    /// Carve an L-shaped, two blocks wide corridor between two points.
+    fn carve_corridor(rng: &mut Rng, blocks: &mut Blocks, a: (usize, usize), b: (usize, usize)) {
+        let corner = if rng.chance(0.5) { (b.0, a.1) } else { (a.0, b.1) };
+        for (p, q) in [(a, corner), (corner, b)] {
+            let (x0, x1) = (p.0.min(q.0), p.0.max(q.0));
+            let (y0, y1) = (p.1.min(q.1), p.1.max(q.1));
+            for x in x0..=x1 + 1 {
+                for y in y0..=y1 + 1 {
+                    // the margin is never carved, even for the corridors
+                    if x < blocks.len() - 1 && y < blocks[0].len() - 1 {
+                        blocks[x][y] = Block::Free;
+                    }
+                }
+            }
+        }
+    }
+}
+
+const ROOMS_MAX_DEPTH: u32 = 4;
+const ROOMS_MIN_LEAF: usize = 7;
+const ROOMS_MIN_ROOM: usize = 4;
+
+// --------------------------------------------------
+// --- CAVES ---
+// --------------------------------------------------
+
+/// Cave generator based on a cellular automaton.
+struct Caves;
+
+impl Caves {
+    fn generate(rng: &mut Rng, width: usize, height: usize) -> (Blocks, Point2<usize>) {
+        for attempt in 1.. {
+            let mut blocks = Self::random_fill(rng, width, height);
+            for _ in 0..CAVES_STEPS {
+                blocks = Self::step(&blocks);
+            }
+            let min_area = if attempt < CAVES_MAX_ATTEMPTS { CAVES_MIN_AREA } else { 0.0 };
+            if let Some(spawn) = Self::keep_largest_cave(rng, &mut blocks, min_area) {
+                return (blocks, spawn);
+            }
+        }
+        unreachable!("cave generation attempts are unbounded")
+    }
+
+    /// Fill the map with random rock, the border is always rock.
+    fn random_fill(rng: &mut Rng, width: usize, height: usize) -> Blocks {
+        let mut blocks = vec![vec![Block::Void; height]; width];
+        for (x, column) in blocks.iter_mut().enumerate() {
+            for (y, block) in column.iter_mut().enumerate() {
+                let border = x == 0 || y == 0 || x == width - 1 || y == height - 1;
+                if !border && !rng.chance(CAVES_FILL_CHANCE) {
+                    *block = Block::Free;
+                }
+            }
+        }
+        blocks
+    }
+
+    /// A single automaton step: rock survives/appears when enough neighbours are rock.
+    fn step(blocks: &Blocks) -> Blocks {
+        let (w, h) = (blocks.len(), blocks[0].len());
+        let mut result = blocks.clone();
+        for (x, column) in result.iter_mut().enumerate().take(w - 1).skip(1) {
+            for (y, block) in column.iter_mut().enumerate().take(h - 1).skip(1) {
+                let rock = neighbours8(x, y, w, h).filter(|&(i, j)| blocks[i][j] != Block::Free);
+                *block = if rock.count() >= CAVES_ROCK_LIMIT { Block::Void } else { Block::Free };
+            }
+        }
+        result
+    }
+
+    /// Fill every cave except the largest one, returns a spawn point inside of it.
+    /// Gives up (returns `None`) when the largest cave is smaller than the given map fraction.
+    fn keep_largest_cave(
+        rng: &mut Rng,
+        blocks: &mut Blocks,
+        min_area: f32,
+    ) -> Option<Point2<usize>> {
+        let (w, h) = (blocks.len(), blocks[0].len());
+        let mut caves: Vec<Vec<(usize, usize)>> = vec![];
+        let mut seen = vec![vec![false; h]; w];
+        for x in 0..w {
+            for y in 0..h {
+                if blocks[x][y] == Block::Free && !seen[x][y] {
+                    let cave = flood_fill(blocks, (x, y));
+                    cave.iter().for_each(|&(i, j)| seen[i][j] = true);
+                    caves.push(cave);
+                }
+            }
+        }
+
+        let largest = caves.iter().enumerate().max_by_key(|(_, c)| c.len())?.0;
+        let cave = caves.swap_remove(largest);
+        caves.iter().flatten().for_each(|&(x, y)| blocks[x][y] = Block::Void);
+        if (cave.len() as f32) < (w * h) as f32 * min_area {
+            return None;
+        }
+        let (x, y) = *rng.pick(&cave);
+        Some(Point2::new(x, y))
+    }
+}
+
+const CAVES_FILL_CHANCE: f32 = 0.45;
+const CAVES_STEPS: u32 = 5;
+const CAVES_ROCK_LIMIT: usize = 5;
+const CAVES_MIN_AREA: f32 = 0.25;
+const CAVES_MAX_ATTEMPTS: u32 = 16;
+
+pub const GEN_DEFAULT_SIZE: (usize, usize) = (48, 32);
+const GEN_MIN_SIZE: usize = 16;
+const GEN_ITEM_AREA: usize = 80;
+/// Glyphs of the generated items, repeated ones are more common.
+const GEN_ITEM_GLYPHS: [char; 8] = ['c', 'c', 'c', 'h', 'm', 'k', 't', 's'];
+
+// --------------------------------------------------
+// --- RNG ---
+// --------------------------------------------------
+
+/// Small deterministic pseudo random number generator (SplitMix64).
+/// Implemented here so that the generated levels never change with dependency updates.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Random number in `lo..hi` range, `lo` when the range is empty.
+    pub fn range(&mut self, lo: usize, hi: usize) -> usize {
+        if hi <= lo {
+            return lo;
+        }
+        lo + (self.next_u64() % (hi - lo) as u64) as usize
+    }
+
+    /// Random number in `0.0..1.0` range.
+    pub fn unit(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.unit() < probability
+    }
+
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.range(0, items.len())]
+    }
+}
+
+// --------------------------------------------------
+// --- TEST ---
+// --------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::{flood_fill, generate, Style};
+    use crate::level::{Block, Collision, DungeonTile};
+
+    const STYLES: [Style; 2] = [Style::Rooms, Style::Caves];
+
+    #[test]
+    fn test_gen_same_seed_same_level() {
+        for style in STYLES {
+            let a = generate(style, 48, 32, 1234);
+            let b = generate(style, 48, 32, 1234);
+            assert_eq!(a.blocks, b.blocks);
+            assert_eq!(a.spawns, b.spawns);
+            assert_ne!(a.blocks, generate(style, 48, 32, 4321).blocks);
+        }
+    }
+
+    #[test]
+    fn test_gen_connected_with_valid_spawn() {
+        for style in STYLES {
+            for seed in 0..20 {
+                let level = generate(style, 48, 32, seed);
+                let spawn = level.spawns[0].position;
+                assert_eq!(level.blocks[spawn.x][spawn.y], Block::Free);
+
+                let free = level.blocks.iter().flatten().filter(|b| **b == Block::Free).count();
+                assert_eq!(flood_fill(&level.blocks, (spawn.x, spawn.y)).len(), free);
+
+                let items: Vec<_> = level.spawns.iter().skip(1).map(|s| s.position).collect();
+                assert!(!items.is_empty());
+                for (i, item) in items.iter().enumerate() {
+                    assert_eq!(level.blocks[item.x][item.y], Block::Free);
+                    assert_ne!(*item, spawn);
+                    assert!(!items[i + 1..].contains(item));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_gen_free_blocks_are_enclosed() {
+        for style in STYLES {
+            let level = generate(style, 40, 40, 7);
+            let (w, h) = (level.blocks.len(), level.blocks[0].len());
+            for x in 0..w {
+                for y in 0..h {
+                    if level.blocks[x][y] != Block::Free {
+                        continue;
+                    }
+                    assert!(x > 0 && y > 0 && x < w - 1 && y < h - 1);
+                    assert_ne!(level.blocks[x - 1][y], Block::Void);
+                    assert_ne!(level.blocks[x + 1][y], Block::Void);
+                    assert_ne!(level.blocks[x][y - 1], Block::Void);
+                    assert_ne!(level.blocks[x][y + 1], Block::Void);
+                }
+            }
+            assert!(!Collision::new(&level.blocks).aabbs.is_empty());
+            assert_eq!(DungeonTile::map_blocks_to_dungeon_tiles(&level.blocks).len(), w);
+        }
+    }
+}
@@ -1,8 +1,25 @@
 pub mod app;
+pub(crate) mod cli;
+pub(crate) mod combat;
+pub(crate) mod config;
+pub(crate) mod console;
+pub(crate) mod cursor;
+pub(crate) mod debug;
+pub(crate) mod enemy;
+pub(crate) mod gamepad;
 pub(crate) mod geometry;
+pub(crate) mod hud;
 pub(crate) mod input;
+pub(crate) mod inventory;
+pub(crate) mod item;
 pub(crate) mod level;
+pub(crate) mod light;
+pub(crate) mod menu;
+pub(crate) mod nav;
 pub(crate) mod observer;
 pub(crate) mod player;
 pub(crate) mod scene;
+pub(crate) mod sprite;
+pub(crate) mod stats;
+pub(crate) mod text;
 pub(crate) mod video;
@@ -0,0 +1,318 @@
+use crate::level::{bounding_region, fov, tile_at, TileRegion};
+use cgmath::{MetricSpace, Point2};
+
+// --------------------------------------------------
+// --- LIGHT ---
+// --------------------------------------------------
+
+/// A colored point light, fading out towards its radius.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Light {
+    pub position: Point2<f32>,
+    pub color: [f32; 3],
+    pub radius: f32,
+}
+
+impl Light {
+    /// Light contributed at the given distance, quadratic falloff to zero at the radius.
+    fn falloff(&self, distance: f32) -> f32 {
+        let t = (1.0 - distance / self.radius).max(0.0);
+        t * t
+    }
+}
+
+/// Color and radius of a kind of lights, e.g. the wall torches of a level.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LightSpec {
+    pub color: [f32; 3],
+    pub radius: f32,
+}
+
+impl LightSpec {
+    /// Light of this kind placed at the given world position.
+    pub fn at(self, position: Point2<f32>) -> Light {
+        Light { position, color: self.color, radius: self.radius }
+    }
+}
+
+/// Short-lived light (e.g. a spell effect), fading out over its duration.
+struct Flash {
+    light: Light,
+    timer: f32,
+    duration: f32,
+}
+
+// --------------------------------------------------
+// --- LIGHTING ---
+// --------------------------------------------------
+
+/// Lights of a level: the ambient light, the static wall torches and the flashes.
+/// At most [`MAX_LIGHTS`] lights are in effect at once, walls block the light.
+pub struct Lighting {
+    pub ambient: [f32; 3],
+    pub torches: Vec<Light>,
+    flashes: Vec<Flash>,
+
+    /// Lights in effect since the last [`Lighting::update`], moved to the centers of their blocks.
+    active: Vec<Light>,
+    /// Block the torches were last sorted by their distance to.
+    focus: Option<Point2<usize>>,
+    /// Relight on the next [`Lighting::update`] even when the lights haven't changed.
+    dirty: bool,
+    /// Per block (columns first), bit `i` is set when the `i`-th active light reaches the block.
+    masks: Vec<u32>,
+    size: (usize, usize),
+}
+
+impl Lighting {
+    /// Creates the lighting of a level of the given size (width, height) in blocks.
+    pub fn new(size: (usize, usize), ambient: [f32; 3], torches: Vec<Light>) -> Self {
+        Self {
+            ambient,
+            torches,
+            flashes: vec![],
+            active: vec![],
+            focus: None,
+            dirty: true,
+            masks: vec![0; size.0 * size.1],
+            size,
+        }
+    }
+
+    /// Light up the area for a while, the oldest flash goes out when there are too many.
+    pub fn flash(&mut self, light: Light, duration: f32) {
+        if self.flashes.len() >= MAX_FLASHES {
+            self.flashes.remove(0);
+        }
+        self.flashes.push(Flash { light, timer: duration, duration });
+    }
+
+    /// Advance the flashes by `dt` seconds.
+    pub fn advance(&mut self, dt: f32) {
+        self.flashes.iter_mut().for_each(|it| it.timer -= dt);
+        self.flashes.retain(|it| it.timer > 0.0);
+    }
+
+    /// Relight on the next [`Lighting::update`], e.g. when the blocks or the torches have changed.
+    pub fn invalidate(&mut self) {
+        self.focus = None;
+        self.dirty = true;
+    }
+
+    /// Pick the lights in effect: the given ones (e.g. the player light), the flashes and then
+    /// the torches nearest to the focus (e.g. the player). Find the blocks every one of them
+    /// reaches, the opaque blocks cast shadows (see [`fov::compute`]).
+    /// The lights shine from the centers of their blocks (see [`LIGHT_FACING`] for the lights
+    /// inside the opaque ones), so nothing is done while the lights stay on the same blocks
+    /// and keep their colors. Returns the region of the blocks whose light has changed.
+    pub fn update(
+        &mut self,
+        lights: &[Light],
+        focus: Point2<f32>,
+        is_opaque: impl Fn(i64, i64) -> bool,
+    ) -> Option<TileRegion> {
+        if let Some(tile) = tile_at(focus, self.size).filter(|it| Some(*it) != self.focus) {
+            self.focus = Some(tile);
+            let focus = Point2::new(tile.x as f32, tile.y as f32);
+            self.torches.sort_by(|a, b| {
+                a.position.distance2(focus).total_cmp(&b.position.distance2(focus))
+            });
+        }
+        let flashes = self.flashes.iter().map(|it| {
+            let fade = (it.timer / it.duration * FLASH_FADE_STEPS).ceil() / FLASH_FADE_STEPS;
+            Light { color: it.light.color.map(|c| c * fade), ..it.light }
+        });
+        let size = self.size;
+        let active: Vec<_> = lights
+            .iter()
+            .copied()
+            .chain(flashes)
+            .chain(self.torches.iter().copied())
+            .filter_map(|it| {
+                let tile = tile_at(it.position, size)?;
+                let (x, y) = (tile.x as i64, tile.y as i64);
+                // a light inside an opaque block (a wall torch) shines from the block it faces,
+                // otherwise it would light up both the sides of a wall
+                let (dx, dy) = if is_opaque(x, y) {
+                    LIGHT_FACING.into_iter().find(|(dx, dy)| !is_opaque(x + dx, y + dy))?
+                } else {
+                    (0, 0)
+                };
+                Some(Light { position: Point2::new((x + dx) as f32, (y + dy) as f32), ..it })
+            })
+            .take(MAX_LIGHTS)
+            .collect();
+        if !self.dirty && active == self.active {
+            return None;
+        }
+        // the blocks reached by a changed light, before or after, have to be tinted again
+        let changed_lights = (0..MAX_LIGHTS)
+            .filter(|i| active.get(*i) != self.active.get(*i))
+            .fold(0u32, |bits, i| bits | 1 << i);
+        self.active = active;
+        self.dirty = false;
+
+        let h = self.size.1;
+        let mut masks = vec![0; self.masks.len()];
+        for (i, light) in self.active.iter().enumerate() {
+            let origin = Point2::new(light.position.x as i64, light.position.y as i64);
+            fov::compute(origin, light.radius, &is_opaque, |x, y| {
+                if x >= 0 && y >= 0 && (y as usize) < h {
+                    if let Some(mask) = masks.get_mut(x as usize * h + y as usize) {
+                        *mask |= 1 << i;
+                    }
+                }
+            });
+        }
+        let previous = std::mem::replace(&mut self.masks, masks);
+        let changed = previous
+            .iter()
+            .zip(&self.masks)
+            .enumerate()
+            .filter(|(_, (old, new))| old != new || (*old | *new) & changed_lights != 0);
+        bounding_region(changed.map(|(i, _)| Point2::new(i / h, i % h)))
+    }
+
+    /// Light at the world position: the ambient light and the lights reaching the block under it.
+    pub fn at(&self, position: Point2<f32>) -> [f32; 3] {
+        match tile_at(position, self.size) {
+            Some(tile) => self.at_tile(tile, position),
+            None => self.ambient,
+        }
+    }
+
+    /// Light at the world position as if it was in the given block, every channel is at most 1.
+    /// The block decides which lights reach the position, e.g. for the corners of the block.
+    pub fn at_tile(&self, tile: Point2<usize>, position: Point2<f32>) -> [f32; 3] {
+        let mask = self.masks.get(tile.x * self.size.1 + tile.y).copied().unwrap_or(0);
+        let mut result = self.ambient;
+        let lights = self.active.iter().enumerate().filter(|(i, _)| mask & (1 << i) != 0);
+        for (_, light) in lights {
+            let falloff = light.falloff(light.position.distance(position));
+            for (channel, color) in result.iter_mut().zip(light.color) {
+                *channel += color * falloff;
+            }
+        }
+        result.map(|it| it.min(1.0))
+    }
+}
+
+/// Lights in effect at once, one bit per light in the block masks.
+pub const MAX_LIGHTS: usize = 32;
+const MAX_FLASHES: usize = 8;
+/// Where a light inside an opaque block shines from: the first free neighbouring block
+/// in this order, the one below it first.
+const LIGHT_FACING: [(i64, i64); 4] = [(0, 1), (0, -1), (1, 0), (-1, 0)];
+/// The flashes fade out in steps, so that the level isn't relit on every tick.
+const FLASH_FADE_STEPS: f32 = 4.0;
+pub const LIGHT_AMBIENT: [f32; 3] = [0.4, 0.4, 0.45];
+pub const TORCH_LIGHT: LightSpec = LightSpec { color: [1.0, 0.6, 0.3], radius: 4.5 };
+pub const PLAYER_LIGHT: LightSpec = LightSpec { color: [0.9, 0.8, 0.6], radius: 5.0 };
+
+// --------------------------------------------------
+// --- TEST ---
+// --------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::{Light, LightSpec, Lighting, MAX_LIGHTS};
+    use cgmath::Point2;
+
+    const RED: LightSpec = LightSpec { color: [1.0, 0.0, 0.0], radius: 4.0 };
+
+    /// 10x5 room split by a wall at x = 5, with a door at y = 4.
+    fn is_opaque(x: i64, y: i64) -> bool {
+        !(0..10).contains(&x) || !(0..5).contains(&y) || (x == 5 && y != 4)
+    }
+
+    #[test]
+    fn test_lighting_falloff_and_occlusion() {
+        let mut lighting = Lighting::new((10, 5), [0.1; 3], vec![]);
+        let light = RED.at(Point2::new(3.0, 1.0));
+        lighting.update(&[light], light.position, is_opaque);
+
+        assert_eq!(lighting.at(Point2::new(3.0, 1.0)), [1.0, 0.1, 0.1]);
+        let near = lighting.at(Point2::new(2.0, 1.0))[0];
+        let far = lighting.at(Point2::new(0.0, 1.0))[0];
+        assert!(near > far && far > 0.1);
+        // the wall itself is lit, but nothing behind it
+        assert!(lighting.at(Point2::new(5.0, 1.0))[0] > 0.1);
+        assert_eq!(lighting.at(Point2::new(6.0, 1.0)), [0.1; 3]);
+        // outside of the radius and outside of the map
+        assert_eq!(lighting.at(Point2::new(3.0, 1.0 + 4.5)), [0.1; 3]);
+    }
+
+    #[test]
+    fn test_lighting_bounded() {
+        let torches = (0..MAX_LIGHTS + 8)
+            .map(|i| RED.at(Point2::new((i % 10) as f32, (i / 10) as f32)))
+            .collect();
+        let mut lighting = Lighting::new((10, 5), [0.0; 3], torches);
+        let light = RED.at(Point2::new(8.0, 2.0));
+        lighting.update(&[light], light.position, is_opaque);
+        assert_eq!(lighting.active.len(), MAX_LIGHTS);
+        // the given lights go first, then the torches nearest to the focus
+        assert_eq!(lighting.active[0], light);
+        assert_eq!(lighting.active[1].position, Point2::new(8.0, 2.0));
+    }
+
+    #[test]
+    fn test_lighting_torch_in_wall() {
+        // the torch is in the (one block thick) wall between the rooms, and faces the right one
+        let mut lighting = Lighting::new((10, 5), [0.1; 3], vec![RED.at(Point2::new(5.0, 1.0))]);
+        lighting.update(&[], Point2::new(5.0, 1.0), |x, y| is_opaque(x, y) || x == 5);
+        assert_eq!(lighting.active[0].position, Point2::new(6.0, 1.0));
+        assert!(lighting.at(Point2::new(7.0, 1.0))[0] > 0.1);
+        assert!(lighting.at(Point2::new(5.0, 1.0))[0] > 0.1);
+        for x in 0..5 {
+            for y in 0..5 {
+                assert_eq!(lighting.at(Point2::new(x as f32, y as f32)), [0.1; 3]);
+            }
+        }
+
+        // a torch with no free block around doesn't shine at all
+        let mut lighting = Lighting::new((10, 5), [0.1; 3], vec![RED.at(Point2::new(3.0, 1.0))]);
+        lighting.update(&[], Point2::new(3.0, 1.0), |_, _| true);
+        assert!(lighting.active.is_empty());
+    }
+
+    #[test]
+    fn test_lighting_unchanged() {
+        let mut lighting = Lighting::new((10, 5), [0.0; 3], vec![RED.at(Point2::new(1.0, 1.0))]);
+        let light = RED.at(Point2::new(3.0, 1.0));
+        assert!(lighting.update(&[light], light.position, is_opaque).is_some());
+        let lit = lighting.at(Point2::new(2.0, 1.0));
+
+        // the lights shine from the centers of their blocks
+        let light = RED.at(Point2::new(3.2, 0.8));
+        assert_eq!(lighting.update(&[light], light.position, is_opaque), None);
+        assert_eq!(lighting.at(Point2::new(2.0, 1.0)), lit);
+        // the blocks reached by the changed light, before or after
+        let light = Light { radius: 3.0, ..light };
+        assert_eq!(lighting.update(&[light], light.position, is_opaque), Some((0..6, 0..5)));
+
+        // relit, only the blocks whose light has changed are returned (the shadow of a new wall)
+        lighting.invalidate();
+        assert_eq!(lighting.update(&[light], light.position, is_opaque), None);
+        lighting.invalidate();
+        let is_opaque = |x, y| is_opaque(x, y) || (x, y) == (2, 2);
+        assert_eq!(lighting.update(&[light], light.position, is_opaque), Some((1..5, 3..5)));
+    }
+
+    #[test]
+    fn test_lighting_flash() {
+        let mut lighting = Lighting::new((10, 5), [0.0; 3], vec![]);
+        let focus = Point2::new(2.0, 2.0);
+        lighting.flash(RED.at(focus), 1.0);
+        lighting.update(&[], focus, is_opaque);
+        assert_eq!(lighting.at(focus)[0], 1.0);
+
+        lighting.advance(0.5);
+        lighting.update(&[], focus, is_opaque);
+        assert_eq!(lighting.at(focus)[0], 0.5);
+
+        lighting.advance(0.5);
+        lighting.update(&[], focus, is_opaque);
+        assert_eq!(lighting.at(focus), [0.0; 3]);
+    }
+}
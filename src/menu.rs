@@ -0,0 +1,415 @@
+use crate::{
+    config::Config,
+    input::Action,
+    text::{measure, Text, GLYPH_HEIGHT},
+};
+use cgmath::{Point2, Vector2};
+use winit::{event::KeyEvent, keyboard::KeyCode};
+
+// --------------------------------------------------
+// --- MENU ---
+// --------------------------------------------------
+
+/// Top-level state of the game: which menu is shown, or whether the scene is played.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameState {
+    MainMenu,
+    Playing,
+    Paused,
+    Options,
+    GameOver,
+}
+
+impl GameState {
+    /// Whether the scene simulation runs in the state.
+    pub fn is_simulated(&self) -> bool {
+        matches!(self, Self::Playing | Self::GameOver)
+    }
+}
+
+/// Something the menu can't do on its own, handled by the application.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MenuAction {
+    NewGame,
+    Quit,
+    /// The config was changed in the options, it should be applied and saved.
+    ConfigChanged,
+}
+
+/// Game state machine together with the menus: main menu, pause, options and game over.
+pub struct Menu {
+    pub state: GameState,
+    /// State the options menu goes back to.
+    options_parent: GameState,
+    selected: usize,
+    /// Action waiting for a new key in the options menu.
+    rebinding: Option<Action>,
+    /// Whether there is a game to continue from the main menu, not while the player is dead.
+    game_started: bool,
+}
+
+impl Menu {
+    /// Creates a new menu, the game starts in the main menu.
+    pub fn new() -> Self {
+        Self {
+            state: GameState::MainMenu,
+            options_parent: GameState::MainMenu,
+            selected: 0,
+            rebinding: None,
+            game_started: false,
+        }
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.state == GameState::Playing
+    }
+
+    /// Open the pause menu, if the game is being played.
+    pub fn pause(&mut self) {
+        if self.is_playing() {
+            self.switch(GameState::Paused);
+        }
+    }
+
+    /// Follow the state of the scene: the game is over while the player is dead.
+    pub fn update(&mut self, player_alive: bool) {
+        match self.state {
+            GameState::Playing if !player_alive => {
+                self.game_started = false;
+                self.switch(GameState::GameOver);
+            }
+            GameState::GameOver if player_alive => {
+                self.game_started = true;
+                self.switch(GameState::Playing);
+            }
+            _ => {}
+        }
+    }
+
+    /// Handle a key press while a menu is shown.
+    pub fn handle_key_event(&mut self, e: &KeyEvent, config: &mut Config) -> Option<MenuAction> {
+        crate::input::pressed_key(e).and_then(|key_code| self.handle_key(key_code, config))
+    }
+
+    /// Handle a pressed key, see [`Menu::handle_key_event`].
+    pub fn handle_key(&mut self, key_code: KeyCode, config: &mut Config) -> Option<MenuAction> {
+        if let Some(action) = self.rebinding.take() {
+            if key_code == KeyCode::Escape {
+                return None;
+            }
+            // the keys of the actions which can't be rebound aren't taken away from them,
+            // another key is waited for instead (the rebindable ones are swapped)
+            let taken = config.keys.action_of(key_code);
+            if taken.is_some_and(|it| !Action::REBINDABLE.contains(&it)) {
+                self.rebinding = Some(action);
+                return None;
+            }
+            config.keys.rebind(action, key_code);
+            return Some(MenuAction::ConfigChanged);
+        }
+
+        let entries = self.entries();
+        match key_code {
+            KeyCode::ArrowUp => self.select(&entries, entries.len() - 1),
+            KeyCode::ArrowDown => self.select(&entries, 1),
+            KeyCode::Enter | KeyCode::Space => {
+                return entries.get(self.selected).and_then(|it| self.activate(*it, config, 1))
+            }
+            KeyCode::ArrowLeft | KeyCode::ArrowRight => {
+                let step = if key_code == KeyCode::ArrowLeft { -1 } else { 1 };
+                return match entries.get(self.selected) {
+                    Some(entry @ (Entry::WindowMode | Entry::Volume)) => {
+                        self.activate(*entry, config, step)
+                    }
+                    _ => None,
+                };
+            }
+            KeyCode::Escape => match self.state {
+                GameState::Paused => self.switch(GameState::Playing),
+                GameState::Options => self.switch(self.options_parent),
+                _ => {}
+            },
+            _ => {}
+        }
+        None
+    }
+
+    /// Queue the menu of the current state for drawing, centered in the window.
+    pub fn draw(&self, text: &mut Text, config: &Config, screen_size: (u32, u32)) {
+        let Some(title) = self.title() else {
+            return;
+        };
+        let screen = Vector2::new(screen_size.0 as f32, screen_size.1 as f32);
+        let scale = crate::hud::ui_scale(screen_size.1);
+
+        let title_scale = scale * MENU_TITLE_SCALE;
+        let size = measure(title, title_scale);
+        let mut y = screen.y * MENU_TITLE_Y;
+        text.queue(title, Point2::new((screen.x - size.x) / 2.0, y), title_scale, COLOR_TITLE);
+        y += size.y + GLYPH_HEIGHT * scale;
+
+        if self.state == GameState::GameOver {
+            let subtitle = "respawning...";
+            let size = measure(subtitle, scale);
+            text.queue(subtitle, Point2::new((screen.x - size.x) / 2.0, y), scale, COLOR_DISABLED);
+            y += size.y * 2.0;
+        }
+
+        let entry_scale = scale * MENU_ENTRY_SCALE;
+        for (i, entry) in self.entries().into_iter().enumerate() {
+            let label = self.label(entry, config);
+            let label =
+                if i == self.selected { format!("> {} <", label) } else { label.to_string() };
+            let color = match i {
+                _ if !self.is_enabled(entry) => COLOR_DISABLED,
+                _ if i == self.selected => COLOR_SELECTED,
+                _ => COLOR_ENTRY,
+            };
+            let size = measure(&label, entry_scale);
+            text.queue(&label, Point2::new((screen.x - size.x) / 2.0, y), entry_scale, color);
+            y += size.y * MENU_LINE_SPACING;
+        }
+    }
+
+    /// Go to another state, the first enabled entry of its menu gets selected.
+    fn switch(&mut self, state: GameState) {
+        log::info!("game state {:?} -> {:?}", self.state, state);
+        self.state = state;
+        self.rebinding = None;
+        self.selected = 0;
+        let entries = self.entries();
+        self.select(&entries, 0);
+    }
+
+    /// Move the selection by the given offset (wrapping), skipping the disabled entries.
+    fn select(&mut self, entries: &[Entry], offset: usize) {
+        if entries.is_empty() {
+            return;
+        }
+        let mut selected = (self.selected + offset) % entries.len();
+        while !self.is_enabled(entries[selected]) {
+            selected = (selected + offset.max(1)) % entries.len();
+        }
+        self.selected = selected;
+    }
+
+    /// Do what the entry says, `step` is the direction of the value change (-1 or 1).
+    fn activate(&mut self, entry: Entry, config: &mut Config, step: i32) -> Option<MenuAction> {
+        match entry {
+            Entry::NewGame => {
+                self.game_started = true;
+                self.switch(GameState::Playing);
+                return Some(MenuAction::NewGame);
+            }
+            Entry::Continue | Entry::Resume => self.switch(GameState::Playing),
+            Entry::Options => {
+                self.options_parent = self.state;
+                self.switch(GameState::Options);
+            }
+            Entry::MainMenu => self.switch(GameState::MainMenu),
+            Entry::Quit => return Some(MenuAction::Quit),
+            Entry::Rebind(action) => self.rebinding = Some(action),
+            Entry::WindowMode => {
+                config.window.mode = config.window.mode.toggle();
+                return Some(MenuAction::ConfigChanged);
+            }
+            Entry::Volume => {
+                // volume goes in steps of 10%, wrapping around at both ends
+                let steps = (config.audio.volume * 10.0).round() as i32 + step;
+                config.audio.volume = steps.rem_euclid(11) as f32 / 10.0;
+                return Some(MenuAction::ConfigChanged);
+            }
+            Entry::Back => self.switch(self.options_parent),
+        }
+        None
+    }
+
+    fn title(&self) -> Option<&'static str> {
+        match self.state {
+            GameState::MainMenu => Some("ROGUELIKE"),
+            GameState::Playing => None,
+            GameState::Paused => Some("PAUSED"),
+            GameState::Options => Some("OPTIONS"),
+            GameState::GameOver => Some("YOU DIED"),
+        }
+    }
+
+    fn entries(&self) -> Vec<Entry> {
+        match self.state {
+            GameState::MainMenu => {
+                vec![Entry::NewGame, Entry::Continue, Entry::Options, Entry::Quit]
+            }
+            GameState::Playing => vec![],
+            GameState::Paused => vec![Entry::Resume, Entry::Options, Entry::MainMenu, Entry::Quit],
+            GameState::Options => {
+                let mut entries: Vec<_> =
+                    Action::REBINDABLE.into_iter().map(Entry::Rebind).collect();
+                entries.extend([Entry::WindowMode, Entry::Volume, Entry::Back]);
+                entries
+            }
+            GameState::GameOver => vec![Entry::MainMenu, Entry::Quit],
+        }
+    }
+
+    fn is_enabled(&self, entry: Entry) -> bool {
+        entry != Entry::Continue || self.game_started
+    }
+
+    fn label(&self, entry: Entry, config: &Config) -> String {
+        match entry {
+            Entry::NewGame => "New game".to_string(),
+            Entry::Continue => "Continue".to_string(),
+            Entry::Resume => "Resume".to_string(),
+            Entry::Options => "Options".to_string(),
+            Entry::MainMenu => "Main menu".to_string(),
+            Entry::Quit => "Quit".to_string(),
+            Entry::Rebind(action) if self.rebinding == Some(action) => {
+                format!("{}: press a key...", action.name())
+            }
+            Entry::Rebind(action) => format!("{}: {}", action.name(), config.keys.describe(action)),
+            Entry::WindowMode => format!("Window: {:?}", config.window.mode),
+            Entry::Volume => format!("Volume: {:.0}%", config.audio.volume * 100.0),
+            Entry::Back => "Back".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Entry {
+    NewGame,
+    Continue,
+    Resume,
+    Options,
+    MainMenu,
+    Quit,
+    Rebind(Action),
+    WindowMode,
+    Volume,
+    Back,
+}
+
+/// Title position, relative to the window height.
+const MENU_TITLE_Y: f32 = 0.25;
+const MENU_TITLE_SCALE: f32 = 3.0;
+const MENU_ENTRY_SCALE: f32 = 1.5;
+const MENU_LINE_SPACING: f32 = 1.25;
+
+const COLOR_TITLE: [f32; 4] = [1.0, 0.85, 0.4, 1.0];
+const COLOR_ENTRY: [f32; 4] = [0.85, 0.85, 0.85, 1.0];
+const COLOR_SELECTED: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const COLOR_DISABLED: [f32; 4] = [0.5, 0.5, 0.5, 1.0];
+
+// --------------------------------------------------
+// --- TEST ---
+// --------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::{GameState, Menu, MenuAction};
+    use crate::{
+        config::{Config, WindowMode},
+        input::Action,
+    };
+    use winit::keyboard::KeyCode;
+
+    #[test]
+    fn test_menu_new_game_pause_and_resume() {
+        let (mut menu, mut config) = (Menu::new(), Config::default());
+        // "Continue" is disabled without a started game, so it gets skipped
+        menu.handle_key(KeyCode::ArrowDown, &mut config);
+        menu.handle_key(KeyCode::ArrowUp, &mut config);
+        assert_eq!(menu.handle_key(KeyCode::Enter, &mut config), Some(MenuAction::NewGame));
+        assert!(menu.is_playing());
+
+        menu.pause();
+        assert_eq!(menu.state, GameState::Paused);
+        menu.handle_key(KeyCode::Escape, &mut config);
+        assert_eq!(menu.state, GameState::Playing);
+
+        // back to the main menu, the game can be continued now
+        menu.pause();
+        menu.handle_key(KeyCode::ArrowDown, &mut config);
+        menu.handle_key(KeyCode::ArrowDown, &mut config);
+        assert_eq!(menu.handle_key(KeyCode::Enter, &mut config), None);
+        assert_eq!(menu.state, GameState::MainMenu);
+        menu.handle_key(KeyCode::ArrowDown, &mut config);
+        menu.handle_key(KeyCode::Enter, &mut config);
+        assert!(menu.is_playing());
+    }
+
+    #[test]
+    fn test_menu_skips_disabled_entries() {
+        let (mut menu, mut config) = (Menu::new(), Config::default());
+        menu.handle_key(KeyCode::ArrowDown, &mut config);
+        assert_eq!(menu.selected, 2);
+        menu.handle_key(KeyCode::ArrowUp, &mut config);
+        menu.handle_key(KeyCode::ArrowUp, &mut config);
+        assert_eq!(menu.selected, 3);
+        assert_eq!(menu.handle_key(KeyCode::Enter, &mut config), Some(MenuAction::Quit));
+    }
+
+    #[test]
+    fn test_menu_options() {
+        let (mut menu, mut config) = (Menu::new(), Config::default());
+        menu.handle_key(KeyCode::ArrowDown, &mut config);
+        menu.handle_key(KeyCode::Enter, &mut config);
+        assert_eq!(menu.state, GameState::Options);
+
+        // rebind "Move up"
+        menu.handle_key(KeyCode::Enter, &mut config);
+        let action = menu.handle_key(KeyCode::KeyI, &mut config);
+        assert_eq!(action, Some(MenuAction::ConfigChanged));
+        assert_eq!(config.keys.get(Action::MoveUp), [KeyCode::KeyI]);
+        // the key was bound to another action, which gets the old key instead
+        assert_eq!(config.keys.get(Action::OpenInventory), [KeyCode::ArrowUp, KeyCode::Tab]);
+
+        // the keys of the actions which can't be rebound are refused
+        menu.handle_key(KeyCode::Enter, &mut config);
+        assert_eq!(menu.handle_key(KeyCode::Digit1, &mut config), None);
+        assert_eq!(menu.handle_key(KeyCode::KeyU, &mut config), Some(MenuAction::ConfigChanged));
+        assert_eq!(config.keys.get(Action::MoveUp), [KeyCode::KeyU]);
+        assert_eq!(config.keys.get(Action::UseSlot1), [KeyCode::Digit1]);
+        // and back to "I"
+        menu.handle_key(KeyCode::Enter, &mut config);
+        menu.handle_key(KeyCode::KeyI, &mut config);
+
+        // the rebinding can be canceled
+        menu.handle_key(KeyCode::Enter, &mut config);
+        assert_eq!(menu.handle_key(KeyCode::Escape, &mut config), None);
+        assert_eq!(config.keys.get(Action::MoveUp), [KeyCode::KeyI]);
+
+        // window mode and volume
+        for _ in Action::REBINDABLE {
+            menu.handle_key(KeyCode::ArrowDown, &mut config);
+        }
+        menu.handle_key(KeyCode::ArrowRight, &mut config);
+        assert_eq!(config.window.mode, WindowMode::Fullscreen);
+        menu.handle_key(KeyCode::ArrowDown, &mut config);
+        menu.handle_key(KeyCode::ArrowLeft, &mut config);
+        assert_eq!(config.audio.volume, 0.7);
+
+        menu.handle_key(KeyCode::Escape, &mut config);
+        assert_eq!(menu.state, GameState::MainMenu);
+    }
+
+    #[test]
+    fn test_menu_game_over() {
+        let (mut menu, mut config) = (Menu::new(), Config::default());
+        menu.handle_key(KeyCode::Enter, &mut config);
+        menu.update(true);
+        assert_eq!(menu.state, GameState::Playing);
+        menu.update(false);
+        assert_eq!(menu.state, GameState::GameOver);
+        assert!(menu.state.is_simulated());
+        menu.update(true);
+        assert_eq!(menu.state, GameState::Playing);
+
+        // a dead game can't be continued from the main menu
+        menu.update(false);
+        menu.handle_key(KeyCode::Enter, &mut config);
+        assert_eq!(menu.state, GameState::MainMenu);
+        assert!(!menu.is_enabled(super::Entry::Continue));
+        menu.handle_key(KeyCode::ArrowDown, &mut config);
+        assert_eq!(menu.selected, 2);
+    }
+}
@@ -0,0 +1,464 @@
+use crate::level::{tile_at, Block};
+use cgmath::{Point2, Vector2};
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+};
+
+// --------------------------------------------------
+// --- NAV ---
+// --------------------------------------------------
+
+/// Map positions of the blocks along a path.
+type TilePath = Vec<Point2<usize>>;
+
+/// Navigation grid of a level: which blocks a character of the given collision radius
+/// can stand on, and paths between them. The found paths are cached until the grid is rebuilt
+/// (or updated around them, see [`Nav::update`]).
+pub struct Nav {
+    size: (usize, usize),
+    /// Blocks a character collides with (anything but [`Block::Free`]), same layout as the blocks.
+    solid: Vec<Vec<bool>>,
+    /// Blocks the center of a character can be on without touching a solid block.
+    walkable: Vec<Vec<bool>>,
+    radius: f32,
+
+    cache: HashMap<(Point2<usize>, Point2<usize>), Option<TilePath>>,
+    generation: u64,
+}
+
+impl Nav {
+    /// Creates a new navigation grid for characters of the given collision radius (box half size).
+    pub fn new(blocks: &[Vec<Block>], radius: f32) -> Self {
+        let mut nav = Self {
+            size: (0, 0),
+            solid: vec![],
+            walkable: vec![],
+            radius,
+            cache: HashMap::new(),
+            generation: 0,
+        };
+        nav.rebuild(blocks);
+        nav
+    }
+
+    /// Build the grid anew from the (changed) blocks, the cached paths are dropped.
+    pub fn rebuild(&mut self, blocks: &[Vec<Block>]) {
+        self.size = (blocks.len(), blocks.first().map_or(0, Vec::len));
+        self.solid = blocks
+            .iter()
+            .map(|column| column.iter().map(|it| *it != Block::Free).collect())
+            .collect();
+        let (w, h) = self.size;
+        self.walkable =
+            (0..w).map(|x| (0..h).map(|y| self.has_clearance(x, y)).collect()).collect();
+        self.cache.clear();
+        self.generation += 1;
+    }
+
+    /// Update the grid after the block at the given map position has changed. Only the blocks
+    /// around it are checked again, and only the cached paths going through the blocks which
+    /// aren't walkable anymore (or cutting their corners) are dropped (or the ones which found no way, when some blocks
+    /// became walkable).
+    pub fn update(&mut self, blocks: &[Vec<Block>], x: usize, y: usize) {
+        let Some(block) = blocks.get(x).and_then(|column| column.get(y)) else {
+            return;
+        };
+        let Some(solid) = self.solid.get_mut(x).and_then(|column| column.get_mut(y)) else {
+            return;
+        };
+        *solid = *block != Block::Free;
+        self.generation += 1;
+
+        let reach = (0.5 + self.radius).ceil() as usize;
+        let (w, h) = self.size;
+        let (mut closed, mut opened) = (vec![], false);
+        for x in x.saturating_sub(reach)..(x + reach + 1).min(w) {
+            for y in y.saturating_sub(reach)..(y + reach + 1).min(h) {
+                let walkable = self.has_clearance(x, y);
+                if walkable != self.walkable[x][y] {
+                    self.walkable[x][y] = walkable;
+                    opened |= walkable;
+                    if !walkable {
+                        closed.push(Point2::new(x, y));
+                    }
+                }
+            }
+        }
+        // a diagonal step needs the blocks at both its corners too, see `Nav::neighbours`
+        let crosses = |path: &TilePath| {
+            let corners = path.windows(2).flat_map(|step| {
+                let (a, b) = (step[0], step[1]);
+                [Point2::new(a.x, b.y), Point2::new(b.x, a.y)]
+            });
+            path.iter().copied().chain(corners).any(|it| closed.contains(&it))
+        };
+        self.cache.retain(|_, path| match path {
+            Some(path) => !crosses(path),
+            None => !opened,
+        });
+    }
+
+    /// Size of the grid in blocks (width, height).
+    pub fn size(&self) -> (usize, usize) {
+        self.size
+    }
+
+    /// Incremented on every rebuild and update, so that the paths found before
+    /// can be recognized as stale.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Whether a character can stand in the center of the block at the given map position.
+    pub fn is_walkable(&self, tile: Point2<usize>) -> bool {
+        self.walkable.get(tile.x).and_then(|column| column.get(tile.y)).copied().unwrap_or(false)
+    }
+
+    /// Smoothed path between the world positions, as a list of waypoints to walk through
+    /// (the start excluded, the end included). `None` when the end can't be reached.
+    pub fn path(&mut self, from: Point2<f32>, to: Point2<f32>) -> Option<Vec<Point2<f32>>> {
+        let start = tile_at(from, self.size)?;
+        let goal = tile_at(to, self.size)?;
+        let tiles = self.find_path(start, goal)?;
+        // the path starts and ends where asked, not in the block centers
+        let mut points = vec![from];
+        points.extend(tiles.iter().skip(1).map(|it| Point2::new(it.x as f32, it.y as f32)));
+        if points.len() > 1 {
+            points.pop();
+        }
+        points.push(to);
+        Some(self.smooth(&points))
+    }
+
+    /// Shortest path between the blocks (both included), 8-way A* over the walkable blocks.
+    /// A diagonal step can't cut a corner: both the blocks next to it have to be walkable too.
+    pub fn find_path(
+        &mut self,
+        start: Point2<usize>,
+        goal: Point2<usize>,
+    ) -> Option<Vec<Point2<usize>>> {
+        if let Some(path) = self.cache.get(&(start, goal)) {
+            return path.clone();
+        }
+        let path = self.search(start, goal);
+        if self.cache.len() >= NAV_CACHE_CAPACITY {
+            self.cache.clear();
+        }
+        self.cache.insert((start, goal), path.clone());
+        path
+    }
+
+    /// Whether a character can move in a straight line between the world positions
+    /// without touching any solid block.
+    pub fn is_clear(&self, a: Point2<f32>, b: Point2<f32>) -> bool {
+        let reach = 0.5 + self.radius - NAV_CLEARANCE_EPSILON;
+        let (x0, x1) = ((a.x.min(b.x) - reach).round(), (a.x.max(b.x) + reach).round());
+        let (y0, y1) = ((a.y.min(b.y) - reach).round(), (a.y.max(b.y) + reach).round());
+        for x in x0 as i64..=x1 as i64 {
+            for y in y0 as i64..=y1 as i64 {
+                if self.is_solid(x, y)
+                    && segment_hits_box(a, b, Point2::new(x as f32, y as f32), reach)
+                {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// The A* search itself, see [`Nav::find_path`].
+    fn search(&self, start: Point2<usize>, goal: Point2<usize>) -> Option<Vec<Point2<usize>>> {
+        let (w, h) = self.size;
+        if start.x >= w || start.y >= h || !self.is_walkable(goal) {
+            return None;
+        }
+        let index = |tile: Point2<usize>| tile.x * h + tile.y;
+        let mut cost = vec![u32::MAX; w * h];
+        let mut came_from = vec![usize::MAX; w * h];
+        let mut open = BinaryHeap::new();
+        cost[index(start)] = 0;
+        open.push(Reverse((heuristic(start, goal), 0, index(start))));
+
+        while let Some(Reverse((_, reached, current))) = open.pop() {
+            // a stale entry, the block has been reached by a cheaper path since
+            if reached > cost[current] {
+                continue;
+            }
+            let tile = Point2::new(current / h, current % h);
+            if tile == goal {
+                let mut path = vec![tile];
+                let mut i = current;
+                while came_from[i] != usize::MAX {
+                    i = came_from[i];
+                    path.push(Point2::new(i / h, i % h));
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for (next, step) in self.neighbours(tile) {
+                let next_cost = cost[current] + step;
+                if next_cost < cost[index(next)] {
+                    cost[index(next)] = next_cost;
+                    came_from[index(next)] = current;
+                    open.push(Reverse((next_cost + heuristic(next, goal), next_cost, index(next))));
+                }
+            }
+        }
+        None
+    }
+
+    /// Walkable blocks reachable by a single step from the given one, with the step costs.
+    fn neighbours(&self, tile: Point2<usize>) -> impl Iterator<Item = (Point2<usize>, u32)> + '_ {
+        let walkable = move |dx: i64, dy: i64| {
+            let (x, y) = (tile.x as i64 + dx, tile.y as i64 + dy);
+            x >= 0 && y >= 0 && self.is_walkable(Point2::new(x as usize, y as usize))
+        };
+        NEIGHBOURS.into_iter().filter_map(move |(dx, dy)| {
+            let diagonal = dx != 0 && dy != 0;
+            if !walkable(dx, dy) || (diagonal && !(walkable(dx, 0) && walkable(0, dy))) {
+                return None;
+            }
+            let next = Point2::new((tile.x as i64 + dx) as usize, (tile.y as i64 + dy) as usize);
+            Some((next, if diagonal { COST_DIAGONAL } else { COST_STRAIGHT }))
+        })
+    }
+
+    /// Drop the waypoints which can be skipped by walking straight to a later one.
+    fn smooth(&self, points: &[Point2<f32>]) -> Vec<Point2<f32>> {
+        let mut result = vec![];
+        let mut anchor = 0;
+        while anchor + 1 < points.len() {
+            let mut next = anchor + 1;
+            while next + 1 < points.len() && self.is_clear(points[anchor], points[next + 1]) {
+                next += 1;
+            }
+            result.push(points[next]);
+            anchor = next;
+        }
+        result
+    }
+
+    /// Whether a character standing in the center of the block doesn't touch any solid block.
+    fn has_clearance(&self, x: usize, y: usize) -> bool {
+        let reach = (0.5 + self.radius).ceil() as i64;
+        let (x, y) = (x as i64, y as i64);
+        for dx in -reach..=reach {
+            for dy in -reach..=reach {
+                let touches =
+                    (dx.abs() as f32) < 0.5 + self.radius && (dy.abs() as f32) < 0.5 + self.radius;
+                if touches && self.is_solid(x + dx, y + dy) {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// Little helper function, anything outside of the map is solid.
+    fn is_solid(&self, x: i64, y: i64) -> bool {
+        if x < 0 || y < 0 {
+            return true;
+        }
+        self.solid
+            .get(x as usize)
+            .and_then(|column| column.get(y as usize))
+            .copied()
+            .unwrap_or(true)
+    }
+}
+
+/// Octile distance, the lower bound of the path cost.
+fn heuristic(a: Point2<usize>, b: Point2<usize>) -> u32 {
+    let dx = a.x.abs_diff(b.x) as u32;
+    let dy = a.y.abs_diff(b.y) as u32;
+    COST_STRAIGHT * dx.max(dy) + (COST_DIAGONAL - COST_STRAIGHT) * dx.min(dy)
+}
+
+/// Whether the segment intersects the square box (given by its center and half size),
+/// the slab test.
+fn segment_hits_box(a: Point2<f32>, b: Point2<f32>, center: Point2<f32>, half_size: f32) -> bool {
+    let delta: Vector2<f32> = b - a;
+    let (mut t0, mut t1) = (0.0f32, 1.0f32);
+    for (origin, d, c) in [(a.x, delta.x, center.x), (a.y, delta.y, center.y)] {
+        let (lo, hi) = (c - half_size, c + half_size);
+        if d.abs() < f32::EPSILON {
+            if origin <= lo || origin >= hi {
+                return false;
+            }
+            continue;
+        }
+        let (near, far) = ((lo - origin) / d, (hi - origin) / d);
+        t0 = t0.max(near.min(far));
+        t1 = t1.min(near.max(far));
+        if t0 >= t1 {
+            return false;
+        }
+    }
+    true
+}
+
+const NEIGHBOURS: [(i64, i64); 8] =
+    [(0, -1), (0, 1), (-1, 0), (1, 0), (-1, -1), (1, -1), (-1, 1), (1, 1)];
+/// Step costs, a diagonal step is roughly `sqrt(2)` times longer.
+const COST_STRAIGHT: u32 = 10;
+const COST_DIAGONAL: u32 = 14;
+/// Cached paths, the whole cache is dropped when it gets full.
+const NAV_CACHE_CAPACITY: usize = 256;
+/// Touching a wall (e.g. walking along it) doesn't count as a collision.
+const NAV_CLEARANCE_EPSILON: f32 = 0.001;
+
+// --------------------------------------------------
+// --- TEST ---
+// --------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::Nav;
+    use crate::{
+        combat::BODY_HALF_SIZE,
+        level::{Block, LevelFile},
+    };
+    use cgmath::Point2;
+
+    fn load(id: &str) -> (Vec<Vec<Block>>, Nav) {
+        let blocks = LevelFile::load(id).unwrap().blocks;
+        let nav = Nav::new(&blocks, BODY_HALF_SIZE);
+        (blocks, nav)
+    }
+
+    /// Every step goes to a walkable neighbour, diagonal steps don't cut corners.
+    fn assert_valid(nav: &Nav, path: &[Point2<usize>]) {
+        for step in path.windows(2) {
+            let (a, b) = (step[0], step[1]);
+            assert!(nav.is_walkable(b));
+            assert!(a.x.abs_diff(b.x) <= 1 && a.y.abs_diff(b.y) <= 1 && a != b);
+            assert!(nav.is_walkable(Point2::new(a.x, b.y)));
+            assert!(nav.is_walkable(Point2::new(b.x, a.y)));
+        }
+    }
+
+    #[test]
+    fn test_nav_walkable() {
+        let (blocks, nav) = load("level0");
+        for (x, column) in blocks.iter().enumerate() {
+            for (y, block) in column.iter().enumerate() {
+                assert_eq!(nav.is_walkable(Point2::new(x, y)), *block == Block::Free);
+            }
+        }
+        assert!(!nav.is_walkable(Point2::new(100, 100)));
+
+        // a wide character doesn't fit next to the walls, nor into the corridor
+        let nav = Nav::new(&blocks, 0.6);
+        assert!(!nav.is_walkable(Point2::new(1, 1)));
+        assert!(nav.is_walkable(Point2::new(2, 2)));
+        assert!(!nav.is_walkable(Point2::new(10, 6)));
+    }
+
+    #[test]
+    fn test_nav_find_path_level0() {
+        let (_, mut nav) = load("level0");
+        // from the spawn through the corridor to the stairs
+        let path = nav.find_path(Point2::new(2, 2), Point2::new(18, 11)).unwrap();
+        assert_eq!(path.first(), Some(&Point2::new(2, 2)));
+        assert_eq!(path.last(), Some(&Point2::new(18, 11)));
+        assert_valid(&nav, &path);
+        assert!(path.iter().any(|it| it.y == 6 && (it.x == 10 || it.x == 11)));
+        // 16 blocks right and 9 down, diagonal steps shorten it
+        assert!(path.len() < 16 + 9);
+
+        assert_eq!(
+            nav.find_path(Point2::new(2, 2), Point2::new(2, 2)),
+            Some(vec![Point2::new(2, 2)])
+        );
+        // walls, void and outside of the map
+        assert_eq!(nav.find_path(Point2::new(2, 2), Point2::new(0, 0)), None);
+        assert_eq!(nav.find_path(Point2::new(2, 2), Point2::new(0, 5)), None);
+        assert_eq!(nav.find_path(Point2::new(2, 2), Point2::new(50, 5)), None);
+    }
+
+    #[test]
+    fn test_nav_find_path_level1() {
+        let (_, mut nav) = load("level1");
+        // around the walled-off block in the middle
+        let path = nav.find_path(Point2::new(3, 14), Point2::new(25, 14)).unwrap();
+        assert_valid(&nav, &path);
+        assert!(path.iter().all(|it| !(7..=21).contains(&it.x) || !(11..=18).contains(&it.y)));
+        // inside of the walled-off block is unreachable
+        assert_eq!(nav.find_path(Point2::new(3, 14), Point2::new(14, 14)), None);
+    }
+
+    #[test]
+    fn test_nav_no_corner_cutting() {
+        // .#
+        // ..
+        let blocks = vec![vec![Block::Free, Block::Free], vec![Block::Wall, Block::Free]];
+        let mut nav = Nav::new(&blocks, BODY_HALF_SIZE);
+        let path = nav.find_path(Point2::new(0, 0), Point2::new(1, 1)).unwrap();
+        assert_eq!(path, vec![Point2::new(0, 0), Point2::new(0, 1), Point2::new(1, 1)]);
+    }
+
+    #[test]
+    fn test_nav_smoothed_path() {
+        let (_, mut nav) = load("level0");
+        let (from, to) = (Point2::new(2.0, 2.0), Point2::new(18.2, 11.0));
+        let path = nav.path(from, to).unwrap();
+        let tiles = nav.find_path(Point2::new(2, 2), Point2::new(18, 11)).unwrap();
+        assert!(path.len() < tiles.len());
+        assert_eq!(path.last(), Some(&to));
+        let mut a = from;
+        for b in path.iter() {
+            assert!(nav.is_clear(a, *b));
+            a = *b;
+        }
+
+        // straight within the room, not through the walls
+        assert_eq!(nav.path(from, Point2::new(5.0, 2.0)), Some(vec![Point2::new(5.0, 2.0)]));
+        assert!(!nav.is_clear(from, Point2::new(2.0, 11.0)));
+    }
+
+    #[test]
+    fn test_nav_cache_invalidation() {
+        let (mut blocks, mut nav) = load("level0");
+        let (start, goal) = (Point2::new(2, 2), Point2::new(18, 11));
+        assert!(nav.find_path(start, goal).is_some());
+        assert_eq!(nav.cache.len(), 1);
+        assert!(nav.find_path(start, goal).is_some());
+        assert_eq!(nav.cache.len(), 1);
+
+        // wall off the corridor
+        let generation = nav.generation();
+        blocks[10][6] = Block::Wall;
+        blocks[11][6] = Block::Wall;
+        nav.rebuild(&blocks);
+        assert!(nav.cache.is_empty());
+        assert_eq!(nav.generation(), generation + 1);
+        assert_eq!(nav.find_path(start, goal), None);
+    }
+
+    #[test]
+    fn test_nav_incremental_update() {
+        let (mut blocks, mut nav) = load("level0");
+        let (start, through, beside) = (Point2::new(2, 2), Point2::new(18, 11), Point2::new(18, 2));
+        assert!(nav.find_path(start, through).is_some());
+        assert!(nav.find_path(start, beside).is_some());
+
+        // wall off the corridor, only the path through it is dropped
+        let generation = nav.generation();
+        for x in [10, 11] {
+            blocks[x][6] = Block::Wall;
+            nav.update(&blocks, x, 6);
+        }
+        assert_eq!(nav.generation(), generation + 2);
+        assert_eq!(nav.cache.len(), 1);
+        assert!(nav.cache.contains_key(&(start, beside)));
+        assert_eq!(nav.walkable, Nav::new(&blocks, BODY_HALF_SIZE).walkable);
+        assert_eq!(nav.find_path(start, through), None);
+
+        // open it again, the way is found anew
+        blocks[10][6] = Block::Free;
+        nav.update(&blocks, 10, 6);
+        assert_eq!(nav.walkable, Nav::new(&blocks, BODY_HALF_SIZE).walkable);
+        assert!(nav.find_path(start, through).is_some());
+    }
+}
@@ -1,12 +1,13 @@
-use cgmath::{InnerSpace, Matrix4, MetricSpace, Point3, SquareMatrix, Vector3};
+use cgmath::{InnerSpace, Matrix4, MetricSpace, Point2, Point3, SquareMatrix, Vector3, Vector4};
 use wgpu::util::DeviceExt;
 
-use crate::player::SPAWN_POSITION;
-
 pub struct Observer {
     eye: Point3<f32>,
     target: Point3<f32>,
     up: Vector3<f32>,
+    prev_position: Point2<f32>,
+    /// Interpolated position of the last rendered frame, see [`Observer::screen_to_world`].
+    rendered_position: Point2<f32>,
 
     left: f32,
     right: f32,
@@ -14,32 +15,23 @@ pub struct Observer {
     top: f32,
     near: f32,
     far: f32,
+    screen_size: (u32, u32),
+    pixels_per_tile: f32,
 
     uniform: ObserverUniform,
-    buffer: wgpu::Buffer,
-    pub bind_group: wgpu::BindGroup,
+    binding: Option<ObserverBinding>,
 }
 
 impl Observer {
-    pub fn new(video: &crate::video::Video) -> Self {
-        let uniform = ObserverUniform { view_proj: Matrix4::identity().into() };
-
-        let buffer = video.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("observer_buffer"),
-            contents: bytemuck::cast_slice(&[uniform]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
-
-        let bind_group = video.device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("observer_bind_group"),
-            layout: &video.bind_group_layouts[crate::video::BIND_GROUP_OBSERVER as usize],
-            entries: &[wgpu::BindGroupEntry { binding: 0, resource: buffer.as_entire_binding() }],
-        });
-
+    /// Create a new observer instance.
+    /// The GPU buffer is not created here, see [`Observer::prepare_render`].
+    pub fn new(position: Point2<f32>) -> Self {
         Self {
-            eye: Point3::new(SPAWN_POSITION.0, 1.0, SPAWN_POSITION.1),
-            target: Point3::new(SPAWN_POSITION.0, 0.0, SPAWN_POSITION.1),
+            eye: Point3::new(position.x, 1.0, position.y),
+            target: Point3::new(position.x, 0.0, position.y),
             up: -Vector3::unit_z(),
+            prev_position: position,
+            rendered_position: position,
 
             left: 0.0,
             right: 0.0,
@@ -47,16 +39,17 @@ impl Observer {
             top: 0.0,
             near: -10.0,
             far: 10.0,
+            screen_size: (0, 0),
+            pixels_per_tile: PIXELS_PER_TILE as f32,
 
-            uniform,
-            buffer,
-            bind_group,
+            uniform: ObserverUniform { view_proj: Matrix4::identity().into() },
+            binding: None,
         }
     }
 
     pub fn handle_resize(&mut self, win_size: (u32, u32)) {
-        let x = (win_size.0 / 2 / PIXELS_PER_TILE) as f32;
-        let y = (win_size.1 / 2 / PIXELS_PER_TILE) as f32;
+        let x = win_size.0 as f32 / 2.0 / self.pixels_per_tile;
+        let y = win_size.1 as f32 / 2.0 / self.pixels_per_tile;
 
         self.left = -x;
         self.right = x;
@@ -64,21 +57,94 @@ impl Observer {
         self.top = y;
         self.near = -10.0;
         self.far = 10.0;
+        self.screen_size = win_size;
+    }
+
+    /// Zoom: size of a single level block on the screen, in pixels.
+    pub fn set_pixels_per_tile(&mut self, pixels_per_tile: u32) {
+        self.pixels_per_tile = pixels_per_tile.max(1) as f32;
+        self.handle_resize(self.screen_size);
     }
 
-    pub fn update(&mut self, video: &crate::video::Video) {
-        let view = Matrix4::look_at_rh(self.eye, self.target, self.up);
+    /// Zoom in (positive steps) or out (negative steps), e.g. by the mouse wheel.
+    /// Every step scales the tiles by [`ZOOM_STEP`], within [`ZOOM_MIN`] and [`ZOOM_MAX`].
+    pub fn zoom(&mut self, steps: f32) {
+        let pixels_per_tile = self.pixels_per_tile * ZOOM_STEP.powf(steps);
+        self.pixels_per_tile = pixels_per_tile.clamp(ZOOM_MIN, ZOOM_MAX);
+        self.handle_resize(self.screen_size);
+    }
+
+    /// Size of a single level block on the screen, in pixels.
+    #[cfg(test)]
+    pub fn pixels_per_tile(&self) -> f32 {
+        self.pixels_per_tile
+    }
+
+    /// Window size in pixels, as of the last [`Observer::handle_resize`].
+    pub fn screen_size(&self) -> (u32, u32) {
+        self.screen_size
+    }
+
+    /// Create the GPU buffer if it is not created yet, and upload the current view projection.
+    /// The view is interpolated between the previous and the current tick, `alpha` is in `0..1`.
+    pub fn prepare_render(&mut self, video: &crate::video::Video, alpha: f32) {
+        let position = self.prev_position + (self.position() - self.prev_position) * alpha;
+        self.rendered_position = position;
+        self.uniform.view_proj = self.view_proj(position).into();
+
+        let uniform = self.uniform;
+        let binding = self.binding.get_or_insert_with(|| ObserverBinding::new(video, uniform));
+        video.queue.write_buffer(&binding.buffer, 0, bytemuck::cast_slice(&[self.uniform]));
+    }
+
+    /// View projection of the observer looking at the given position.
+    fn view_proj(&self, position: Point2<f32>) -> Matrix4<f32> {
+        let offset = Vector3::new(position.x - self.eye.x, 0.0, position.y - self.eye.z);
+        let view = Matrix4::look_at_rh(self.eye + offset, self.target + offset, self.up);
         let proj = cgmath::ortho(self.left, self.right, self.bottom, self.top, self.near, self.far);
-        let view_proj = OPENGL_TO_WGPU_MATRIX * proj * view;
-        self.uniform.view_proj = view_proj.into();
-        video.queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[self.uniform]));
+        OPENGL_TO_WGPU_MATRIX * proj * view
+    }
+
+    /// Convert a window position (in pixels, from the top left corner) into the world (XZ plane)
+    /// coordinates of the last rendered frame, by inverting its view projection.
+    /// `None` while the window has no size yet.
+    pub fn screen_to_world(&self, cursor: Point2<f32>) -> Option<Point2<f32>> {
+        let (width, height) = self.screen_size;
+        if width == 0 || height == 0 {
+            return None;
+        }
+        let inverse = self.view_proj(self.rendered_position).invert()?;
+        // the screen Y axis goes down, the clip space Y axis goes up
+        let ndc = Vector4::new(
+            cursor.x / width as f32 * 2.0 - 1.0,
+            1.0 - cursor.y / height as f32 * 2.0,
+            0.0,
+            1.0,
+        );
+        let world = inverse * ndc;
+        Some(Point2::new(world.x / world.w, world.z / world.w))
+    }
+
+    /// Bind group to be used while rendering, `None` until [`Observer::prepare_render`] is called.
+    pub fn bind_group(&self) -> Option<&wgpu::BindGroup> {
+        self.binding.as_ref().map(|b| &b.bind_group)
+    }
+
+    /// Current observer position in the world (XZ plane) coordinates.
+    pub fn position(&self) -> Point2<f32> {
+        Point2::new(self.eye.x, self.eye.z)
     }
 
-    pub fn follow_player(&mut self, player: &crate::player::Player) {
-        let mut position = (self.eye.x, self.eye.z).into();
+    /// Remember the current position, must be called at the beginning of every simulation tick.
+    pub fn begin_tick(&mut self) {
+        self.prev_position = self.position();
+    }
+
+    pub fn follow_player(&mut self, player: &crate::player::Player, dt: f32) {
+        let mut position = self.position();
         if player.position.distance(position) > CAM_FOLLOW_THRESHOLD {
             let dir = (player.position - position).normalize();
-            position += dir * CAM_FOLLOW_SPEED;
+            position += dir * CAM_FOLLOW_SPEED * dt;
             self.eye.x = position.x;
             self.eye.z = position.y;
             self.target.x = position.x;
@@ -86,23 +152,40 @@ impl Observer {
         }
     }
 
-    pub fn apply_input(&mut self, input: &crate::input::Input) {
-        if input.key_w {
-            self.eye.z -= CAM_MANUAL_SPEED;
-            self.target.z -= CAM_MANUAL_SPEED;
+    /// Instantly move the observer to a new position without following/easing.
+    pub fn teleport(&mut self, position: Point2<f32>) {
+        self.prev_position = position;
+        self.rendered_position = position;
+        self.eye.x = position.x;
+        self.eye.z = position.y;
+        self.target.x = position.x;
+        self.target.z = position.y;
+    }
+
+    pub fn apply_input(&mut self, input: &crate::input::Input, dt: f32) {
+        use crate::input::Action;
+        let speed = CAM_MANUAL_SPEED * dt;
+        if input.is_down(Action::CameraUp) {
+            self.eye.z -= speed;
+            self.target.z -= speed;
         }
-        if input.key_s {
-            self.eye.z += CAM_MANUAL_SPEED;
-            self.target.z += CAM_MANUAL_SPEED;
+        if input.is_down(Action::CameraDown) {
+            self.eye.z += speed;
+            self.target.z += speed;
         }
-        if input.key_a {
-            self.eye.x -= CAM_MANUAL_SPEED;
-            self.target.x -= CAM_MANUAL_SPEED;
+        if input.is_down(Action::CameraLeft) {
+            self.eye.x -= speed;
+            self.target.x -= speed;
         }
-        if input.key_d {
-            self.eye.x += CAM_MANUAL_SPEED;
-            self.target.x += CAM_MANUAL_SPEED;
+        if input.is_down(Action::CameraRight) {
+            self.eye.x += speed;
+            self.target.x += speed;
         }
+        // a gamepad stick pans proportionally
+        self.eye.x += input.camera_stick.x * speed;
+        self.eye.z += input.camera_stick.y * speed;
+        self.target.x += input.camera_stick.x * speed;
+        self.target.z += input.camera_stick.y * speed;
     }
 }
 
@@ -112,15 +195,98 @@ struct ObserverUniform {
     view_proj: [[f32; 4]; 4],
 }
 
-const CAM_MANUAL_SPEED: f32 = 0.065;
-const CAM_FOLLOW_SPEED: f32 = 0.035;
+/// GPU side of the observer: uniform buffer and its bind group.
+struct ObserverBinding {
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl ObserverBinding {
+    fn new(video: &crate::video::Video, uniform: ObserverUniform) -> Self {
+        let buffer = video.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("observer_buffer"),
+            contents: bytemuck::cast_slice(&[uniform]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group = video.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("observer_bind_group"),
+            layout: &video.bind_group_layouts[crate::video::BIND_GROUP_OBSERVER as usize],
+            entries: &[wgpu::BindGroupEntry { binding: 0, resource: buffer.as_entire_binding() }],
+        });
+
+        Self { buffer, bind_group }
+    }
+}
+
+/// Units per second.
+const CAM_MANUAL_SPEED: f32 = 3.9;
+/// Units per second.
+const CAM_FOLLOW_SPEED: f32 = 2.1;
 const CAM_FOLLOW_THRESHOLD: f32 = 2.75;
-const PIXELS_PER_TILE: u32 = 32 * 5;
+pub const PIXELS_PER_TILE: u32 = 32 * 5;
+/// Zoom factor of a single mouse wheel step.
+const ZOOM_STEP: f32 = 1.1;
+/// Pixels per tile when zoomed out the most.
+const ZOOM_MIN: f32 = 24.0;
+/// Pixels per tile when zoomed in the most.
+const ZOOM_MAX: f32 = 480.0;
 
 #[rustfmt::skip]
-const OPENGL_TO_WGPU_MATRIX: Matrix4<f32> = Matrix4::new(
+pub const OPENGL_TO_WGPU_MATRIX: Matrix4<f32> = Matrix4::new(
     1.0, 0.0, 0.0, 0.0,
     0.0, 1.0, 0.0, 0.0,
     0.0, 0.0, 0.5, 0.5,
     0.0, 0.0, 0.0, 1.0,
 );
+
+// --------------------------------------------------
+// --- TEST ---
+// --------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::{Observer, ZOOM_MAX, ZOOM_MIN};
+    use approx::assert_relative_eq;
+    use cgmath::Point2;
+
+    #[test]
+    fn test_observer_screen_to_world() {
+        let mut observer = Observer::new(Point2::new(10.0, 5.0));
+        assert_eq!(observer.screen_to_world(Point2::new(0.0, 0.0)), None);
+        observer.handle_resize((1280, 720));
+        observer.set_pixels_per_tile(160);
+
+        // the center of the screen is the observer position, a tile is 160 pixels wide
+        let center = observer.screen_to_world(Point2::new(640.0, 360.0)).unwrap();
+        assert_relative_eq!(center.x, 10.0, epsilon = 1e-5);
+        assert_relative_eq!(center.y, 5.0, epsilon = 1e-5);
+        let corner = observer.screen_to_world(Point2::new(0.0, 0.0)).unwrap();
+        assert_relative_eq!(corner.x, 6.0, epsilon = 1e-5);
+        assert_relative_eq!(corner.y, 2.75, epsilon = 1e-5);
+        let corner = observer.screen_to_world(Point2::new(1280.0, 720.0)).unwrap();
+        assert_relative_eq!(corner.x, 14.0, epsilon = 1e-5);
+        assert_relative_eq!(corner.y, 7.25, epsilon = 1e-5);
+
+        observer.teleport(Point2::new(0.0, 0.0));
+        let center = observer.screen_to_world(Point2::new(640.0, 360.0)).unwrap();
+        assert_relative_eq!(center.x, 0.0, epsilon = 1e-5);
+        assert_relative_eq!(center.y, 0.0, epsilon = 1e-5);
+    }
+
+    #[test]
+    fn test_observer_zoom() {
+        let mut observer = Observer::new(Point2::new(0.0, 0.0));
+        observer.handle_resize((1280, 720));
+        observer.zoom(1.0);
+        assert!(observer.pixels_per_tile() > 160.0);
+        // zoomed in, the same pixel is closer to the center
+        let edge = observer.screen_to_world(Point2::new(1280.0, 360.0)).unwrap();
+        assert!(edge.x < 4.0);
+
+        observer.zoom(-100.0);
+        assert_eq!(observer.pixels_per_tile(), ZOOM_MIN);
+        observer.zoom(100.0);
+        assert_eq!(observer.pixels_per_tile(), ZOOM_MAX);
+    }
+}
@@ -1,49 +1,117 @@
-use crate::{geometry::Direction, input::Input, video};
-use cgmath::{InnerSpace, Point2, Vector2};
-use thiserror::Error;
-use wgpu::util::DeviceExt;
+use crate::{
+    combat::{Hit, Melee},
+    geometry::Direction,
+    input::{Action as InputAction, Input},
+    inventory::{EquipSlot, Inventory, InventoryError, INVENTORY_CAPACITY},
+    item::ItemKind,
+    sprite::{Action, Animation, Mesh, ANIM_LOOP_SECS},
+    stats::Stats,
+    video,
+};
+use cgmath::{InnerSpace, MetricSpace, Point2, Vector2, Zero};
+use std::collections::VecDeque;
 
 // --------------------------------------------------
 // --- PLAYER ---
 // --------------------------------------------------
 
 /// Represents the player character.
+/// All the speeds are per second, accelerations are per second squared, timers are in seconds.
 pub struct Player {
     pub position: Point2<f32>,
+    prev_position: Point2<f32>,
+    facing: Direction,
 
     velocity: Vector2<f32>,
-    velocity_delta: f32,
-    velocity_max: f32,
+    pub velocity_delta: f32,
+    pub velocity_max: f32,
     velocity_slowdown: f32,
 
-    attack: bool,
+    pub stats: Stats,
+    /// Cheat (see the console), the player character ignores all the hits.
+    pub god: bool,
+    hurt_timer: f32,
+    respawn_timer: f32,
+    respawn_delay: f32,
+    pub melee: Melee,
 
-    pub mesh: Mesh,
+    pub inventory: Inventory,
+
+    /// Waypoints of the walk (e.g. by a mouse click), the next one first.
+    /// Any manual movement cancels the walk.
+    path: VecDeque<Point2<f32>>,
+    /// Closest distance to the next waypoint so far, and how long it has not got any closer.
+    path_best: f32,
+    path_stuck_timer: f32,
+
+    /// Normals of the level collision contacts of the last tick.
+    pub contact_normals: Vec<Vector2<f32>>,
+
+    pub animation: Animation,
+    pub mesh: Option<Mesh>,
 }
 
 impl Player {
     /// Creates a new player character instance.
-    pub fn new(video: &crate::video::Video) -> Result<Self, PlayerError> {
-        let mesh = Mesh::new(video)?;
-
-        Ok(Self {
-            position: SPAWN_POSITION.into(),
+    /// The mesh is not created here, see [`Player::prepare_render`].
+    pub fn new(position: Point2<f32>) -> Self {
+        Self {
+            position,
+            prev_position: position,
+            facing: Direction::Down,
 
             velocity: Vector2::new(0.0, 0.0),
-            velocity_delta: 0.01,
-            velocity_max: 0.025,
-            velocity_slowdown: 0.92,
+            velocity_delta: PLAYER_VELOCITY_DELTA,
+            velocity_max: PLAYER_VELOCITY_MAX,
+            velocity_slowdown: 0.0067,
+
+            stats: Stats::new(PLAYER_HP, PLAYER_HP_REGEN, PLAYER_MP, PLAYER_MP_REGEN),
+            god: false,
+            hurt_timer: 0.0,
+            respawn_timer: 0.0,
+            respawn_delay: PLAYER_RESPAWN_DELAY,
+            melee: Melee::new(PLAYER_MELEE_DAMAGE, PLAYER_MELEE_KNOCKBACK, PLAYER_MELEE_COOLDOWN),
+
+            inventory: Inventory::new(INVENTORY_CAPACITY),
+
+            path: VecDeque::new(),
+            path_best: f32::MAX,
+            path_stuck_timer: 0.0,
+
+            contact_normals: vec![],
+
+            animation: Animation::new(),
+            mesh: None,
+        }
+    }
 
-            attack: false,
+    /// Apply the gameplay tunables, the character gets fully healed.
+    pub fn apply_config(&mut self, config: &crate::config::GameplayConfig) {
+        self.velocity_delta = config.player_velocity_delta;
+        self.velocity_max = config.player_velocity_max;
+        self.stats = Stats::new(
+            config.player_max_hp,
+            config.player_hp_regen,
+            config.player_max_mp,
+            config.player_mp_regen,
+        );
+        self.respawn_delay = config.player_respawn_delay;
+        self.melee =
+            Melee::new(config.player_melee_damage, PLAYER_MELEE_KNOCKBACK, PLAYER_MELEE_COOLDOWN);
+    }
 
-            mesh,
-        })
+    /// Create the rendering resources, if they are not created yet.
+    /// The mesh is drawn between the previous and the current tick positions, `alpha` is in `0..1`.
+    pub fn prepare_render(&mut self, video: &video::Video, alpha: f32) {
+        self.mesh.get_or_insert_with(|| Mesh::new(video, "player"));
+        self.animation.position = self.prev_position + (self.position - self.prev_position) * alpha;
     }
 
-    /// Advance the player character state, physics, etc.
-    pub fn advance(&mut self) {
-        self.position += self.velocity;
-        self.velocity *= self.velocity_slowdown;
+    /// Advance the player character state, physics, etc. by `dt` seconds.
+    pub fn advance(&mut self, dt: f32) {
+        self.prev_position = self.position;
+        self.position += self.velocity * dt;
+        self.velocity *= self.velocity_slowdown.powf(dt);
 
         if self.velocity.x > self.velocity_max {
             self.velocity.x = self.velocity_max;
@@ -58,353 +126,212 @@ impl Player {
             self.velocity.y = -self.velocity_max;
         }
 
-        self.mesh.advance(self.position, self.velocity, self.attack);
+        self.stats.advance(dt);
+        self.melee.advance(dt);
+        self.hurt_timer = (self.hurt_timer - dt).max(0.0);
+        self.respawn_timer = (self.respawn_timer - dt).max(0.0);
+
+        let walk = Animation::is_walking(self.velocity) && self.is_alive();
+        if walk {
+            self.facing = Direction::from_velocity(self.velocity);
+        }
+        let action = if !self.is_alive() {
+            Action::Death
+        } else if self.hurt_timer > 0.0 {
+            Action::Hurt
+        } else if self.melee.is_swinging() {
+            Action::Attack
+        } else {
+            Action::None
+        };
+        self.animation.advance(self.facing, walk, action, dt);
     }
 
-    /// Apply input to the player character state, physics, etc.
-    pub fn apply_input(&mut self, input: &Input) {
-        if input.key_up {
-            self.velocity.y -= self.velocity_delta
+    /// Apply input to the player character state, physics, etc. for `dt` seconds.
+    /// A dead player character ignores any input.
+    pub fn apply_input(&mut self, input: &Input, dt: f32) {
+        if !self.is_alive() {
+            return;
         }
-        if input.key_down {
-            self.velocity.y += self.velocity_delta
+        let manual = InputAction::MOVEMENT.into_iter().any(|it| input.is_down(it));
+        if manual || input.move_stick != Vector2::zero() {
+            self.path.clear();
         }
-        if input.key_left {
-            self.velocity.x -= self.velocity_delta
+        self.follow_path(dt);
+
+        if input.move_stick != Vector2::zero() {
+            // analog: the speed follows how far the stick is pushed
+            self.velocity += input.move_stick * self.velocity_delta * dt;
+            let max = input.move_stick.magnitude().min(1.0) * self.velocity_max;
+            if self.velocity.magnitude() > max {
+                self.velocity = self.velocity.normalize_to(max);
+            }
+        } else {
+            if input.is_down(InputAction::MoveUp) {
+                self.velocity.y -= self.velocity_delta * dt
+            }
+            if input.is_down(InputAction::MoveDown) {
+                self.velocity.y += self.velocity_delta * dt
+            }
+            if input.is_down(InputAction::MoveLeft) {
+                self.velocity.x -= self.velocity_delta * dt
+            }
+            if input.is_down(InputAction::MoveRight) {
+                self.velocity.x += self.velocity_delta * dt
+            }
         }
-        if input.key_right {
-            self.velocity.x += self.velocity_delta
+        // one swing per press, holding the key down doesn't keep attacking
+        if input.just_pressed(InputAction::Attack) && self.hurt_timer <= 0.0 && self.melee.start() {
+            self.animation.restart();
         }
-        self.attack = input.key_space;
-    }
 
-    pub fn sync_level_collision(&mut self, collision: &crate::level::Collision) {
-        let ts = crate::level::MESH_XZ_COORD * 0.75;
-        let player = crate::geometry::Aabb::new(
-            Point2::new(self.position.x - ts, self.position.y - ts),
-            Point2::new(self.position.x + ts, self.position.y + ts),
-        );
-
-        collision.aabbs.iter().for_each(|aabb| {
-            let contact = aabb.check_contact(&player);
-            if contact.intersects {
-                let offset = contact.min_trans * contact.penetration;
-                self.position -= Vector2::new(offset.x, offset.y);
+        // an item is used once per key press, not for every tick the key is held down
+        for (slot, action) in InputAction::SLOTS.into_iter().enumerate() {
+            if input.just_pressed(action) {
+                match self.inventory.use_item(slot, &mut self.stats) {
+                    Ok(kind) => log::info!(
+                        "used {:?}, weapon: {:?}, armor: {:?}",
+                        kind,
+                        self.inventory.equipped(EquipSlot::Weapon),
+                        self.inventory.equipped(EquipSlot::Armor),
+                    ),
+                    Err(e) => log::info!("cannot use slot {}: {}", slot + 1, e),
+                }
             }
-        });
-    }
-}
-
-#[derive(Error, Debug)]
-pub enum PlayerError {
-    #[error("mesh error: {0}")]
-    Mesh(#[from] MeshError),
-}
-
-pub const SPAWN_POSITION: (f32, f32) = (1.75, 1.75);
-
-// --------------------------------------------------
-// --- MESH ---
-// --------------------------------------------------
-
-// Represents a player character mesh.
-pub struct Mesh {
-    frame: f32,
-    position: Point2<f32>,
-    direction: Direction,
-
-    texture_id: TextureID,
-    pub textures: [video::TextureGroup; TEX_COUNT],
-
-    pub vertex_buffer: Vec<wgpu::Buffer>,
-    #[allow(dead_code)]
-    vertex_count: Vec<u32>,
-
-    pub index_buffer: Vec<wgpu::Buffer>,
-    #[allow(dead_code)]
-    pub index_count: Vec<u32>,
-
-    pub buffer: wgpu::Buffer,
-    pub bind_group: wgpu::BindGroup,
-}
-
-impl Mesh {
-    /// Creates a new player character mesh instance.
-    pub fn new(video: &video::Video) -> Result<Self, MeshError> {
-        // textures
-        let texture_id = TextureID::Orc3Idle;
-        let mut textures = Vec::with_capacity(TEX_ID_LOOKUP.len());
-        for (idx, (sub_path, _, _)) in TEX_ID_LOOKUP.iter().enumerate() {
-            let bytes = &std::fs::read(format!("{}{}", TEX_PATH_PREFIX, sub_path))
-                .map_err(MeshError::ReadIO)?;
-            let label = format!("{:?}", TextureID::from_index(idx));
-            textures.push(video::TextureGroup::new(video, bytes, &label)?);
-        }
-        let textures = textures.try_into().map_err(|_| MeshError::ReadConvert)?;
-
-        // geometry -- vertices
-        let mut vertex_buffer = Vec::with_capacity(TEX_COLUMN_COUNTS.len());
-        let mut vertex_count = Vec::with_capacity(TEX_COLUMN_COUNTS.len());
-        for (idx, col_count) in TEX_COLUMN_COUNTS.iter().enumerate() {
-            let (vs, vsc) = Self::build_vertices(*col_count);
-            vertex_buffer.push(video.device.create_buffer_init(
-                &wgpu::util::BufferInitDescriptor {
-                    label: Some(&format!("player_mesh_vertex_buffer_{}", idx)),
-                    contents: bytemuck::cast_slice(&vs),
-                    usage: wgpu::BufferUsages::VERTEX,
-                },
-            ));
-            vertex_count.push(vsc);
         }
+    }
 
-        // geometry -- indices
-        let mut index_buffer = Vec::with_capacity(TEX_COLUMN_COUNTS.len());
-        let mut index_count = Vec::with_capacity(TEX_COLUMN_COUNTS.len());
-        for (idx, vsc) in vertex_count.iter().enumerate() {
-            let (is, isc) = Self::build_indices(*vsc);
-            index_buffer.push(video.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some(&format!("player_mesh_index_buffer_{}", idx)),
-                contents: bytemuck::cast_slice(&is),
-                usage: wgpu::BufferUsages::INDEX,
-            }));
-            index_count.push(isc);
-        }
+    /// Walk through the waypoints (e.g. found by [`crate::nav::Nav::path`]).
+    pub fn walk(&mut self, path: Vec<Point2<f32>>) {
+        self.path = VecDeque::from(path);
+        self.path_best = f32::MAX;
+        self.path_stuck_timer = 0.0;
+    }
 
-        // WGPU buffer and bind group
-        let buffer = video.device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("player_mesh_buffer"),
-            size: std::mem::size_of::<[crate::video::MatrixUniform; 1]>() as u64,
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-        let bind_group = video.device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("player_mesh_bind_group"),
-            layout: &video.bind_group_layouts[crate::video::BIND_GROUP_TRANSFORM as usize],
-            entries: &[wgpu::BindGroupEntry { binding: 0, resource: buffer.as_entire_binding() }],
-        });
-
-        Ok(Self {
-            frame: 0.0,
-            position: Point2::new(0.0, 0.0),
-            direction: Direction::Down,
-
-            texture_id,
-            textures,
-
-            vertex_buffer,
-            vertex_count,
-
-            index_buffer,
-            index_count,
-
-            buffer,
-            bind_group,
-        })
+    /// Whether the player character is walking (e.g. after a mouse click).
+    #[cfg(test)]
+    pub fn is_walking(&self) -> bool {
+        !self.path.is_empty()
     }
 
-    /// Build vertices vector to be used to create a new vertex buffer.
-    /// Internal helper for [`new`]. Short variable names are more
-    /// readable here in this specific function
-    fn build_vertices(col_count: u32) -> (Vec<crate::video::Vertex>, u32) {
-        let s = TEX_TILE_SIZE as f32;
-        let (w, h) = (col_count as f32 * s, TEX_ROW_COUNT as f32 * s);
-
-        let vertex_count = VERTS_PER_TILE * TEX_ROW_COUNT * col_count;
-        let mut vertices = Vec::with_capacity(vertex_count as usize);
-
-        for x in 0..col_count {
-            for y in 0..TEX_ROW_COUNT {
-                let (x, y) = (x as f32, y as f32);
-                vertices.push(crate::video::Vertex::new(
-                    (-VERT_XZ_COORD, VERT_Y_COORD, -VERT_XZ_COORD).into(),
-                    ((s * x) / w, (s * y) / h).into(),
-                ));
-                vertices.push(crate::video::Vertex::new(
-                    (-VERT_XZ_COORD, VERT_Y_COORD, VERT_XZ_COORD).into(),
-                    ((s * x) / w, (s * (y + 1.0)) / h).into(),
-                ));
-                vertices.push(crate::video::Vertex::new(
-                    (VERT_XZ_COORD, VERT_Y_COORD, VERT_XZ_COORD).into(),
-                    ((s * (x + 1.0)) / w, (s * (y + 1.0)) / h).into(),
-                ));
-                vertices.push(crate::video::Vertex::new(
-                    (VERT_XZ_COORD, VERT_Y_COORD, -VERT_XZ_COORD).into(),
-                    ((s * (x + 1.0)) / w, (s * y) / h).into(),
-                ));
+    /// Accelerate towards the next waypoint, braking before the last one.
+    /// The walk is given up when the player character gets stuck (e.g. against a wall).
+    fn follow_path(&mut self, dt: f32) {
+        let Some(&waypoint) = self.path.front() else {
+            return;
+        };
+        let distance = self.position.distance(waypoint);
+        if distance <= PATH_WAYPOINT_RADIUS {
+            self.path.pop_front();
+            self.path_best = f32::MAX;
+            self.path_stuck_timer = 0.0;
+            return;
+        }
+        if distance < self.path_best - PATH_PROGRESS {
+            self.path_best = distance;
+            self.path_stuck_timer = 0.0;
+        } else {
+            self.path_stuck_timer += dt;
+            if self.path_stuck_timer > PATH_STUCK_SECS {
+                log::info!("player stuck at {:?}, walk to {:?} cancelled", self.position, waypoint);
+                self.path.clear();
+                return;
             }
         }
 
-        (vertices, vertex_count)
-    }
-
-    /// Build indices vector to be used to create a new index buffer.
-    /// Internal helper for [`new`].
-    fn build_indices(vertex_count: u32) -> (Vec<u16>, u32) {
-        let index_count = vertex_count * INDS_PER_TILE;
-        let mut indices = Vec::with_capacity(index_count as usize);
-
-        for i in 0..(vertex_count / VERTS_PER_TILE) as u16 {
-            let t = TRI_INDS.iter().map(|x| *x + i * VERTS_PER_TILE as u16);
-            indices.extend(t);
+        let max = if self.path.len() == 1 {
+            self.velocity_max.min(distance * PATH_BRAKING)
+        } else {
+            self.velocity_max
+        };
+        self.velocity += (waypoint - self.position) / distance * self.velocity_delta * dt;
+        if self.velocity.magnitude() > max {
+            self.velocity = self.velocity.normalize_to(max);
         }
-
-        (indices, index_count)
     }
 
-    /// Advances internal mesh state changes. Such as animation, etc.
-    fn advance(&mut self, position: Point2<f32>, velocity: Vector2<f32>, attack: bool) {
-        // 1. update internal state
-        self.position = position;
-        self.direction = Direction::from_velocity(velocity);
-
-        // 2. proceed/progress tiles animation
-        self.frame += self.get_anim_speed();
-        if self.frame >= self.get_max_frame() {
-            self.frame = 0.0;
-        }
-
-        // 3. pick correct texture
-        let mut texture_id = if attack { TextureID::Orc3Attack } else { TextureID::Orc3Idle };
-        if velocity.magnitude2() > WALK_THRESHOLD {
-            texture_id = if attack { TextureID::Orc3WalkAttack } else { TextureID::Orc3Walk };
+    /// Apply a hit landed by an enemy, the player gets knocked back and loses the current swing.
+    pub fn take_hit(&mut self, hit: Hit) {
+        if self.god {
+            return;
         }
-        if self.texture_id != texture_id {
-            self.texture_id = texture_id;
-            self.frame = 0.0;
+        self.stats.take_damage((hit.damage - self.inventory.defense()).max(PLAYER_MIN_DAMAGE));
+        self.velocity += hit.knockback;
+        self.hurt_timer = ANIM_LOOP_SECS;
+        self.melee.cancel();
+        if !self.is_alive() {
+            log::info!("player died at {:?}", self.position);
+            self.respawn_timer = self.respawn_delay;
         }
     }
 
-    /// Render mesh with its current given state based on provided video instance and render pass.
-    pub fn render(&self, vid: &video::Video, rp: &mut wgpu::RenderPass) {
-        rp.set_bind_group(1, &self.bind_group, &[]);
-        rp.set_bind_group(2, &self.textures[self.texture_id.index()].bind_group, &[]);
-
-        let b = self.get_buffer();
-        rp.set_vertex_buffer(0, self.vertex_buffer[b].slice(..));
-        rp.set_index_buffer(self.index_buffer[b].slice(..), wgpu::IndexFormat::Uint16);
-
-        let m = video::MatrixUniform {
-            matrix: cgmath::Matrix4::from_translation(
-                (self.position.x, 0.0, self.position.y).into(),
-            )
-            .into(),
-        };
-        vid.queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&m.matrix));
-
-        let idx = (self.frame as u32 * VERTS_PER_TILE + self.get_texture_row()) * INDS_PER_TILE;
-        rp.draw_indexed(idx..idx + 6, 0, 0..1);
+    pub fn is_alive(&self) -> bool {
+        self.stats.is_alive()
     }
 
-    /// Little helper function.
-    fn get_anim_speed(&self) -> f32 {
-        TEX_ID_LOOKUP[self.texture_id.index()].1 as f32 / 4.0 * ANIM_SPEED
+    /// Put an item picked up from the floor into the inventory.
+    pub fn pick_item(&mut self, kind: ItemKind) -> Result<(), InventoryError> {
+        let slot = self.inventory.add(kind)?;
+        log::info!(
+            "picked up {:?} into slot {}, {} in total",
+            kind,
+            slot + 1,
+            self.inventory.count(kind)
+        );
+        Ok(())
     }
 
-    /// Little helper function.
-    fn get_max_frame(&self) -> f32 {
-        TEX_ID_LOOKUP[self.texture_id.index()].1 as f32
+    /// Checks whether the player character is dead long enough to be respawned.
+    pub fn is_respawn_ready(&self) -> bool {
+        !self.is_alive() && self.respawn_timer <= 0.0
     }
 
-    /// Little helper function.
-    fn get_buffer(&self) -> usize {
-        TEX_ID_LOOKUP[self.texture_id.index()].2 as usize
+    /// Bring the player character back to life at the given position.
+    pub fn respawn(&mut self, position: Point2<f32>) {
+        self.teleport(position);
+        self.stats.restore();
+        self.hurt_timer = 0.0;
+        self.facing = Direction::Down;
     }
 
-    /// Little helper function.
-    fn get_texture_row(&self) -> u32 {
-        match self.direction {
-            Direction::Up => 1,
-            Direction::Down => 0,
-            Direction::Left => 2,
-            Direction::Right => 3,
-        }
+    /// Direction the player character looks at, it's kept when the player stops.
+    pub fn facing(&self) -> Direction {
+        self.facing
     }
-}
-
-#[derive(Error, Debug)]
-pub enum MeshError {
-    #[error("read io error: {0}")]
-    ReadIO(#[from] std::io::Error),
 
-    #[error("read convert error")]
-    ReadConvert,
-
-    #[error("texture error: {0}")]
-    Texture(#[from] crate::video::TextureError),
-}
-
-// TODO: add Orc1 and Orc2 textures, after that there will be no need in `#[allow(...)]` below.
-#[allow(clippy::enum_variant_names)]
-#[derive(Debug, PartialEq)]
-enum TextureID {
-    Orc3Attack,
-    Orc3Death,
-    Orc3Hurt,
-    Orc3Idle,
-    Orc3Run,
-    Orc3RunAttack,
-    Orc3Walk,
-    Orc3WalkAttack,
-}
-
-impl TextureID {
-    /// Given an enum instance, provide index based on it.
-    fn index(&self) -> usize {
-        match *self {
-            Self::Orc3Attack => 0,
-            Self::Orc3Death => 1,
-            Self::Orc3Hurt => 2,
-            Self::Orc3Idle => 3,
-            Self::Orc3Run => 4,
-            Self::Orc3RunAttack => 5,
-            Self::Orc3Walk => 6,
-            Self::Orc3WalkAttack => 7,
-        }
+    /// Instantly move the player character to a new position, dropping any momentum.
+    pub fn teleport(&mut self, position: Point2<f32>) {
+        self.position = position;
+        self.prev_position = position;
+        self.velocity = Vector2::new(0.0, 0.0);
+        self.path.clear();
     }
 
-    /// Given index, provide an enum instance based on it.
-    fn from_index(index: usize) -> Self {
-        match index {
-            0 => Self::Orc3Attack,
-            1 => Self::Orc3Death,
-            2 => Self::Orc3Hurt,
-            3 => Self::Orc3Idle,
-            4 => Self::Orc3Run,
-            5 => Self::Orc3RunAttack,
-            6 => Self::Orc3Walk,
-            7 => Self::Orc3WalkAttack,
-            _ => panic!("invalid texture index"),
-        }
+    pub fn sync_level_collision(&mut self, collision: &crate::level::Collision) {
+        let half_size = crate::combat::BODY_HALF_SIZE;
+        (self.position, self.contact_normals) =
+            collision.push_out_with_normals(self.position, half_size);
     }
 }
 
-/// Tuple is a) the texture image file path, b) columns count in the image,
-/// and c) vertex/index buffer index to be used to draw it.
-const TEX_ID_LOOKUP: [(&str, u32, u32); TEX_COUNT] = [
-    ("attack/orc3_attack_full.png", 8, 0), // TextureID::Orc3Attack
-    ("death/orc3_death_full.png", 8, 0),   // TextureID::Orc3Death
-    ("hurt/orc3_hurt_full.png", 6, 1),     // TextureID::Orc3Hurt
-    ("idle/orc3_idle_full.png", 4, 2),     // TextureID::Orc3Idle
-    ("run/orc3_run_full.png", 8, 0),       // TextureID::Orc3Run
-    ("run_attack/orc3_run_attack_full.png", 8, 0), // TextureID::Orc3RunAttack
-    ("walk/orc3_walk_full.png", 6, 1),     // TextureID::Orc3Walk
-    ("walk_attack/orc3_walk_attack_full.png", 6, 1), // TextureID::Orc3WalkAttack
-];
-
-const TEX_COUNT: usize = 8;
-const TEX_PATH_PREFIX: &str = "./assets/orc/png/Orc3/orc3_";
-const TEX_COLUMN_COUNTS: [u32; 3] = [8, 6, 4];
-const TEX_ROW_COUNT: u32 = 4;
-const TEX_TILE_SIZE: u32 = 64;
-
-const VERTS_PER_TILE: u32 = 4;
-const INDS_PER_TILE: u32 = 6;
-
-/// Two triangles, every of 3 indices/vertices.
-const TRI_INDS: [u16; 6] = [0, 2, 3, 0, 1, 2];
-
-const VERT_XZ_COORD: f32 = 1.0;
-const VERT_Y_COORD: f32 = -0.25;
-
-const ANIM_SPEED: f32 = 0.15;
-const WALK_THRESHOLD: f32 = 0.000_005;
+pub const PLAYER_VELOCITY_DELTA: f32 = 36.0;
+pub const PLAYER_VELOCITY_MAX: f32 = 1.5;
+pub const PLAYER_HP: f32 = 100.0;
+pub const PLAYER_HP_REGEN: f32 = 1.0;
+pub const PLAYER_MP: f32 = 50.0;
+pub const PLAYER_MP_REGEN: f32 = 2.0;
+/// Every hit hurts a bit, no matter how good the armor is.
+const PLAYER_MIN_DAMAGE: f32 = 1.0;
+/// Time (in seconds) the dead player character lies on the floor before a respawn.
+pub const PLAYER_RESPAWN_DELAY: f32 = 3.0;
+pub const PLAYER_MELEE_DAMAGE: f32 = 25.0;
+const PLAYER_MELEE_KNOCKBACK: f32 = 3.0;
+const PLAYER_MELEE_COOLDOWN: f32 = 0.6;
+/// A waypoint is reached within the distance.
+const PATH_WAYPOINT_RADIUS: f32 = 0.05;
+/// The walk has to get closer to the next waypoint at least by the distance...
+const PATH_PROGRESS: f32 = 0.01;
+/// ...within the time (in seconds), otherwise the walk is given up.
+const PATH_STUCK_SECS: f32 = 0.5;
+/// Speed limit (per second) per unit of the distance to the last waypoint.
+const PATH_BRAKING: f32 = 4.0;
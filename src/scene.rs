@@ -1,35 +1,673 @@
+use crate::level::{Block, LevelState};
+use cgmath::MetricSpace;
+use std::collections::HashMap;
 use thiserror::Error;
 
+/// Game simulation state. Doesn't require a window or a GPU,
+/// rendering resources are attached separately with [`Scene::prepare_render`].
 pub struct Scene {
     pub observer: crate::observer::Observer,
     pub level: crate::level::Level,
     pub player: crate::player::Player,
+    pub enemies: Vec<crate::enemy::Enemy>,
+    pub items: Vec<crate::item::Item>,
+    pub sheet: Option<crate::sprite::Sheet>,
+    pub item_sheet: Option<crate::item::Sheet>,
+    pub debug: crate::debug::Overlay,
+    pub cursor: crate::cursor::Cursor,
+    pub hud: crate::hud::Hud,
+    /// Screen space text, queued anew every frame.
+    pub text: crate::text::Text,
+
+    /// Events of the last tick, see [`Event`].
+    pub events: Vec<Event>,
+
+    /// Tunables applied to the player and every spawned enemy.
+    pub gameplay: crate::config::GameplayConfig,
+
+    /// State of the levels the player has left (by level id), restored on the way back.
+    pub visited: HashMap<String, LevelState>,
+    /// Level which failed to load from the stairs the player stands on, not tried again
+    /// until the player steps off the stairs.
+    failed_exit: Option<String>,
 }
 
 impl Scene {
-    pub fn new(video: &crate::video::Video) -> Result<Self, SceneError> {
-        let observer = crate::observer::Observer::new(video);
-        let level = crate::level::Level::new(video)?;
-        let player = crate::player::Player::new(video)?;
-        Ok(Self { observer, level, player })
+    /// Creates a new scene with the default gameplay tunables.
+    #[cfg(test)]
+    pub fn new(level_id: &str) -> Result<Self, SceneError> {
+        Self::with_config(level_id, crate::config::GameplayConfig::default())
     }
 
-    pub fn advance(&mut self, video: &crate::video::Video, input: &crate::input::Input) {
-        self.observer.update(video);
-        self.observer.follow_player(&self.player);
-        self.observer.apply_input(input);
+    /// Creates a new scene with the given gameplay tunables.
+    pub fn with_config(
+        level_id: &str,
+        gameplay: crate::config::GameplayConfig,
+    ) -> Result<Self, SceneError> {
+        let level = crate::level::Level::new(level_id)?;
+        log::info!("level {:?} ({}) loaded", level.name, level.id);
+        let observer = crate::observer::Observer::new(level.player_spawn());
+        let mut player = crate::player::Player::new(level.player_spawn());
+        player.apply_config(&gameplay);
+        let enemies = Self::spawn_enemies(&level, &gameplay);
+        let items = crate::item::Item::spawn_all(&level);
+        Ok(Self {
+            observer,
+            level,
+            player,
+            enemies,
+            items,
+            sheet: None,
+            item_sheet: None,
+            debug: crate::debug::Overlay::new(),
+            cursor: crate::cursor::Cursor::new(),
+            hud: crate::hud::Hud::new(),
+            text: crate::text::Text::new(),
+            events: vec![],
+            gameplay,
+            visited: HashMap::new(),
+            failed_exit: None,
+        })
+    }
+
+    /// Advance the simulation by a single fixed tick of [`TICK_SECS`] seconds.
+    /// The presses and releases of the input are consumed by the tick.
+    pub fn advance(&mut self, input: &mut crate::input::Input) {
+        let dt = TICK_SECS;
+        self.events.clear();
+        if input.just_pressed(crate::input::Action::OpenInventory) {
+            self.hud.inventory_open = !self.hud.inventory_open;
+        }
+        if input.just_pressed(crate::input::Action::Interact) {
+            self.interact();
+        }
+
+        self.observer.begin_tick();
+        self.observer.follow_player(&self.player, dt);
+        self.observer.apply_input(input, dt);
 
-        self.player.advance();
-        self.player.apply_input(input);
+        self.player.advance(dt);
+        self.player.apply_input(input, dt);
         self.player.sync_level_collision(&self.level.collision);
+        self.level.update_fov(self.player.position, SIGHT_RADIUS);
+        self.level.lighting.advance(dt);
+
+        let target = self.player.is_alive().then_some(self.player.position);
+        for enemy in self.enemies.iter_mut() {
+            enemy.navigate(target, &mut self.level.nav);
+            enemy.advance(target, dt);
+            enemy.sync_level_collision(&self.level.collision);
+        }
+        crate::combat::resolve(&mut self.player, &mut self.enemies);
+
+        if self.player.is_alive() {
+            let body = crate::combat::body(self.player.position);
+            for item in crate::item::pick_up(&mut self.items, &body) {
+                if self.player.pick_item(item.kind).is_ok() {
+                    self.events
+                        .push(Event::ItemPicked { kind: item.kind, position: item.position });
+                    self.level.lighting.flash(PICKUP_FLASH.at(item.position), PICKUP_FLASH_SECS);
+                } else {
+                    // no room in the inventory, the item stays on the floor
+                    self.items.push(item);
+                }
+            }
+        }
+
+        if self.player.is_respawn_ready() {
+            let spawn = self.level.player_spawn();
+            log::info!("player respawned at {:?}", spawn);
+            self.player.respawn(spawn);
+            self.observer.teleport(spawn);
+        }
+
+        let exit = self.level.exit_at(self.player.position).map(str::to_string);
+        if exit.is_none() {
+            self.failed_exit = None;
+        }
+        if let Some(level_id) = exit.filter(|it| self.failed_exit.as_ref() != Some(it)) {
+            if let Err(e) = self.load_level(&level_id) {
+                log::error!("cannot load level {:?}: {}", level_id, e);
+                self.events.push(Event::LevelLoadFailed { position: self.player.position });
+                self.failed_exit = Some(level_id);
+            }
+        }
+        self.level.update_lighting(&[self.player_light()]);
+        input.end_tick();
+    }
+
+    /// Create missing rendering resources (e.g. after a level swap) and update the GPU state.
+    /// `alpha` (in `0..1`) is how far the rendered frame is between the last two ticks.
+    pub fn prepare_render(
+        &mut self,
+        video: &crate::video::Video,
+        alpha: f32,
+    ) -> Result<(), SceneError> {
+        self.observer.prepare_render(video, alpha);
+        self.level.prepare_render(video)?;
+        self.player.prepare_render(video, alpha);
+        self.enemies.iter_mut().for_each(|e| e.prepare_render(video, alpha));
+        self.items.iter_mut().for_each(|it| it.prepare_render(video));
+        if self.sheet.is_none() {
+            self.sheet = Some(crate::sprite::Sheet::new(video)?);
+        }
+        if self.item_sheet.is_none() {
+            self.item_sheet = Some(crate::item::Sheet::new(video)?);
+        }
+        self.cursor.update(&self.observer, &self.level);
+        self.cursor.prepare_render(video);
+        if self.debug.enabled {
+            self.debug.build(&self.level, &self.player, &self.enemies, &self.items);
+            self.debug.prepare_render(video);
+        }
+        self.hud.prepare_render(video);
+        self.text.prepare_render(video)?;
+        Ok(())
+    }
+
+    /// Send the player walking to the free block under the mouse cursor, around the walls.
+    /// Returns whether there is such a block and it can be reached.
+    pub fn walk_to_cursor(&mut self) -> bool {
+        let tile = self.cursor.world_position(&self.observer).and_then(|it| self.level.tile_at(it));
+        let Some(tile) = tile.filter(|it| self.level.block(it.x, it.y) == Some(Block::Free)) else {
+            return false;
+        };
+        if !self.player.is_alive() {
+            return false;
+        }
+        let target = cgmath::Point2::new(tile.x as f32, tile.y as f32);
+        match self.level.nav.path(self.player.position, target) {
+            Some(path) => self.player.walk(path),
+            None => return false,
+        }
+        true
+    }
+
+    /// Open or close the door nearest to the spot in front of the player, within reach.
+    /// A door cannot be closed while someone stands in the doorway.
+    /// Returns whether a door has been toggled.
+    pub fn interact(&mut self) -> bool {
+        if !self.player.is_alive() {
+            return false;
+        }
+        let front = self.player.position + self.player.facing().to_vector() * INTERACT_FRONT;
+        let position =
+            |tile: cgmath::Point2<usize>| cgmath::Point2::new(tile.x as f32, tile.y as f32);
+        let door = self
+            .level
+            .doors()
+            .filter(|(tile, _)| position(*tile).distance(self.player.position) <= INTERACT_REACH)
+            .min_by(|a, b| {
+                position(a.0).distance2(front).total_cmp(&position(b.0).distance2(front))
+            });
+        let Some((tile, open)) = door else {
+            return false;
+        };
+        let doorway =
+            crate::geometry::Aabb::from_center(position(tile), crate::level::MESH_XZ_COORD);
+        let bodies = std::iter::once(self.player.position)
+            .chain(self.enemies.iter().filter(|it| it.is_alive()).map(|it| it.position));
+        if open && bodies.map(crate::combat::body).any(|it| it.intersects(&doorway)) {
+            return false;
+        }
+        self.level.set_door(tile, !open);
+        self.events.push(Event::DoorToggled { position: tile, open: !open });
+        true
+    }
+
+    /// Light carried by the player, configured by the gameplay tunables.
+    pub fn player_light(&self) -> crate::light::Light {
+        let spec = crate::light::LightSpec {
+            color: self.gameplay.player_light_color,
+            radius: self.gameplay.player_light_radius,
+        };
+        spec.at(self.player.position)
+    }
+
+    /// Enemies placed by the level, configured by the gameplay tunables.
+    fn spawn_enemies(
+        level: &crate::level::Level,
+        gameplay: &crate::config::GameplayConfig,
+    ) -> Vec<crate::enemy::Enemy> {
+        let mut enemies = crate::enemy::Enemy::spawn_all(level);
+        enemies.iter_mut().for_each(|it| it.apply_config(gameplay));
+        enemies
+    }
+
+    /// Start a new frame of the screen space text with the HUD.
+    /// Anything queued afterwards (e.g. the console) is drawn on top of it.
+    pub fn build_hud(&mut self) {
+        self.text.clear();
+        self.hud.build(&self.observer, &self.level, &self.player, &mut self.text);
+    }
+
+    /// Swap the current level with a new one. The state of the current level is kept, and
+    /// restored if the player has been on the new one before. The player arrives next to
+    /// the stairs leading back, or onto the spawn point of the new level.
+    pub fn load_level(&mut self, level_id: &str) -> Result<(), SceneError> {
+        let level = crate::level::Level::new(level_id)?;
+        let state = self.level_state();
+        let previous = std::mem::replace(&mut self.level, level);
+        self.visited.insert(previous.id.clone(), state);
+        log::info!("level {:?} ({}) loaded", self.level.name, self.level.id);
+
+        let state = self.visited.remove(&self.level.id).unwrap_or_default();
+        self.level.restore(&state);
+        let arrival = self.level.arrival_from(&previous.id);
+        self.player.teleport(arrival);
+        self.observer.teleport(arrival);
+        self.enemies = Self::spawn_enemies(&self.level, &self.gameplay);
+        self.enemies.retain(|it| it.spawn.is_none_or(|s| !state.killed.contains(&s)));
+        self.items = crate::item::Item::spawn_all(&self.level);
+        self.items.retain(|it| it.spawn.is_none_or(|s| !state.picked.contains(&s)));
+        Ok(())
+    }
+
+    /// Current state of the level: the doors, the explored blocks, and the spawn markers
+    /// of the killed enemies and the picked items.
+    pub fn level_state(&self) -> LevelState {
+        let spawns = |kind: fn(crate::level::SpawnKind) -> bool| {
+            self.level.spawns.iter().filter(move |s| kind(s.kind)).map(|s| s.position)
+        };
+        let alive: Vec<_> =
+            self.enemies.iter().filter(|it| it.is_alive()).filter_map(|it| it.spawn).collect();
+        let lying: Vec<_> = self.items.iter().filter_map(|it| it.spawn).collect();
+        let enemy = |it| matches!(it, crate::level::SpawnKind::Enemy(_));
+        let item = |it| matches!(it, crate::level::SpawnKind::Item(_));
+        LevelState {
+            killed: spawns(enemy).filter(|it| !alive.contains(it)).collect(),
+            picked: spawns(item).filter(|it| !lying.contains(it)).collect(),
+            ..self.level.state()
+        }
     }
 }
 
+/// Something which happened in the game world during a tick, the rest of the game may react to it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event {
+    ItemPicked {
+        kind: crate::item::ItemKind,
+        position: cgmath::Point2<f32>,
+    },
+    DoorToggled {
+        position: cgmath::Point2<usize>,
+        open: bool,
+    },
+    /// The stairs the player stepped on lead to a level which can't be loaded.
+    LevelLoadFailed {
+        position: cgmath::Point2<f32>,
+    },
+}
+
+/// Simulation runs at the fixed rate of 60 ticks per second, independently of the frame rate.
+pub const TICK_SECS: f32 = 1.0 / 60.0;
+/// The player sees the blocks within the radius (unless a wall is in the way),
+/// they get explored (shown on the minimap).
+const SIGHT_RADIUS: f32 = 6.0;
+/// Picked items light up the area for a moment.
+const PICKUP_FLASH: crate::light::LightSpec =
+    crate::light::LightSpec { color: [1.0, 0.9, 0.4], radius: 3.0 };
+const PICKUP_FLASH_SECS: f32 = 0.4;
+/// Doors farther from the player (center to center) cannot be opened or closed.
+const INTERACT_REACH: f32 = 1.5;
+/// The door in front of the player is preferred, this far in front of the player's center.
+const INTERACT_FRONT: f32 = 0.5;
+
 #[derive(Error, Debug)]
 pub enum SceneError {
     #[error("level error: {0}")]
     Level(#[from] crate::level::LevelError),
 
-    #[error("player error: {0}")]
-    Player(#[from] crate::player::PlayerError),
+    #[error("sprite sheet error: {0}")]
+    Sheet(#[from] crate::sprite::SheetError),
+
+    #[error("item sheet error: {0}")]
+    ItemSheet(#[from] crate::item::SheetError),
+
+    #[error("text error: {0}")]
+    Text(#[from] crate::text::TextError),
+}
+
+// --------------------------------------------------
+// --- TEST ---
+// --------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::{Event, Scene, PICKUP_FLASH_SECS, TICK_SECS};
+    use crate::{
+        combat::Hit,
+        config::GameplayConfig,
+        input::{Action, Input},
+        item::ItemKind,
+        level::Block,
+    };
+    use cgmath::{Point2, Vector2};
+
+    fn run(scene: &mut Scene, input: &mut Input, frames: u32) {
+        (0..frames).for_each(|_| scene.advance(input));
+    }
+
+    #[test]
+    fn test_scene_headless_spawn() {
+        let scene = Scene::new("level0").unwrap();
+        assert_eq!(scene.level.id, "level0");
+        assert_eq!(scene.player.position, Point2::new(2.0, 2.0));
+        assert!(scene.level.mesh.is_none() && scene.player.mesh.is_none());
+    }
+
+    #[test]
+    fn test_scene_player_moves() {
+        let mut scene = Scene::new("level0").unwrap();
+        let mut input = Input::new();
+        input.set(Action::MoveRight, true);
+        run(&mut scene, &mut input, 30); // half a second
+        assert!(scene.player.position.x > 2.5);
+        assert!((scene.player.position.y - 2.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_scene_player_analog_movement() {
+        let distance = |stick| {
+            let mut scene = Scene::new("level0").unwrap();
+            let mut input = Input::new();
+            input.move_stick = stick;
+            // the stick takes over the digital movement
+            input.set(Action::MoveLeft, true);
+            run(&mut scene, &mut input, 20);
+            scene.player.position.x - 2.0
+        };
+        let (full, half) = (distance(Vector2::new(1.0, 0.0)), distance(Vector2::new(0.5, 0.0)));
+        assert!(half > 0.0 && half < full * 0.6);
+    }
+
+    #[test]
+    fn test_scene_player_stopped_by_wall() {
+        let mut scene = Scene::new("level0").unwrap();
+        let mut input = Input::new();
+        input.set(Action::MoveUp, true);
+        run(&mut scene, &mut input, 300);
+        // the top wall ends at 0.5, the player box half size is 0.375
+        assert!(scene.player.position.y > 0.85 && scene.player.position.y < 0.95);
+
+        input.set(Action::MoveUp, false);
+        input.set(Action::MoveLeft, true);
+        run(&mut scene, &mut input, 300);
+        assert!(scene.player.position.x > 0.85 && scene.player.position.x < 0.95);
+    }
+
+    #[test]
+    fn test_scene_player_slides_across_collision_chunks() {
+        // the left wall of level1 is split in two collision boxes at the chunk border (y = 16)
+        let mut scene = Scene::new("level1").unwrap();
+        scene.player.teleport(Point2::new(1.0, 13.0));
+        let mut input = Input::new();
+        input.set(Action::MoveLeft, true);
+        input.set(Action::MoveDown, true);
+        run(&mut scene, &mut input, 30);
+        let mut y = scene.player.position.y;
+        while y < 19.0 {
+            run(&mut scene, &mut input, 1);
+            assert!(scene.player.position.y > y, "snagged at {:?}", scene.player.position);
+            assert!(scene.player.position.x > 0.85 && scene.player.position.x < 0.95);
+            y = scene.player.position.y;
+        }
+    }
+
+    #[test]
+    fn test_scene_walk_to_cursor() {
+        let mut scene = Scene::new("level0").unwrap();
+        scene.observer.handle_resize((1280, 720));
+        assert!(!scene.walk_to_cursor());
+
+        // a wall, 2 tiles above the player
+        scene.cursor.position = Some(Point2::new(640.0, 360.0 - 2.0 * 160.0));
+        assert!(!scene.walk_to_cursor());
+
+        // 3 tiles to the right of the player
+        scene.cursor.position = Some(Point2::new(640.0 + 3.0 * 160.0, 360.0));
+        assert!(scene.walk_to_cursor());
+        let mut input = Input::new();
+        run(&mut scene, &mut input, 240);
+        assert!(!scene.player.is_walking());
+        assert!((scene.player.position.x - 5.0).abs() < 0.1);
+        assert!((scene.player.position.y - 2.0).abs() < 0.1);
+
+        // around the walls, through the corridor (no enemies on the way)
+        scene.enemies.clear();
+        scene.observer.teleport(Point2::new(12.0, 10.0));
+        scene.cursor.position = Some(Point2::new(640.0, 360.0));
+        assert!(scene.walk_to_cursor());
+        run(&mut scene, &mut input, 900);
+        assert!(!scene.player.is_walking());
+        assert!((scene.player.position.x - 12.0).abs() < 0.1);
+        assert!((scene.player.position.y - 10.0).abs() < 0.1);
+
+        // any manual movement cancels the walk
+        scene.player.walk(vec![Point2::new(10.0, 11.0)]);
+        input.set(Action::MoveDown, true);
+        run(&mut scene, &mut input, 1);
+        assert!(!scene.player.is_walking());
+    }
+
+    #[test]
+    fn test_scene_walk_stuck() {
+        let mut scene = Scene::new("level0").unwrap();
+        // straight into the wall between the two halves of the level
+        scene.player.walk(vec![Point2::new(2.0, 11.0)]);
+        run(&mut scene, &mut Input::new(), 120);
+        assert!(!scene.player.is_walking());
+        assert!(scene.player.position.y < 3.5);
+    }
+
+    #[test]
+    fn test_scene_stairs_swap_level() {
+        let mut scene = Scene::new("level0").unwrap();
+        scene.player.teleport(Point2::new(18.0, 11.0));
+        run(&mut scene, &mut Input::new(), 1);
+        assert_eq!(scene.level.id, "level1");
+        // next to the stairs leading back up
+        assert_eq!(scene.player.position, Point2::new(33.0, 33.0));
+
+        scene.player.teleport(Point2::new(2.0, 33.0));
+        run(&mut scene, &mut Input::new(), 1);
+        assert_eq!(scene.level.id, "level2");
+        assert_eq!(scene.player.position, Point2::new(1.0, 2.0));
+        scene.player.teleport(Point2::new(2.0, 2.0));
+        run(&mut scene, &mut Input::new(), 1);
+        assert_eq!(scene.level.id, "level1");
+        assert_eq!(scene.player.position, Point2::new(1.0, 33.0));
+        scene.player.teleport(Point2::new(34.0, 33.0));
+        run(&mut scene, &mut Input::new(), 1);
+        assert_eq!(scene.level.id, "level0");
+        assert_eq!(scene.player.position, Point2::new(17.0, 11.0));
+    }
+
+    #[test]
+    fn test_scene_stairs_to_missing_level() {
+        let path = std::env::temp_dir().join("test_scene_stairs_to_missing_level.txt");
+        let level = "name: Dead End\nsize: 5x3\nstairs_down: missing\n---\n#####\n#@.>#\n#####\n";
+        std::fs::write(&path, level).unwrap();
+        let mut scene = Scene::new(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // tried once, not on every tick while the player stays on the stairs
+        let mut failures = 0;
+        let mut stand = |scene: &mut Scene, position, ticks| {
+            scene.player.teleport(position);
+            for _ in 0..ticks {
+                run(scene, &mut Input::new(), 1);
+                let failed =
+                    scene.events.iter().filter(|it| matches!(it, Event::LevelLoadFailed { .. }));
+                failures += failed.count();
+            }
+        };
+        stand(&mut scene, Point2::new(3.0, 1.0), 10);
+        assert_eq!(scene.level.id, path.to_str().unwrap());
+        assert_eq!(scene.failed_exit.as_deref(), Some("missing"));
+        // tried again after stepping off the stairs and back on
+        stand(&mut scene, Point2::new(1.0, 1.0), 1);
+        assert_eq!(scene.failed_exit, None);
+        stand(&mut scene, Point2::new(3.0, 1.0), 10);
+        assert_eq!(failures, 2);
+    }
+
+    #[test]
+    fn test_scene_door_interact() {
+        let mut scene = Scene::new("level0").unwrap();
+        scene.enemies.clear();
+        let mut input = Input::new();
+        assert_eq!(scene.level.door(Point2::new(10, 4)), Some(true));
+        assert!(!scene.interact());
+
+        scene.player.teleport(Point2::new(11.0, 5.2));
+        input.set(Action::Interact, true);
+        run(&mut scene, &mut input, 1);
+        assert_eq!(
+            scene.events,
+            [Event::DoorToggled { position: Point2::new(11, 4), open: false }]
+        );
+        assert_eq!(scene.level.block(11, 4), Some(Block::Wall));
+        assert_eq!(scene.level.door(Point2::new(10, 4)), Some(true));
+        // the closed door blocks the way, and the view
+        scene.player.teleport(Point2::new(11.0, 4.4));
+        run(&mut scene, &mut Input::new(), 1);
+        assert!(scene.player.position.y > 4.8);
+        assert!(!scene.level.is_visible(Point2::new(11.0, 3.0)));
+
+        assert!(scene.interact());
+        assert_eq!(scene.level.door(Point2::new(11, 4)), Some(true));
+        // nobody gets shut in the doorway
+        scene.player.teleport(Point2::new(11.0, 4.0));
+        assert!(!scene.interact());
+        assert_eq!(scene.level.door(Point2::new(11, 4)), Some(true));
+    }
+
+    #[test]
+    fn test_scene_level_state_kept() {
+        let mut scene = Scene::new("level0").unwrap();
+        let (enemies, items) = (scene.enemies.len(), scene.items.len());
+        scene.enemies[0].stats.hp = 0.0;
+        scene.player.teleport(Point2::new(11.0, 5.2));
+        assert!(scene.interact());
+        scene.player.teleport(Point2::new(10.0, 3.0));
+        run(&mut scene, &mut Input::new(), 1);
+        assert_eq!(scene.items.len(), items - 1);
+
+        scene.load_level("level1").unwrap();
+        scene.load_level("level0").unwrap();
+        assert_eq!(scene.enemies.len(), enemies - 1);
+        assert_eq!(scene.items.len(), items - 1);
+        assert_eq!(scene.level.door(Point2::new(11, 4)), Some(false));
+        assert!(scene.level.is_explored(10, 3));
+        assert_eq!(scene.visited.len(), 1);
+        assert!(scene.visited.contains_key("level1"));
+    }
+
+    #[test]
+    fn test_scene_gameplay_config() {
+        let gameplay =
+            GameplayConfig { player_max_hp: 250.0, enemy_max_hp: 7.0, ..Default::default() };
+        let mut scene = Scene::with_config("level0", gameplay).unwrap();
+        assert_eq!(scene.player.stats.max_hp, 250.0);
+        scene.player.teleport(Point2::new(18.0, 11.0));
+        run(&mut scene, &mut Input::new(), 1);
+        assert_eq!(scene.level.id, "level1");
+        assert!(!scene.enemies.is_empty());
+        assert!(scene.enemies.iter().all(|it| it.stats.max_hp == 7.0));
+    }
+
+    #[test]
+    fn test_scene_enemies_spawned_and_collide() {
+        let mut scene = Scene::new("level1").unwrap();
+        assert!(!scene.enemies.is_empty());
+        // keep the player far away, the enemies should never leave the level boundaries
+        scene.player.teleport(Point2::new(-100.0, -100.0));
+        run(&mut scene, &mut Input::new(), 1200);
+        for enemy in scene.enemies.iter() {
+            let (x, y) = (enemy.position.x, enemy.position.y);
+            assert!(x > 0.5 && y > 0.5 && x < 35.5 && y < 33.5);
+        }
+    }
+
+    #[test]
+    fn test_scene_generated_level() {
+        let mut scene = Scene::new("gen:caves:42").unwrap();
+        let mut input = Input::new();
+        input.set(Action::MoveDown, true);
+        input.set(Action::MoveRight, true);
+        run(&mut scene, &mut input, 600);
+        let (x, y) = (scene.player.position.x, scene.player.position.y);
+        assert!(x > 0.0 && y > 0.0 && x < 48.0 && y < 32.0);
+    }
+
+    #[test]
+    fn test_scene_player_death_and_respawn() {
+        let mut scene = Scene::new("level0").unwrap();
+        scene.player.teleport(Point2::new(10.0, 7.0));
+        let knockback = Vector2::new(0.0, 0.0);
+        scene.player.take_hit(Hit { damage: 1000.0, knockback });
+        assert!(!scene.player.is_alive());
+
+        let mut input = Input::new();
+        input.set(Action::MoveRight, true);
+        run(&mut scene, &mut input, 60);
+        assert!(!scene.player.is_alive());
+        assert_eq!(scene.player.position, Point2::new(10.0, 7.0));
+
+        run(&mut scene, &mut Input::new(), 180);
+        assert!(scene.player.is_alive());
+        assert_eq!(scene.player.position, Point2::new(2.0, 2.0));
+        assert_eq!(scene.player.stats.hp, scene.player.stats.max_hp);
+    }
+
+    #[test]
+    fn test_scene_item_picked_up() {
+        let mut scene = Scene::new("level0").unwrap();
+        let coins = scene.items.len();
+        scene.player.teleport(Point2::new(10.0, 3.0));
+        run(&mut scene, &mut Input::new(), 1);
+        assert_eq!(scene.items.len(), coins - 1);
+        assert!(matches!(scene.events[0], Event::ItemPicked { kind: ItemKind::Coin, .. }));
+        assert_eq!(scene.player.inventory.count(ItemKind::Coin), 1);
+
+        run(&mut scene, &mut Input::new(), 1);
+        assert!(scene.events.is_empty());
+    }
+
+    #[test]
+    fn test_scene_lighting() {
+        let mut scene = Scene::new("level0").unwrap();
+        run(&mut scene, &mut Input::new(), 1);
+        let ambient = scene.level.lighting.ambient;
+        let spawn = scene.level.player_spawn();
+        assert!(scene.level.light_at(spawn)[0] > ambient[0]);
+        let lit = scene.level.light_at(Point2::new(4.0, 2.0));
+
+        // the player light goes along with the player
+        scene.player.teleport(Point2::new(10.0, 6.0));
+        run(&mut scene, &mut Input::new(), 1);
+        assert!(scene.level.light_at(Point2::new(10.0, 6.0))[0] > ambient[0]);
+        assert!(scene.level.light_at(Point2::new(4.0, 2.0))[2] < lit[2]);
+
+        // picked items flash for a moment
+        scene.player.teleport(Point2::new(10.0, 3.0));
+        run(&mut scene, &mut Input::new(), 1);
+        let flash = scene.level.light_at(Point2::new(10.0, 3.0));
+        run(&mut scene, &mut Input::new(), (PICKUP_FLASH_SECS / TICK_SECS) as u32 + 1);
+        assert!(scene.level.light_at(Point2::new(10.0, 3.0))[2] < flash[2]);
+    }
+
+    #[test]
+    fn test_scene_inventory_toggled_once_per_press() {
+        let mut scene = Scene::new("level0").unwrap();
+        let mut input = Input::new();
+        input.set(Action::OpenInventory, true);
+        run(&mut scene, &mut input, 10);
+        assert!(scene.hud.inventory_open);
+        input.set(Action::OpenInventory, false);
+        input.set(Action::OpenInventory, true);
+        run(&mut scene, &mut input, 1);
+        assert!(!scene.hud.inventory_open);
+    }
 }
@@ -4,20 +4,23 @@ struct ObserverUniform {
 @group(0) @binding(0)
 var<uniform> observer: ObserverUniform;
 
-struct MatrixUniform {
+struct TransformUniform {
     matrix: mat4x4<f32>,
+    tint: vec4<f32>,
 };
 @group(1) @binding(0)
-var<uniform> transform: MatrixUniform;
+var<uniform> transform: TransformUniform;
 
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) tex_coords: vec2<f32>,
+    @location(2) tint: vec3<f32>,
 };
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) tex_coords: vec2<f32>,
+    @location(1) tint: vec3<f32>,
 };
 
 @vertex
@@ -26,6 +29,7 @@ fn vs_main(
 ) -> VertexOutput {
     var out: VertexOutput;
     out.tex_coords = in.tex_coords;
+    out.tint = in.tint * transform.tint.rgb;
     out.clip_position = observer.view_proj * transform.matrix * vec4<f32>(in.position, 1.0);
     return out;
 }
@@ -39,5 +43,6 @@ var s_diffuse: sampler;
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     // Note: might be useful for quick debugging.
     // return vec4<f32>(1.0, 0.0, 0.0, 1.0);
-    return textureSample(t_diffuse, s_diffuse, in.tex_coords);
+    // the tint is the light (or the fog of war), the alpha is kept
+    return textureSample(t_diffuse, s_diffuse, in.tex_coords) * vec4<f32>(in.tint, 1.0);
 }
@@ -0,0 +1,382 @@
+use crate::{geometry::Direction, video};
+use cgmath::{InnerSpace, Point2, Vector2};
+use thiserror::Error;
+use wgpu::util::DeviceExt;
+
+// --------------------------------------------------
+// --- ANIMATION ---
+// --------------------------------------------------
+
+/// Represents a character animation state, i.e. which frame of which texture to draw.
+/// Doesn't depend on any rendering resources, see [`Sheet`] and [`Mesh`] for these.
+pub struct Animation {
+    frame: f32,
+    /// Interpolated position to draw the sprite at.
+    pub position: Point2<f32>,
+    direction: Direction,
+    texture_id: TextureID,
+}
+
+impl Animation {
+    /// Creates a new character animation state.
+    pub fn new() -> Self {
+        Self {
+            frame: 0.0,
+            position: Point2::new(0.0, 0.0),
+            direction: Direction::Down,
+            texture_id: TextureID::Orc3Idle,
+        }
+    }
+
+    /// Advances internal animation state changes by `dt` seconds.
+    pub fn advance(&mut self, direction: Direction, walk: bool, action: Action, dt: f32) {
+        // 1. update internal state
+        self.direction = direction;
+
+        // 2. proceed/progress tiles animation, death is the only one which doesn't loop
+        self.frame += self.get_anim_speed() * dt;
+        if self.frame >= self.get_max_frame() {
+            let death = self.texture_id == TextureID::Orc3Death;
+            self.frame = if death { self.get_max_frame() - 1.0 } else { 0.0 };
+        }
+
+        // 3. pick correct texture
+        let texture_id = match (action, walk) {
+            (Action::Death, _) => TextureID::Orc3Death,
+            (Action::Hurt, _) => TextureID::Orc3Hurt,
+            (Action::Attack, false) => TextureID::Orc3Attack,
+            (Action::Attack, true) => TextureID::Orc3WalkAttack,
+            (Action::None, false) => TextureID::Orc3Idle,
+            (Action::None, true) => TextureID::Orc3Walk,
+        };
+        if self.texture_id != texture_id {
+            // switching between the attack textures keeps the swing progress
+            let progress = self.frame / self.get_max_frame();
+            let keep = self.texture_id.is_attack() && texture_id.is_attack();
+            self.texture_id = texture_id;
+            self.frame = if keep { progress * self.get_max_frame() } else { 0.0 };
+        }
+    }
+
+    /// Start the current animation from its first frame, e.g. on a new attack swing.
+    pub fn restart(&mut self) {
+        self.frame = 0.0;
+    }
+
+    /// Checks whether the current frame is a part of an attack swing which is able to hit.
+    pub fn is_hit_frame(&self) -> bool {
+        let frame = self.frame as u32;
+        match self.texture_id {
+            TextureID::Orc3Attack | TextureID::Orc3RunAttack => (2..=5).contains(&frame),
+            TextureID::Orc3WalkAttack => (1..=3).contains(&frame),
+            _ => false,
+        }
+    }
+
+    /// Checks whether a character moving with the given velocity should be animated as walking.
+    pub fn is_walking(velocity: Vector2<f32>) -> bool {
+        velocity.magnitude2() > WALK_THRESHOLD
+    }
+
+    /// Little helper function.
+    fn get_anim_speed(&self) -> f32 {
+        TEX_ID_LOOKUP[self.texture_id.index()].1 as f32 / 4.0 * ANIM_SPEED
+    }
+
+    /// Little helper function.
+    fn get_max_frame(&self) -> f32 {
+        TEX_ID_LOOKUP[self.texture_id.index()].1 as f32
+    }
+
+    /// Little helper function.
+    fn get_buffer(&self) -> usize {
+        TEX_ID_LOOKUP[self.texture_id.index()].2 as usize
+    }
+
+    /// Little helper function.
+    fn get_texture_row(&self) -> u32 {
+        match self.direction {
+            Direction::Up => 1,
+            Direction::Down => 0,
+            Direction::Left => 2,
+            Direction::Right => 3,
+        }
+    }
+}
+
+/// What a character is busy with, overrides the idle and walk animations.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    None,
+    Attack,
+    Hurt,
+    Death,
+}
+
+// --------------------------------------------------
+// --- SHEET ---
+// --------------------------------------------------
+
+/// Represents shared rendering resources of the character sprites: textures and geometry.
+/// Created once and used to draw every [`Mesh`].
+pub struct Sheet {
+    pub textures: [video::TextureGroup; TEX_COUNT],
+
+    pub vertex_buffer: Vec<wgpu::Buffer>,
+    #[allow(dead_code)]
+    vertex_count: Vec<u32>,
+
+    pub index_buffer: Vec<wgpu::Buffer>,
+    #[allow(dead_code)]
+    pub index_count: Vec<u32>,
+}
+
+impl Sheet {
+    /// Creates a new character sprite sheet instance.
+    pub fn new(video: &video::Video) -> Result<Self, SheetError> {
+        // textures
+        let mut textures = Vec::with_capacity(TEX_ID_LOOKUP.len());
+        for (idx, (sub_path, _, _)) in TEX_ID_LOOKUP.iter().enumerate() {
+            let bytes = &std::fs::read(format!("{}{}", TEX_PATH_PREFIX, sub_path))
+                .map_err(SheetError::ReadIO)?;
+            let label = format!("{:?}", TextureID::from_index(idx));
+            textures.push(video::TextureGroup::new(video, bytes, &label)?);
+        }
+        let textures = textures.try_into().map_err(|_| SheetError::ReadConvert)?;
+
+        // geometry -- vertices
+        let mut vertex_buffer = Vec::with_capacity(TEX_COLUMN_COUNTS.len());
+        let mut vertex_count = Vec::with_capacity(TEX_COLUMN_COUNTS.len());
+        for (idx, col_count) in TEX_COLUMN_COUNTS.iter().enumerate() {
+            let (vs, vsc) = Self::build_vertices(*col_count);
+            vertex_buffer.push(video.device.create_buffer_init(
+                &wgpu::util::BufferInitDescriptor {
+                    label: Some(&format!("sprite_sheet_vertex_buffer_{}", idx)),
+                    contents: bytemuck::cast_slice(&vs),
+                    usage: wgpu::BufferUsages::VERTEX,
+                },
+            ));
+            vertex_count.push(vsc);
+        }
+
+        // geometry -- indices
+        let mut index_buffer = Vec::with_capacity(TEX_COLUMN_COUNTS.len());
+        let mut index_count = Vec::with_capacity(TEX_COLUMN_COUNTS.len());
+        for (idx, vsc) in vertex_count.iter().enumerate() {
+            let (is, isc) = Self::build_indices(*vsc);
+            index_buffer.push(video.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(&format!("sprite_sheet_index_buffer_{}", idx)),
+                contents: bytemuck::cast_slice(&is),
+                usage: wgpu::BufferUsages::INDEX,
+            }));
+            index_count.push(isc);
+        }
+
+        Ok(Self { textures, vertex_buffer, vertex_count, index_buffer, index_count })
+    }
+
+    /// Build vertices vector to be used to create a new vertex buffer.
+    /// Internal helper for [`new`]. Short variable names are more
+    /// readable here in this specific function
+    fn build_vertices(col_count: u32) -> (Vec<crate::video::Vertex>, u32) {
+        let s = TEX_TILE_SIZE as f32;
+        let (w, h) = (col_count as f32 * s, TEX_ROW_COUNT as f32 * s);
+
+        let vertex_count = VERTS_PER_TILE * TEX_ROW_COUNT * col_count;
+        let mut vertices = Vec::with_capacity(vertex_count as usize);
+
+        for x in 0..col_count {
+            for y in 0..TEX_ROW_COUNT {
+                let (x, y) = (x as f32, y as f32);
+                vertices.push(crate::video::Vertex::new(
+                    (-VERT_XZ_COORD, VERT_Y_COORD, -VERT_XZ_COORD).into(),
+                    ((s * x) / w, (s * y) / h).into(),
+                ));
+                vertices.push(crate::video::Vertex::new(
+                    (-VERT_XZ_COORD, VERT_Y_COORD, VERT_XZ_COORD).into(),
+                    ((s * x) / w, (s * (y + 1.0)) / h).into(),
+                ));
+                vertices.push(crate::video::Vertex::new(
+                    (VERT_XZ_COORD, VERT_Y_COORD, VERT_XZ_COORD).into(),
+                    ((s * (x + 1.0)) / w, (s * (y + 1.0)) / h).into(),
+                ));
+                vertices.push(crate::video::Vertex::new(
+                    (VERT_XZ_COORD, VERT_Y_COORD, -VERT_XZ_COORD).into(),
+                    ((s * (x + 1.0)) / w, (s * y) / h).into(),
+                ));
+            }
+        }
+
+        (vertices, vertex_count)
+    }
+
+    /// Build indices vector to be used to create a new index buffer.
+    /// Internal helper for [`new`].
+    fn build_indices(vertex_count: u32) -> (Vec<u16>, u32) {
+        let index_count = vertex_count * INDS_PER_TILE;
+        let mut indices = Vec::with_capacity(index_count as usize);
+
+        for i in 0..(vertex_count / VERTS_PER_TILE) as u16 {
+            let t = TRI_INDS.iter().map(|x| *x + i * VERTS_PER_TILE as u16);
+            indices.extend(t);
+        }
+
+        (indices, index_count)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum SheetError {
+    #[error("read io error: {0}")]
+    ReadIO(#[from] std::io::Error),
+
+    #[error("read convert error")]
+    ReadConvert,
+
+    #[error("texture error: {0}")]
+    Texture(#[from] crate::video::TextureError),
+}
+
+// --------------------------------------------------
+// --- MESH ---
+// --------------------------------------------------
+
+/// Represents a single character instance on the screen: its own transform uniform.
+/// Textures and geometry come from the shared [`Sheet`].
+pub struct Mesh {
+    pub buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl Mesh {
+    /// Creates a new character mesh instance.
+    pub fn new(video: &video::Video, label: &str) -> Self {
+        let buffer = video.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(&format!("{}_mesh_buffer", label)),
+            size: std::mem::size_of::<crate::video::TransformUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = video.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&format!("{}_mesh_bind_group", label)),
+            layout: &video.bind_group_layouts[crate::video::BIND_GROUP_TRANSFORM as usize],
+            entries: &[wgpu::BindGroupEntry { binding: 0, resource: buffer.as_entire_binding() }],
+        });
+        Self { buffer, bind_group }
+    }
+
+    /// Render mesh with the given animation state based on provided video instance and render pass.
+    pub fn render(
+        &self,
+        vid: &video::Video,
+        rp: &mut wgpu::RenderPass,
+        sheet: &Sheet,
+        anim: &Animation,
+        tint: [f32; 3],
+    ) {
+        rp.set_bind_group(1, &self.bind_group, &[]);
+        rp.set_bind_group(2, &sheet.textures[anim.texture_id.index()].bind_group, &[]);
+
+        let b = anim.get_buffer();
+        rp.set_vertex_buffer(0, sheet.vertex_buffer[b].slice(..));
+        rp.set_index_buffer(sheet.index_buffer[b].slice(..), wgpu::IndexFormat::Uint16);
+
+        let m = video::TransformUniform::new(
+            cgmath::Matrix4::from_translation((anim.position.x, 0.0, anim.position.y).into()),
+            tint,
+        );
+        vid.queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[m]));
+
+        let idx = (anim.frame as u32 * VERTS_PER_TILE + anim.get_texture_row()) * INDS_PER_TILE;
+        rp.draw_indexed(idx..idx + 6, 0, 0..1);
+    }
+}
+
+// --------------------------------------------------
+// --- TEXTURES ---
+// --------------------------------------------------
+
+// TODO: add Orc1 and Orc2 textures, after that there will be no need in `#[allow(...)]` below.
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, PartialEq)]
+enum TextureID {
+    Orc3Attack,
+    Orc3Death,
+    Orc3Hurt,
+    Orc3Idle,
+    Orc3Run,
+    Orc3RunAttack,
+    Orc3Walk,
+    Orc3WalkAttack,
+}
+
+impl TextureID {
+    /// Given an enum instance, provide index based on it.
+    fn index(&self) -> usize {
+        match *self {
+            Self::Orc3Attack => 0,
+            Self::Orc3Death => 1,
+            Self::Orc3Hurt => 2,
+            Self::Orc3Idle => 3,
+            Self::Orc3Run => 4,
+            Self::Orc3RunAttack => 5,
+            Self::Orc3Walk => 6,
+            Self::Orc3WalkAttack => 7,
+        }
+    }
+
+    /// Checks whether the texture is one of the attack swings.
+    fn is_attack(&self) -> bool {
+        matches!(self, Self::Orc3Attack | Self::Orc3RunAttack | Self::Orc3WalkAttack)
+    }
+
+    /// Given index, provide an enum instance based on it.
+    fn from_index(index: usize) -> Self {
+        match index {
+            0 => Self::Orc3Attack,
+            1 => Self::Orc3Death,
+            2 => Self::Orc3Hurt,
+            3 => Self::Orc3Idle,
+            4 => Self::Orc3Run,
+            5 => Self::Orc3RunAttack,
+            6 => Self::Orc3Walk,
+            7 => Self::Orc3WalkAttack,
+            _ => panic!("invalid texture index"),
+        }
+    }
+}
+
+/// Tuple is a) the texture image file path, b) columns count in the image,
+/// and c) vertex/index buffer index to be used to draw it.
+const TEX_ID_LOOKUP: [(&str, u32, u32); TEX_COUNT] = [
+    ("attack/orc3_attack_full.png", 8, 0), // TextureID::Orc3Attack
+    ("death/orc3_death_full.png", 8, 0),   // TextureID::Orc3Death
+    ("hurt/orc3_hurt_full.png", 6, 1),     // TextureID::Orc3Hurt
+    ("idle/orc3_idle_full.png", 4, 2),     // TextureID::Orc3Idle
+    ("run/orc3_run_full.png", 8, 0),       // TextureID::Orc3Run
+    ("run_attack/orc3_run_attack_full.png", 8, 0), // TextureID::Orc3RunAttack
+    ("walk/orc3_walk_full.png", 6, 1),     // TextureID::Orc3Walk
+    ("walk_attack/orc3_walk_attack_full.png", 6, 1), // TextureID::Orc3WalkAttack
+];
+
+const TEX_COUNT: usize = 8;
+const TEX_PATH_PREFIX: &str = "./assets/orc/png/Orc3/orc3_";
+const TEX_COLUMN_COUNTS: [u32; 3] = [8, 6, 4];
+const TEX_ROW_COUNT: u32 = 4;
+const TEX_TILE_SIZE: u32 = 64;
+
+const VERTS_PER_TILE: u32 = 4;
+const INDS_PER_TILE: u32 = 6;
+
+/// Two triangles, every of 3 indices/vertices.
+const TRI_INDS: [u16; 6] = [0, 2, 3, 0, 1, 2];
+
+const VERT_XZ_COORD: f32 = 1.0;
+const VERT_Y_COORD: f32 = -0.25;
+
+/// Animation frames per second, for every 4 columns in the texture.
+const ANIM_SPEED: f32 = 9.0;
+/// Duration of a single animation loop (in seconds), e.g. a single attack swing.
+pub const ANIM_LOOP_SECS: f32 = 4.0 / ANIM_SPEED;
+/// Squared velocity (units per second) above which a character is walking.
+const WALK_THRESHOLD: f32 = 0.018;
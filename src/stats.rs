@@ -0,0 +1,92 @@
+// --------------------------------------------------
+// --- STATS ---
+// --------------------------------------------------
+
+/// Health and mana of a character. Regeneration is per second, it stops when the character dies.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stats {
+    pub hp: f32,
+    pub max_hp: f32,
+    pub hp_regen: f32,
+
+    pub mp: f32,
+    pub max_mp: f32,
+    pub mp_regen: f32,
+}
+
+impl Stats {
+    /// Creates new stats with full health and mana.
+    pub fn new(max_hp: f32, hp_regen: f32, max_mp: f32, mp_regen: f32) -> Self {
+        Self { hp: max_hp, max_hp, hp_regen, mp: max_mp, max_mp, mp_regen }
+    }
+
+    /// Regenerate health and mana for `dt` seconds.
+    pub fn advance(&mut self, dt: f32) {
+        if !self.is_alive() {
+            return;
+        }
+        self.hp = (self.hp + self.hp_regen * dt).min(self.max_hp);
+        self.mp = (self.mp + self.mp_regen * dt).min(self.max_mp);
+    }
+
+    /// Lose some health, it never goes below zero.
+    pub fn take_damage(&mut self, amount: f32) {
+        self.hp = (self.hp - amount).max(0.0);
+    }
+
+    /// Regain some health, up to the maximum. Dead characters can't be healed.
+    pub fn heal(&mut self, amount: f32) {
+        if self.is_alive() {
+            self.hp = (self.hp + amount).min(self.max_hp);
+        }
+    }
+
+    /// Regain some mana, up to the maximum.
+    pub fn restore_mp(&mut self, amount: f32) {
+        self.mp = (self.mp + amount).min(self.max_mp);
+    }
+
+    /// Bring health and mana back to their maximum values, e.g. on respawn.
+    pub fn restore(&mut self) {
+        self.hp = self.max_hp;
+        self.mp = self.max_mp;
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.hp > 0.0
+    }
+}
+
+// --------------------------------------------------
+// --- TEST ---
+// --------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::Stats;
+
+    #[test]
+    fn test_stats_regen_is_clamped() {
+        let mut stats = Stats::new(100.0, 2.0, 50.0, 5.0);
+        stats.take_damage(10.0);
+        stats.mp = 30.0;
+        stats.advance(1.0);
+        assert_eq!((stats.hp, stats.mp), (92.0, 35.0));
+        stats.advance(100.0);
+        assert_eq!((stats.hp, stats.mp), (100.0, 50.0));
+    }
+
+    #[test]
+    fn test_stats_death_and_restore() {
+        let mut stats = Stats::new(100.0, 2.0, 50.0, 5.0);
+        stats.take_damage(150.0);
+        assert_eq!(stats.hp, 0.0);
+        assert!(!stats.is_alive());
+        stats.advance(10.0);
+        assert!(!stats.is_alive());
+        stats.heal(50.0);
+        assert!(!stats.is_alive());
+        stats.restore();
+        assert_eq!((stats.hp, stats.mp), (100.0, 50.0));
+    }
+}
@@ -0,0 +1,308 @@
+use crate::video::{self, TextureGroup, Vertex};
+use cgmath::{Point2, Vector2};
+use thiserror::Error;
+
+// --------------------------------------------------
+// --- TEXT ---
+// --------------------------------------------------
+
+/// Screen space text renderer using a monospace bitmap font atlas.
+/// Text is queued every frame in pixel coordinates (origin in the top left corner of the window),
+/// independently of the [`crate::observer::Observer`] camera.
+pub struct Text {
+    vertices: Vec<Vertex>,
+    runs: Vec<Run>,
+    binding: Option<TextBinding>,
+}
+
+impl Text {
+    /// Creates a new empty text renderer. The GPU resources are not created here,
+    /// see [`Text::prepare_render`].
+    pub fn new() -> Self {
+        Self { vertices: vec![], runs: vec![], binding: None }
+    }
+
+    /// Forget all the queued text, usually called at the beginning of every frame.
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.runs.clear();
+    }
+
+    /// Queue a text to be drawn with its top left corner at the given position (in pixels).
+    /// Lines are separated by `\n`, `scale` multiplies the size of the glyphs.
+    pub fn queue(&mut self, text: &str, position: Point2<f32>, scale: f32, color: [f32; 4]) {
+        let start = self.vertices.len();
+        layout(text, position, scale, &mut self.vertices);
+        let glyphs = (self.vertices.len() - start) / VERTS_PER_GLYPH;
+        if glyphs > 0 {
+            let first = (start / VERTS_PER_GLYPH) as u32;
+            self.runs.push(Run { first, glyphs: glyphs as u32, color });
+        }
+    }
+
+    /// Create the GPU resources if they are not created yet and upload the queued text.
+    pub fn prepare_render(&mut self, video: &video::Video) -> Result<(), TextError> {
+        if self.binding.is_none() {
+            self.binding = Some(TextBinding::new(video)?);
+        }
+        let Some(binding) = self.binding.as_mut() else {
+            return Ok(());
+        };
+
+        binding.screen.update(video);
+        binding.reserve(video, self.vertices.len() / VERTS_PER_GLYPH, self.runs.len());
+        if !self.vertices.is_empty() {
+            video.queue.write_buffer(
+                &binding.vertex_buffer,
+                0,
+                bytemuck::cast_slice(&self.vertices),
+            );
+        }
+        for (run, style) in self.runs.iter().zip(binding.styles.iter()) {
+            video.queue.write_buffer(&style.buffer, 0, bytemuck::cast_slice(&run.color));
+        }
+        Ok(())
+    }
+
+    /// Render the queued text, the text pipeline must be set already.
+    pub fn render(&self, rp: &mut wgpu::RenderPass) {
+        let Some(binding) = &self.binding else {
+            return;
+        };
+        rp.set_bind_group(video::BIND_GROUP_OBSERVER, &binding.screen.bind_group, &[]);
+        rp.set_bind_group(video::BIND_GROUP_TEXTURE, &binding.font.bind_group, &[]);
+        rp.set_vertex_buffer(0, binding.vertex_buffer.slice(..));
+        rp.set_index_buffer(binding.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+
+        for (run, style) in self.runs.iter().zip(binding.styles.iter()) {
+            rp.set_bind_group(video::BIND_GROUP_TRANSFORM, &style.bind_group, &[]);
+            let first = run.first * INDICES_PER_GLYPH as u32;
+            rp.draw_indexed(first..first + run.glyphs * INDICES_PER_GLYPH as u32, 0, 0..1);
+        }
+    }
+}
+
+/// Size of the text in pixels: the longest line and the number of lines.
+pub fn measure(text: &str, scale: f32) -> Vector2<f32> {
+    let columns = text.lines().map(|it| it.chars().count()).max().unwrap_or(0);
+    let lines = text.lines().count();
+    Vector2::new(columns as f32 * GLYPH_WIDTH * scale, lines as f32 * GLYPH_HEIGHT * scale)
+}
+
+/// Glyphs drawn with the same color: a single draw call.
+struct Run {
+    first: u32,
+    glyphs: u32,
+    color: [f32; 4],
+}
+
+/// Push 4 vertices (a quad) per visible glyph of the text. Spaces only advance the cursor,
+/// characters missing in the atlas are drawn as [`GLYPH_UNKNOWN`].
+fn layout(text: &str, position: Point2<f32>, scale: f32, vertices: &mut Vec<Vertex>) {
+    let (w, h) = (GLYPH_WIDTH * scale, GLYPH_HEIGHT * scale);
+    let mut cursor = position;
+    for c in text.chars() {
+        match c {
+            '\n' => {
+                cursor = Point2::new(position.x, cursor.y + h);
+                continue;
+            }
+            ' ' | '\t' => {}
+            c => {
+                let (u, v) = glyph_texture_position(c);
+                let (x0, y0, x1, y1) = (cursor.x, cursor.y, cursor.x + w, cursor.y + h);
+                vertices.push(Vertex::new((x0, y0, 0.0).into(), (u, v).into()));
+                vertices.push(Vertex::new((x0, y1, 0.0).into(), (u, v + TEX_GLYPH_V).into()));
+                vertices.push(Vertex::new(
+                    (x1, y1, 0.0).into(),
+                    (u + TEX_GLYPH_U, v + TEX_GLYPH_V).into(),
+                ));
+                vertices.push(Vertex::new((x1, y0, 0.0).into(), (u + TEX_GLYPH_U, v).into()));
+            }
+        }
+        cursor.x += w;
+    }
+}
+
+/// Texture coordinates of the top left corner of the glyph in the atlas.
+fn glyph_texture_position(c: char) -> (f32, f32) {
+    let c = if (' '..=GLYPH_UNKNOWN).contains(&c) { c } else { GLYPH_UNKNOWN };
+    let i = c as u32 - ' ' as u32;
+    let (col, row) = (i % TEX_COLUMNS, i / TEX_COLUMNS);
+    (col as f32 * TEX_GLYPH_U, row as f32 * TEX_GLYPH_V)
+}
+
+/// Glyph size in pixels, at scale 1.
+pub const GLYPH_WIDTH: f32 = 12.0;
+pub const GLYPH_HEIGHT: f32 = 24.0;
+/// The last cell of the atlas (DEL) holds the "missing glyph" box.
+const GLYPH_UNKNOWN: char = '\u{7f}';
+const TEX_COLUMNS: u32 = 16;
+const TEX_ROWS: u32 = 6;
+const TEX_GLYPH_U: f32 = 1.0 / TEX_COLUMNS as f32;
+const TEX_GLYPH_V: f32 = 1.0 / TEX_ROWS as f32;
+const VERTS_PER_GLYPH: usize = 4;
+const INDICES_PER_GLYPH: usize = 6;
+
+#[derive(Error, Debug)]
+pub enum TextError {
+    #[error("font texture error: {0}")]
+    Texture(#[from] crate::video::TextureError),
+}
+
+// --------------------------------------------------
+// --- BINDING ---
+// --------------------------------------------------
+
+/// GPU resources of the text renderer. Vertex and index buffers grow when the queued text
+/// doesn't fit, every run of text gets its own color uniform.
+struct TextBinding {
+    font: TextureGroup,
+    screen: video::ScreenBinding,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    glyph_capacity: usize,
+    styles: Vec<Style>,
+}
+
+impl TextBinding {
+    fn new(video: &video::Video) -> Result<Self, TextError> {
+        let font = TextureGroup::new(video, include_bytes!("../assets/font/font.png"), "font")?;
+
+        let screen = video::ScreenBinding::new(video, "text");
+        let (vertex_buffer, index_buffer) = Self::create_buffers(video, TEXT_MIN_GLYPHS);
+        Ok(Self {
+            font,
+            screen,
+            vertex_buffer,
+            index_buffer,
+            glyph_capacity: TEXT_MIN_GLYPHS,
+            styles: vec![],
+        })
+    }
+
+    /// Make sure the given number of glyphs and runs fit into the buffers.
+    fn reserve(&mut self, video: &video::Video, glyphs: usize, runs: usize) {
+        if self.glyph_capacity < glyphs {
+            self.glyph_capacity = glyphs.next_power_of_two();
+            (self.vertex_buffer, self.index_buffer) =
+                Self::create_buffers(video, self.glyph_capacity);
+        }
+        while self.styles.len() < runs {
+            self.styles.push(Style::new(video));
+        }
+    }
+
+    /// Internal helper function, the indices never change so they are uploaded right away.
+    fn create_buffers(video: &video::Video, glyphs: usize) -> (wgpu::Buffer, wgpu::Buffer) {
+        let vertex_buffer = video.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("text_vertex_buffer"),
+            size: (glyphs * VERTS_PER_GLYPH * std::mem::size_of::<Vertex>()) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let indices: Vec<u32> = (0..glyphs as u32)
+            .flat_map(|i| [0, 2, 3, 0, 1, 2].map(|it| i * VERTS_PER_GLYPH as u32 + it))
+            .collect();
+        let index_buffer = video.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("text_index_buffer"),
+            size: (indices.len() * std::mem::size_of::<u32>()) as u64,
+            usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        video.queue.write_buffer(&index_buffer, 0, bytemuck::cast_slice(&indices));
+        (vertex_buffer, index_buffer)
+    }
+}
+
+/// Color uniform of a single run of text.
+struct Style {
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl Style {
+    fn new(video: &video::Video) -> Self {
+        let buffer = video.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("text_style_buffer"),
+            size: std::mem::size_of::<[f32; 4]>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = video.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("text_style_bind_group"),
+            layout: &video.bind_group_layouts[video::BIND_GROUP_TRANSFORM as usize],
+            entries: &[wgpu::BindGroupEntry { binding: 0, resource: buffer.as_entire_binding() }],
+        });
+        Self { buffer, bind_group }
+    }
+}
+
+const TEXT_MIN_GLYPHS: usize = 256;
+
+// --------------------------------------------------
+// --- TEST ---
+// --------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::{layout, measure, Text, GLYPH_HEIGHT, GLYPH_WIDTH, TEX_COLUMNS, TEX_ROWS};
+    use crate::video::Vertex;
+    use cgmath::Point2;
+    use image::GenericImageView;
+
+    /// Position and texture coordinates of the first vertex of every glyph.
+    fn corners(vertices: &[Vertex]) -> Vec<[f32; 8]> {
+        let floats: &[[f32; 8]] = bytemuck::cast_slice(vertices);
+        floats.iter().step_by(4).copied().collect()
+    }
+
+    #[test]
+    fn test_text_layout_multi_line() {
+        let mut vertices = vec![];
+        layout("ab c\nd", Point2::new(10.0, 20.0), 2.0, &mut vertices);
+        let actual: Vec<_> = corners(&vertices).iter().map(|v| (v[0], v[1])).collect();
+        let (w, h) = (GLYPH_WIDTH * 2.0, GLYPH_HEIGHT * 2.0);
+        let expected =
+            vec![(10.0, 20.0), (10.0 + w, 20.0), (10.0 + 3.0 * w, 20.0), (10.0, 20.0 + h)];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_text_measure() {
+        let actual = measure("abc\nd\nefgh", 2.0);
+        assert_eq!((actual.x, actual.y), (4.0 * GLYPH_WIDTH * 2.0, 3.0 * GLYPH_HEIGHT * 2.0));
+        assert_eq!(measure("", 1.0).x, 0.0);
+    }
+
+    #[test]
+    fn test_text_layout_texture_coordinates() {
+        let mut vertices = vec![];
+        layout("!0é", Point2::new(0.0, 0.0), 1.0, &mut vertices);
+        let actual: Vec<_> = corners(&vertices).iter().map(|v| (v[3], v[4])).collect();
+        let (u, v) = (1.0 / TEX_COLUMNS as f32, 1.0 / TEX_ROWS as f32);
+        // unknown characters use the last cell of the atlas
+        let expected = vec![(u, 0.0), (0.0, v), (15.0 * u, 5.0 * v)];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_text_runs() {
+        let mut text = Text::new();
+        text.queue("hi", Point2::new(0.0, 0.0), 1.0, [1.0; 4]);
+        text.queue(" \n ", Point2::new(0.0, 0.0), 1.0, [1.0; 4]);
+        text.queue("there", Point2::new(0.0, 0.0), 1.0, [0.5; 4]);
+        let runs: Vec<_> = text.runs.iter().map(|it| (it.first, it.glyphs)).collect();
+        assert_eq!(runs, vec![(0, 2), (2, 5)]);
+        text.clear();
+        assert!(text.runs.is_empty() && text.vertices.is_empty());
+    }
+
+    #[test]
+    fn test_text_font_atlas_size() {
+        let image = image::load_from_memory(include_bytes!("../assets/font/font.png")).unwrap();
+        let expected = (GLYPH_WIDTH as u32 * TEX_COLUMNS, GLYPH_HEIGHT as u32 * TEX_ROWS);
+        assert_eq!(image.dimensions(), expected);
+    }
+}
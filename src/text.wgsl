@@ -0,0 +1,44 @@
+struct ScreenUniform {
+    view_proj: mat4x4<f32>,
+};
+@group(0) @binding(0)
+var<uniform> screen: ScreenUniform;
+
+struct StyleUniform {
+    color: vec4<f32>,
+};
+@group(1) @binding(0)
+var<uniform> style: StyleUniform;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(
+    in: VertexInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.tex_coords = in.tex_coords;
+    out.color = style.color;
+    out.clip_position = screen.view_proj * vec4<f32>(in.position, 1.0);
+    return out;
+}
+
+@group(2) @binding(0)
+var t_diffuse: texture_2d<f32>;
+@group(2) @binding(1)
+var s_diffuse: sampler;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // the font atlas is white, only its alpha matters
+    return in.color * textureSample(t_diffuse, s_diffuse, in.tex_coords);
+}
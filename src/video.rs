@@ -2,7 +2,7 @@ use cgmath::{Point2, Point3};
 use image::{GenericImageView, ImageError};
 use std::{iter, sync::Arc};
 use thiserror::Error;
-use wgpu::{CreateSurfaceError, RequestDeviceError};
+use wgpu::RequestDeviceError;
 use winit::window::Window;
 
 // --------------------------------------------------
@@ -135,11 +135,21 @@ impl TextureGroup {
 pub struct Vertex {
     position: [f32; 3],
     tex_coords: [f32; 2],
+    /// Color the texture is multiplied by, white by default.
+    tint: [f32; 3],
 }
 
 impl Vertex {
     pub fn new(position: Point3<f32>, tex_coords: Point2<f32>) -> Self {
-        Self { position: position.into(), tex_coords: tex_coords.into() }
+        Self { position: position.into(), tex_coords: tex_coords.into(), tint: [1.0; 3] }
+    }
+
+    pub fn tint(&self) -> [f32; 3] {
+        self.tint
+    }
+
+    pub fn set_tint(&mut self, tint: [f32; 3]) {
+        self.tint = tint;
     }
 
     pub fn get_layout<'a>() -> wgpu::VertexBufferLayout<'a> {
@@ -157,6 +167,11 @@ impl Vertex {
                     shader_location: 1,
                     format: wgpu::VertexFormat::Float32x2,
                 },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 5]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
             ],
         }
     }
@@ -172,6 +187,57 @@ pub struct MatrixUniform {
     pub matrix: [[f32; 4]; 4],
 }
 
+/// Model transform of the main shader, with the color (e.g. the light) the mesh is multiplied by.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TransformUniform {
+    pub matrix: [[f32; 4]; 4],
+    /// RGB tint, the last component is padding.
+    pub tint: [f32; 4],
+}
+
+impl TransformUniform {
+    pub fn new(matrix: cgmath::Matrix4<f32>, tint: [f32; 3]) -> Self {
+        Self { matrix: matrix.into(), tint: [tint[0], tint[1], tint[2], 1.0] }
+    }
+}
+
+// --------------------------------------------------
+// --- SCREEN BINDING ---
+// --------------------------------------------------
+
+/// Orthographic projection of the whole window in pixels, origin in the top left corner.
+/// Bound in place of the observer for the screen space rendering (text, HUD).
+pub struct ScreenBinding {
+    buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl ScreenBinding {
+    pub fn new(video: &Video, label: &str) -> Self {
+        let buffer = video.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(&format!("{}_screen_buffer", label)),
+            size: std::mem::size_of::<MatrixUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = video.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&format!("{}_screen_bind_group", label)),
+            layout: &video.bind_group_layouts[BIND_GROUP_OBSERVER as usize],
+            entries: &[wgpu::BindGroupEntry { binding: 0, resource: buffer.as_entire_binding() }],
+        });
+        Self { buffer, bind_group }
+    }
+
+    /// Upload the projection matching the current size of the surface.
+    pub fn update(&self, video: &Video) {
+        let (width, height) = video.size();
+        let proj = cgmath::ortho(0.0, width as f32, height as f32, 0.0, -1.0, 1.0);
+        let m = MatrixUniform { matrix: (crate::observer::OPENGL_TO_WGPU_MATRIX * proj).into() };
+        video.queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[m]));
+    }
+}
+
 // --------------------------------------------------
 // --- VIDEO ---
 // --------------------------------------------------
@@ -187,27 +253,55 @@ pub struct Video<'a> {
     pub queue: wgpu::Queue,
     config: wgpu::SurfaceConfiguration,
     pipeline: Option<wgpu::RenderPipeline>,
+    debug_pipeline: Option<wgpu::RenderPipeline>,
+    hud_pipeline: Option<wgpu::RenderPipeline>,
+    text_pipeline: Option<wgpu::RenderPipeline>,
     pub bind_group_layouts: Vec<wgpu::BindGroupLayout>,
 }
 
 impl<'a> Video<'a> {
-    pub async fn new(window: Arc<Window>) -> Result<Video<'a>, VideoError> {
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::PRIMARY,
-            ..Default::default()
-        });
-
+    pub async fn new(
+        window: Arc<Window>,
+        graphics: &crate::config::GraphicsConfig,
+    ) -> Result<Video<'a>, VideoError> {
         let window_size = window.inner_size();
-        let surface = instance.create_surface(window)?;
 
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
-            .await
-            .ok_or(VideoError::RequestAdapter())?;
+        // the surface belongs to the instance, so each backend gets its own pair
+        let mut tried = vec![];
+        let mut selected = None;
+        for (backend, software) in graphics.adapter_attempts() {
+            let attempt = match software {
+                true => format!("{} (software)", backend.name()),
+                false => backend.name().to_string(),
+            };
+            let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+                backends: backend.to_wgpu(),
+                ..Default::default()
+            });
+            let surface = match instance.create_surface(Arc::clone(&window)) {
+                Ok(surface) => surface,
+                Err(e) => {
+                    log::warn!("{}: create surface error: {}", attempt, e);
+                    tried.push(attempt);
+                    continue;
+                }
+            };
+            let name = graphics.adapter.as_deref().filter(|_| !software);
+            match request_adapter(&instance, &surface, backend, name, software).await {
+                Some(adapter) => {
+                    selected = Some((instance, surface, adapter));
+                    break;
+                }
+                None => {
+                    log::warn!("{}: no suitable adapter", attempt);
+                    tried.push(attempt);
+                }
+            }
+        }
+        let (instance, surface, adapter) =
+            selected.ok_or_else(|| VideoError::RequestAdapter(tried.join(", ")))?;
+        let info = adapter.get_info();
+        log::info!("adapter {:?} ({:?}, {:?})", info.name, info.backend, info.device_type);
 
         let (device, queue) = adapter
             .request_device(
@@ -233,7 +327,11 @@ impl<'a> Video<'a> {
             format: surface_format,
             width: window_size.width,
             height: window_size.height,
-            present_mode: surface_caps.present_modes[0],
+            present_mode: if graphics.vsync {
+                wgpu::PresentMode::AutoVsync
+            } else {
+                wgpu::PresentMode::AutoNoVsync
+            },
             alpha_mode: surface_caps.alpha_modes[0],
             view_formats: vec![],
             desired_maximum_frame_latency: 2,
@@ -301,6 +399,9 @@ impl<'a> Video<'a> {
             queue,
             config,
             pipeline: None,
+            debug_pipeline: None,
+            hud_pipeline: None,
+            text_pipeline: None,
             bind_group_layouts,
         })
     }
@@ -313,6 +414,11 @@ impl<'a> Video<'a> {
         }
     }
 
+    /// Size of the surface in pixels.
+    pub fn size(&self) -> (u32, u32) {
+        (self.config.width, self.config.height)
+    }
+
     fn get_pipeline(&mut self) -> &wgpu::RenderPipeline {
         self.pipeline.get_or_insert_with(|| {
             let shader = self.device.create_shader_module(wgpu::include_wgsl!("shader.wgsl"));
@@ -364,6 +470,141 @@ impl<'a> Video<'a> {
         })
     }
 
+    /// Pipeline of the debug overlay: colored lines, only the observer bind group is used.
+    fn get_debug_pipeline(&mut self) -> &wgpu::RenderPipeline {
+        self.debug_pipeline.get_or_insert_with(|| {
+            Self::create_color_pipeline(
+                &self.device,
+                &self.config,
+                &self.bind_group_layouts,
+                wgpu::PrimitiveTopology::LineList,
+                "debug",
+            )
+        })
+    }
+
+    /// Pipeline of the HUD shapes: colored triangles, the observer bind group holds
+    /// the screen projection instead.
+    fn get_hud_pipeline(&mut self) -> &wgpu::RenderPipeline {
+        self.hud_pipeline.get_or_insert_with(|| {
+            Self::create_color_pipeline(
+                &self.device,
+                &self.config,
+                &self.bind_group_layouts,
+                wgpu::PrimitiveTopology::TriangleList,
+                "hud",
+            )
+        })
+    }
+
+    /// Internal helper: pipeline drawing colored vertices (see [`crate::debug::LineVertex`]).
+    fn create_color_pipeline(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        bind_group_layouts: &[wgpu::BindGroupLayout],
+        topology: wgpu::PrimitiveTopology,
+        label: &str,
+    ) -> wgpu::RenderPipeline {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("debug.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(&format!("{}_pipeline_layout", label)),
+            bind_group_layouts: &[&bind_group_layouts[BIND_GROUP_OBSERVER as usize]],
+            push_constant_ranges: &[],
+        });
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(&format!("{}_pipeline", label)),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[crate::debug::LineVertex::get_layout()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        })
+    }
+
+    /// Pipeline of the screen space text: the same bind group layouts as the main pipeline,
+    /// but the groups hold the screen projection, the text color and the font atlas.
+    fn get_text_pipeline(&mut self) -> &wgpu::RenderPipeline {
+        self.text_pipeline.get_or_insert_with(|| {
+            let shader = self.device.create_shader_module(wgpu::include_wgsl!("text.wgsl"));
+
+            let pipeline_layout =
+                self.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("text_pipeline_layout"),
+                    bind_group_layouts: &self.bind_group_layouts.iter().collect::<Vec<_>>(),
+                    push_constant_ranges: &[],
+                });
+
+            self.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("text_pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[Vertex::get_layout()],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: self.config.format,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: None,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    unclipped_depth: false,
+                    conservative: false,
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+                cache: None,
+            })
+        })
+    }
+
     pub fn render(&mut self, scene: &crate::scene::Scene) -> Result<(), wgpu::SurfaceError> {
         let output = self.surface.get_current_texture()?;
         let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
@@ -393,9 +634,54 @@ impl<'a> Video<'a> {
             });
             render_pass.set_pipeline(self.get_pipeline());
 
-            render_pass.set_bind_group(BIND_GROUP_OBSERVER, &scene.observer.bind_group, &[]);
-            scene.level.mesh.render(self, &mut render_pass);
-            scene.player.mesh.render(self, &mut render_pass);
+            // nothing can be drawn until the scene is prepared for rendering
+            if let Some(bind_group) = scene.observer.bind_group() {
+                render_pass.set_bind_group(BIND_GROUP_OBSERVER, bind_group, &[]);
+                if let Some(mesh) = &scene.level.mesh {
+                    mesh.render(self, &mut render_pass);
+                }
+                // anything out of the player's field of view is hidden by the fog of war,
+                // the rest is lit by the light at its position
+                if let Some(sheet) = &scene.item_sheet {
+                    for item in scene.items.iter().filter(|it| scene.level.is_visible(it.position))
+                    {
+                        if let Some(mesh) = &item.mesh {
+                            let light = scene.level.light_at(item.position);
+                            mesh.render(self, &mut render_pass, sheet, item, light);
+                        }
+                    }
+                }
+                if let Some(sheet) = &scene.sheet {
+                    let visible =
+                        |it: &&crate::enemy::Enemy| scene.level.is_visible(it.animation.position);
+                    for enemy in scene.enemies.iter().filter(visible) {
+                        if let Some(mesh) = &enemy.mesh {
+                            let light = scene.level.light_at(enemy.animation.position);
+                            mesh.render(self, &mut render_pass, sheet, &enemy.animation, light);
+                        }
+                    }
+                    if let Some(mesh) = &scene.player.mesh {
+                        let light = scene.level.light_at(scene.player.animation.position);
+                        mesh.render(self, &mut render_pass, sheet, &scene.player.animation, light);
+                    }
+                }
+                render_pass.set_pipeline(self.get_debug_pipeline());
+                render_pass.set_bind_group(BIND_GROUP_OBSERVER, bind_group, &[]);
+                scene.cursor.render(&mut render_pass);
+                if scene.debug.enabled {
+                    scene.debug.render(&mut render_pass);
+                }
+            }
+
+            // HUD and text go on top of everything, in the screen space
+            render_pass.set_pipeline(self.get_hud_pipeline());
+            scene.hud.render_shapes(&mut render_pass);
+            if let Some(sheet) = &scene.item_sheet {
+                render_pass.set_pipeline(self.get_pipeline());
+                scene.hud.render_icons(&mut render_pass, sheet);
+            }
+            render_pass.set_pipeline(self.get_text_pipeline());
+            scene.text.render(&mut render_pass);
         }
 
         self.queue.submit(iter::once(encoder.finish()));
@@ -404,13 +690,47 @@ impl<'a> Video<'a> {
     }
 }
 
+// --------------------------------------------------
+// --- ADAPTER ---
+// --------------------------------------------------
+
+/// Lists the adapters of all the backends available on this machine.
+pub fn list_adapters() -> Vec<wgpu::AdapterInfo> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::all(),
+        ..Default::default()
+    });
+    instance.enumerate_adapters(wgpu::Backends::all()).iter().map(|it| it.get_info()).collect()
+}
+
+/// Finds an adapter which can present to the surface: the one named (if any), otherwise
+/// the one `wgpu` prefers.
+async fn request_adapter(
+    instance: &wgpu::Instance,
+    surface: &wgpu::Surface<'_>,
+    backend: crate::config::Backend,
+    name: Option<&str>,
+    software: bool,
+) -> Option<wgpu::Adapter> {
+    if let Some(name) = name {
+        let name = name.to_lowercase();
+        return instance.enumerate_adapters(backend.to_wgpu()).into_iter().find(|it| {
+            it.get_info().name.to_lowercase().contains(&name) && it.is_surface_supported(surface)
+        });
+    }
+    instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            compatible_surface: Some(surface),
+            force_fallback_adapter: software,
+        })
+        .await
+}
+
 #[derive(Error, Debug)]
 pub enum VideoError {
-    #[error("create surface error: {0}")]
-    CreateSurface(#[from] CreateSurfaceError),
-
-    #[error("request adapter error")]
-    RequestAdapter(),
+    #[error("no suitable adapter, tried: {0}")]
+    RequestAdapter(String),
 
     #[error("request device error: {0}")]
     RequestDevice(#[from] RequestDeviceError),